@@ -0,0 +1,354 @@
+/*
+ * Forward mode automatic differentiation, evaluates an equation alongside its
+ * first (and optionally second) partial derivatives using dual numbers
+ */
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::Scope;
+use crate::parser::{native::NativeFunc, Operator, ParseNode};
+
+/// Value, gradient & hessian of an equation at a point
+#[derive(Debug, Clone)]
+pub struct Derivatives {
+    pub value: f32,
+    pub gradient: Vec<f32>,
+    pub hessian: Option<Vec<Vec<f32>>>,
+}
+
+/// Dual number carrying the partial derivatives of a value with respect to
+/// every differentiated variable, the hessian is stored row major & is left
+/// empty when second derivatives aren't tracked
+#[derive(Debug, Clone)]
+pub struct Dual {
+    pub val: f32,
+    pub grad: Vec<f32>,
+    pub hess: Vec<f32>,
+}
+
+impl Dual {
+    /// Dual number of a constant, which has no derivatives
+    pub fn constant(val: f32, vars: usize, hessian: bool) -> Self {
+        Self {
+            val,
+            grad: vec![0.; vars],
+            hess: if hessian {
+                vec![0.; vars * vars]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    /// Dual number of the i-th differentiated variable
+    pub fn variable(val: f32, i: usize, vars: usize, hessian: bool) -> Self {
+        let mut out = Self::constant(val, vars, hessian);
+        out.grad[i] = 1.;
+        out
+    }
+
+    fn vars(&self) -> usize {
+        self.grad.len()
+    }
+
+    /// Applies a function through the chain rule, given its value, first &
+    /// second derivative at self
+    pub fn chain(&self, f: f32, df: f32, ddf: f32) -> Self {
+        let n = self.vars();
+        let mut hess = vec![0.; self.hess.len()];
+
+        if !hess.is_empty() {
+            for i in 0..n {
+                for j in 0..n {
+                    hess[i * n + j] = df * self.hess[i * n + j] + ddf * self.grad[i] * self.grad[j];
+                }
+            }
+        }
+
+        Self {
+            val: f,
+            grad: self.grad.iter().map(|g| df * g).collect(),
+            hess,
+        }
+    }
+
+    pub fn recip(&self) -> Self {
+        let v = self.val;
+        self.chain(1. / v, -1. / (v * v), 2. / (v * v * v))
+    }
+
+    pub fn powf(&self, exponent: f32) -> Self {
+        let (v, e) = (self.val, exponent);
+
+        // skips terms with a 0 coefficient, which would be 0 * inf at v = 0
+        let df = if e == 0. { 0. } else { e * v.powf(e - 1.) };
+        let ddf = if e == 0. || e == 1. {
+            0.
+        } else {
+            e * (e - 1.) * v.powf(e - 2.)
+        };
+
+        self.chain(v.powf(e), df, ddf)
+    }
+
+    pub fn pow(&self, exponent: &Dual) -> Self {
+        // constant exponents use the power rule, which also works for
+        // negative bases
+        if exponent
+            .grad
+            .iter()
+            .chain(exponent.hess.iter())
+            .all(|d| *d == 0.)
+        {
+            return self.powf(exponent.val);
+        }
+
+        // a^b = exp(b * ln(a))
+        (exponent.clone() * self.ln()).exp()
+    }
+
+    pub fn exp(&self) -> Self {
+        let e = self.val.exp();
+        self.chain(e, e, e)
+    }
+
+    pub fn ln(&self) -> Self {
+        let v = self.val;
+        self.chain(v.ln(), 1. / v, -1. / (v * v))
+    }
+
+    pub fn sqrt(&self) -> Self {
+        let s = self.val.sqrt();
+        self.chain(s, 0.5 / s, -0.25 / (s * s * s))
+    }
+
+    /// Multiplies by a constant
+    pub fn scale(&self, by: f32) -> Self {
+        self.chain(self.val * by, by, 0.)
+    }
+}
+
+// Operator Overloads ---------------------------------------------------------
+
+impl Add for Dual {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self.val += rhs.val;
+        self.grad
+            .iter_mut()
+            .zip(rhs.grad)
+            .for_each(|(a, b)| *a += b);
+        self.hess
+            .iter_mut()
+            .zip(rhs.hess)
+            .for_each(|(a, b)| *a += b);
+        self
+    }
+}
+
+impl Sub for Dual {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Neg for Dual {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.scale(-1.)
+    }
+}
+
+impl Mul for Dual {
+    type Output = Self;
+
+    // product rule, (ab)'' = a''b + 2a'b' + ab''
+    fn mul(self, rhs: Self) -> Self::Output {
+        let n = self.vars();
+        let mut hess = vec![0.; self.hess.len()];
+
+        if !hess.is_empty() {
+            for i in 0..n {
+                for j in 0..n {
+                    hess[i * n + j] = self.val * rhs.hess[i * n + j]
+                        + rhs.val * self.hess[i * n + j]
+                        + self.grad[i] * rhs.grad[j]
+                        + rhs.grad[i] * self.grad[j];
+                }
+            }
+        }
+
+        let grad = self
+            .grad
+            .iter()
+            .zip(rhs.grad.iter())
+            .map(|(a, b)| self.val * b + rhs.val * a)
+            .collect();
+
+        Self {
+            val: self.val * rhs.val,
+            grad,
+            hess,
+        }
+    }
+}
+
+impl Div for Dual {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        // a / b = a * (1 / b)
+        Mul::mul(self, rhs.recip())
+    }
+}
+
+// Evaluation -----------------------------------------------------------------
+
+impl ParseNode {
+    /// Evaluates the node tree along with its partial derivatives with
+    /// respect to each variable in wrt, as well as the hessian if requested
+    pub fn eval_dual(&self, scope: &Scope, wrt: &[&str], hessian: bool) -> Derivatives {
+        let dual = self.dual(scope, wrt, hessian);
+        let n = wrt.len();
+
+        Derivatives {
+            value: dual.val,
+            hessian: if hessian {
+                Some(
+                    dual.hess
+                        .chunks(n.max(1))
+                        .take(n)
+                        .map(|row| row.to_vec())
+                        .collect(),
+                )
+            } else {
+                None
+            },
+            gradient: dual.grad,
+        }
+    }
+
+    pub fn dual(&self, scope: &Scope, wrt: &[&str], hessian: bool) -> Dual {
+        let constant = |val: f32| Dual::constant(val, wrt.len(), hessian);
+
+        match self {
+            ParseNode::FunctionDefine(_, _, body) => body.dual(scope, wrt, hessian),
+            ParseNode::Factor(val) => constant(*val),
+            ParseNode::Tuple(..) => constant(f32::NAN),
+            ParseNode::Identifier(name) => {
+                let val = scope.get(name).unwrap_or(f32::NAN);
+
+                match wrt.iter().position(|var| var == name) {
+                    Some(i) => Dual::variable(val, i, wrt.len(), hessian),
+                    None => constant(val),
+                }
+            }
+            ParseNode::UnaryOp(op, node) => match op {
+                Operator::Sub => -node.dual(scope, wrt, hessian),
+                _ => node.dual(scope, wrt, hessian),
+            },
+            ParseNode::BinOp(lhs, op, rhs) => {
+                let lhs = lhs.dual(scope, wrt, hessian);
+                let rhs = rhs.dual(scope, wrt, hessian);
+
+                match op {
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                    Operator::Pow => lhs.pow(&rhs),
+                }
+            }
+            ParseNode::Function(name, args) => {
+                if name == "time" && args.is_empty() {
+                    return constant(scope.time());
+                }
+
+                let args: Vec<Dual> = args
+                    .iter()
+                    .map(|arg| arg.dual(scope, wrt, hessian))
+                    .collect();
+
+                dual_native(name, args).unwrap_or_else(|| constant(f32::NAN))
+            }
+        }
+    }
+}
+
+/// Evaluates a native function on dual numbers, functions with jumps (eg.
+/// floor) are treated as locally constant so their derivatives are 0
+fn dual_native(name: &str, mut args: Vec<Dual>) -> Option<Dual> {
+    let out = match (name, args.len()) {
+        ("sin", 1) => {
+            let v = args[0].val;
+            args[0].chain(v.sin(), v.cos(), -v.sin())
+        }
+        ("cos", 1) => {
+            let v = args[0].val;
+            args[0].chain(v.cos(), -v.sin(), -v.cos())
+        }
+        ("tan", 1) => {
+            let t = args[0].val.tan();
+            let sec2 = 1. + t * t;
+            args[0].chain(t, sec2, 2. * t * sec2)
+        }
+        ("asin", 1) | ("acos", 1) => {
+            let v = args[0].val;
+            let d = 1. / (1. - v * v).sqrt();
+            let dd = v * d * d * d;
+
+            if name == "asin" {
+                args[0].chain(v.asin(), d, dd)
+            } else {
+                args[0].chain(v.acos(), -d, -dd)
+            }
+        }
+        ("atan", 1) => {
+            let v = args[0].val;
+            let d = 1. / (1. + v * v);
+            args[0].chain(v.atan(), d, -2. * v * d * d)
+        }
+        ("abs", 1) => {
+            let v = args[0].val;
+            args[0].chain(v.abs(), NativeFunc::eval("sign", &[v])?, 0.)
+        }
+        ("fract", 1) => {
+            let v = args[0].val;
+            args[0].chain(v - v.floor(), 1., 0.)
+        }
+        ("round", 1) | ("floor", 1) | ("ceil", 1) | ("sign", 1) => {
+            let v = NativeFunc::eval(name, &[args[0].val])?;
+            args[0].chain(v, 0., 0.)
+        }
+        ("exp", 1) => args[0].exp(),
+        ("log", 1) => args[0].ln(),
+        ("sqrt", 1) => args[0].sqrt(),
+        ("pow", 2) => args[0].pow(&args[1]),
+        // mod(a, b) = a - b * floor(a / b)
+        ("mod", 2) => {
+            let quotient = (args[0].val / args[1].val).floor();
+            let b = args.pop()?;
+            let a = args.pop()?;
+            a - b.scale(quotient)
+        }
+        ("hypot", 2) => {
+            let b = args.pop()?;
+            let a = args.pop()?;
+            (a.clone() * a + b.clone() * b).sqrt()
+        }
+        // lerp(a, b, t) = a + (b - a) * t
+        ("lerp", 3) => {
+            let t = args.pop()?;
+            let b = args.pop()?;
+            let a = args.pop()?;
+            a.clone() + (b - a) * t
+        }
+        _ => return None,
+    };
+
+    Some(out)
+}
@@ -0,0 +1,402 @@
+/*
+ * Interval arithmetic evaluation, gives guaranteed bounds of an equation over
+ * a range of inputs (eg. the range of f over a rectangle of the graph)
+ */
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::Scope;
+use crate::parser::{native::NativeFunc, Operator, ParseNode};
+
+/// Closed range of values [lo, hi], undefined is set when the range may
+/// contain NaN or infinity (eg. division by an interval containing 0)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f32,
+    pub hi: f32,
+    pub undefined: bool,
+}
+
+impl Interval {
+    pub fn new(lo: f32, hi: f32) -> Self {
+        Self::bounded(lo, hi, false)
+    }
+
+    /// Interval containing a single value
+    pub fn point(val: f32) -> Self {
+        Self::new(val, val)
+    }
+
+    /// Interval containing every value, which may be undefined
+    pub fn entire() -> Self {
+        Self {
+            lo: f32::NEG_INFINITY,
+            hi: f32::INFINITY,
+            undefined: true,
+        }
+    }
+
+    /// Builds an interval, flagging it if either bound is infinite or NaN
+    fn bounded(lo: f32, hi: f32, undefined: bool) -> Self {
+        if lo.is_nan() || hi.is_nan() {
+            return Self::entire();
+        }
+
+        Self {
+            lo: lo.min(hi),
+            hi: lo.max(hi),
+            undefined: undefined || lo.is_infinite() || hi.is_infinite(),
+        }
+    }
+
+    /// Builds an interval out of rounded results, widening it by a float step
+    /// on each side so rounding errors can't shrink the bounds
+    fn outward(lo: f32, hi: f32, undefined: bool) -> Self {
+        Self::bounded(next_down(lo), next_up(hi), undefined)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.hi - self.lo
+    }
+
+    pub fn contains(&self, val: f32) -> bool {
+        self.lo <= val && val <= self.hi
+    }
+
+    /// Applies a non decreasing function to both bounds
+    fn monotonic(&self, f: impl Fn(f32) -> f32) -> Self {
+        Self::outward(f(self.lo), f(self.hi), self.undefined)
+    }
+
+    /// Clips the interval to a domain [lo, hi], flagging it as undefined if
+    /// any part of it was outside of the domain
+    fn clip(&self, lo: f32, hi: f32) -> Option<Self> {
+        if self.hi < lo || self.lo > hi {
+            return None;
+        }
+
+        let outside = self.lo < lo || self.hi > hi;
+        Some(Self::bounded(
+            self.lo.max(lo),
+            self.hi.min(hi),
+            self.undefined || outside,
+        ))
+    }
+
+    pub fn sin(&self) -> Self {
+        self.wave(0., f32::sin)
+    }
+
+    pub fn cos(&self) -> Self {
+        // the cosine is a sine shifted by pi/2
+        self.wave(FRAC_PI_2, f32::cos)
+    }
+
+    /// Bounds of a sine wave shifted left by the given amount
+    fn wave(&self, shift: f32, f: fn(f32) -> f32) -> Self {
+        let (lo, hi) = (self.lo + shift, self.hi + shift);
+
+        // infinite intervals have a NaN width
+        let width = hi - lo;
+        if width.is_nan() || width >= TAU {
+            return Self::bounded(-1., 1., self.undefined);
+        }
+
+        // checks if a peak of the wave (at offset + 2k*pi) lies inside
+        let has_peak = |offset: f32| {
+            let k = ((lo - offset) / TAU).ceil();
+            offset + k * TAU <= hi
+        };
+
+        let (a, b) = (f(self.lo), f(self.hi));
+        let upper = if has_peak(FRAC_PI_2) { 1. } else { a.max(b) };
+        let lower = if has_peak(-FRAC_PI_2) { -1. } else { a.min(b) };
+
+        Self::outward(lower, upper, self.undefined)
+            .clip(-1., 1.)
+            .unwrap()
+    }
+
+    pub fn tan(&self) -> Self {
+        // asymptotes at pi/2 + k*pi
+        let k = ((self.lo - FRAC_PI_2) / PI).ceil();
+
+        let width = self.width();
+        if width.is_nan() || width >= PI || FRAC_PI_2 + k * PI <= self.hi {
+            return Self::entire();
+        }
+
+        self.monotonic(f32::tan)
+    }
+
+    pub fn abs(&self) -> Self {
+        if self.contains(0.) {
+            Self::bounded(0., self.lo.abs().max(self.hi.abs()), self.undefined)
+        } else {
+            let (a, b) = (self.lo.abs(), self.hi.abs());
+            Self::bounded(a.min(b), a.max(b), self.undefined)
+        }
+    }
+
+    pub fn sqrt(&self) -> Self {
+        match self.clip(0., f32::INFINITY) {
+            Some(clipped) => Self {
+                lo: next_down(clipped.lo.sqrt()).max(0.),
+                hi: next_up(clipped.hi.sqrt()),
+                undefined: clipped.undefined,
+            },
+            None => Self::entire(),
+        }
+    }
+
+    pub fn ln(&self) -> Self {
+        match self.clip(0., f32::INFINITY) {
+            Some(clipped) => clipped.monotonic(f32::ln),
+            None => Self::entire(),
+        }
+    }
+
+    pub fn exp(&self) -> Self {
+        let out = self.monotonic(f32::exp);
+        Self {
+            lo: out.lo.max(0.),
+            ..out
+        }
+    }
+
+    /// Raises the interval to a whole number power
+    fn powi(&self, n: i32) -> Self {
+        if n == 0 {
+            return Self::bounded(1., 1., self.undefined);
+        }
+
+        if n < 0 {
+            return Self::point(1.) / self.powi(-n);
+        }
+
+        if n % 2 == 1 {
+            return self.monotonic(|v| v.powi(n));
+        }
+
+        // even powers fold negative values over onto the positives
+        let out = self.abs().monotonic(|v| v.powi(n));
+        Self {
+            lo: out.lo.max(0.),
+            ..out
+        }
+    }
+
+    pub fn pow(&self, exponent: &Interval) -> Self {
+        let is_whole = exponent.lo == exponent.hi && exponent.lo.fract() == 0.;
+
+        if is_whole && exponent.lo.abs() <= i32::MAX as f32 {
+            let out = self.powi(exponent.lo as i32);
+            return Self {
+                undefined: out.undefined || exponent.undefined,
+                ..out
+            };
+        }
+
+        // a^b = exp(b * ln(a)), which is only defined for a >= 0
+        (*exponent * self.ln()).exp()
+    }
+
+    pub fn floor(&self) -> Self {
+        Self::bounded(self.lo.floor(), self.hi.floor(), self.undefined)
+    }
+
+    pub fn fract(&self) -> Self {
+        // fract is only continuous between whole numbers
+        if self.lo.floor() == self.hi.floor() {
+            self.monotonic(|v| v - v.floor())
+                .clip(0., 1.)
+                .unwrap_or_else(Self::entire)
+        } else {
+            Self::bounded(0., 1., self.undefined)
+        }
+    }
+
+    pub fn hypot(&self, other: &Interval) -> Self {
+        (self.powi(2) + other.powi(2)).sqrt()
+    }
+}
+
+// Operator Overloads ---------------------------------------------------------
+
+impl Add for Interval {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::outward(
+            self.lo + rhs.lo,
+            self.hi + rhs.hi,
+            self.undefined || rhs.undefined,
+        )
+    }
+}
+
+impl Sub for Interval {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::outward(
+            self.lo - rhs.hi,
+            self.hi - rhs.lo,
+            self.undefined || rhs.undefined,
+        )
+    }
+}
+
+impl Neg for Interval {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::bounded(-self.hi, -self.lo, self.undefined)
+    }
+}
+
+impl Mul for Interval {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        // 0 * inf is treated as 0 rather than NaN
+        let mul = |a: f32, b: f32| if a == 0. || b == 0. { 0. } else { a * b };
+
+        let products = [
+            mul(self.lo, rhs.lo),
+            mul(self.lo, rhs.hi),
+            mul(self.hi, rhs.lo),
+            mul(self.hi, rhs.hi),
+        ];
+
+        let lo = products.iter().cloned().fold(f32::INFINITY, f32::min);
+        let hi = products.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+
+        Self::outward(lo, hi, self.undefined || rhs.undefined)
+    }
+}
+
+impl Div for Interval {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        // dividing by anything that could be 0 is unbounded
+        if rhs.contains(0.) {
+            return Self::entire();
+        }
+
+        let recip = Self::outward(1. / rhs.hi, 1. / rhs.lo, rhs.undefined);
+        Mul::mul(self, recip)
+    }
+}
+
+// Evaluation -----------------------------------------------------------------
+
+impl ParseNode {
+    /// Evaluates guaranteed bounds of the node tree, where each variable in
+    /// ranges can take any value inside of its interval & any other variable
+    /// is read from the scope
+    pub fn eval_interval(&self, scope: &Scope, ranges: &[(&str, Interval)]) -> Interval {
+        match self {
+            ParseNode::FunctionDefine(_, _, body) => body.eval_interval(scope, ranges),
+            ParseNode::Factor(val) => Interval::point(*val),
+            ParseNode::Tuple(..) => Interval::entire(),
+            ParseNode::Identifier(name) => match ranges.iter().find(|(var, _)| var == name) {
+                Some((_, range)) => *range,
+                None => scope
+                    .get(name)
+                    .map(Interval::point)
+                    .unwrap_or_else(Interval::entire),
+            },
+            ParseNode::UnaryOp(op, node) => match op {
+                Operator::Sub => -node.eval_interval(scope, ranges),
+                _ => node.eval_interval(scope, ranges),
+            },
+            ParseNode::BinOp(lhs, op, rhs) => {
+                let lhs = lhs.eval_interval(scope, ranges);
+                let rhs = rhs.eval_interval(scope, ranges);
+
+                match op {
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                    Operator::Pow => lhs.pow(&rhs),
+                }
+            }
+            ParseNode::Function(name, args) => {
+                if name == "time" && args.is_empty() {
+                    return Interval::point(scope.time());
+                }
+
+                let args: Vec<Interval> = args
+                    .iter()
+                    .map(|arg| arg.eval_interval(scope, ranges))
+                    .collect();
+
+                interval_native(name, &args).unwrap_or_else(Interval::entire)
+            }
+        }
+    }
+}
+
+fn interval_native(name: &str, args: &[Interval]) -> Option<Interval> {
+    let out = match (name, args) {
+        ("sin", [a]) => a.sin(),
+        ("cos", [a]) => a.cos(),
+        ("tan", [a]) => a.tan(),
+        ("asin", [a]) => a.clip(-1., 1.)?.monotonic(f32::asin),
+        // acos is decreasing, so the bounds are swapped
+        ("acos", [a]) => -(a.clip(-1., 1.)?.monotonic(|v| -v.acos())),
+        ("atan", [a]) => a.monotonic(f32::atan),
+        ("abs", [a]) => a.abs(),
+        ("exp", [a]) => a.exp(),
+        ("log", [a]) => a.ln(),
+        ("sqrt", [a]) => a.sqrt(),
+        ("fract", [a]) => a.fract(),
+        // step functions are non decreasing, so their bounds are exact
+        ("round", [a]) | ("floor", [a]) | ("ceil", [a]) | ("sign", [a]) => {
+            let f = |v: f32| NativeFunc::eval(name, &[v]).unwrap();
+            Interval::bounded(f(a.lo), f(a.hi), a.undefined)
+        }
+        ("pow", [a, b]) => a.pow(b),
+        ("hypot", [a, b]) => a.hypot(b),
+        // mod(a, b) = a - b * floor(a / b)
+        ("mod", [a, b]) => {
+            let quotient = (*a / *b).floor();
+
+            // when a doesn't wrap around the result is a shifted copy of it
+            if quotient.lo == quotient.hi && b.lo == b.hi {
+                *a - *b * quotient
+            } else if b.lo > 0. {
+                Interval::bounded(0., b.hi, a.undefined || b.undefined)
+            } else {
+                *a - *b * quotient
+            }
+        }
+        // lerp(a, b, t) = a + (b - a) * t
+        ("lerp", [a, b, t]) => *a + (*b - *a) * *t,
+        _ => return None,
+    };
+
+    Some(out)
+}
+
+/// Next representable float towards negative infinity
+fn next_down(val: f32) -> f32 {
+    -next_up(-val)
+}
+
+/// Next representable float towards positive infinity
+fn next_up(val: f32) -> f32 {
+    if val.is_nan() || val == f32::INFINITY {
+        val
+    } else if val == 0. {
+        f32::from_bits(1)
+    } else if val > 0. {
+        f32::from_bits(val.to_bits() + 1)
+    } else {
+        f32::from_bits(val.to_bits() - 1)
+    }
+}
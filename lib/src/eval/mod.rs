@@ -0,0 +1,98 @@
+/*
+ * CPU evaluation of equations, used for anything that needs to know the value
+ * of a graph outside of the shaders (eg. fitting the view, meshing)
+ */
+
+// Module Definitions ---------------------------------------------------------
+pub mod dual;
+pub mod interval;
+
+use crate::parser::{
+    native::{NativeConstant, NativeFunc},
+    Operator, ParseNode,
+};
+
+/// Variable bindings used when evaluating an equation on the CPU
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    vars: Vec<(String, f32)>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self { vars: vec![] }
+    }
+
+    /// Builder style binding of a variable
+    pub fn with(mut self, name: &str, val: f32) -> Self {
+        self.set(name, val);
+        self
+    }
+
+    /// Binds a variable, overwriting any previous value
+    pub fn set(&mut self, name: &str, val: f32) {
+        match self.vars.iter_mut().find(|(var, _)| var == name) {
+            Some((_, old)) => *old = val,
+            None => self.vars.push((name.into(), val)),
+        }
+    }
+
+    /// Looks up a variable, falling back to native constants. As in the
+    /// shader t is an alias for TIME, which is 0 unless bound
+    pub fn get(&self, name: &str) -> Option<f32> {
+        let bound = |name: &str| {
+            self.vars
+                .iter()
+                .find(|(var, _)| var == name)
+                .map(|(_, val)| *val)
+        };
+
+        bound(name)
+            .or_else(|| NativeConstant::value(name))
+            .or_else(|| match name {
+                "t" | "TIME" => Some(bound("TIME").unwrap_or(0.)),
+                _ => None,
+            })
+    }
+
+    pub fn time(&self) -> f32 {
+        self.get("TIME").unwrap_or(0.)
+    }
+}
+
+impl ParseNode {
+    /// Evaluates the node tree on the CPU, anything that can't be evaluated
+    /// (eg. unbound variables) results in NaN
+    pub fn eval(&self, scope: &Scope) -> f32 {
+        match self {
+            ParseNode::FunctionDefine(_, _, body) => body.eval(scope),
+            ParseNode::Factor(val) => *val,
+            ParseNode::Identifier(name) => scope.get(name).unwrap_or(f32::NAN),
+            // vectors don't have a single value
+            ParseNode::Tuple(..) => f32::NAN,
+            ParseNode::UnaryOp(op, node) => match op {
+                Operator::Sub => -node.eval(scope),
+                _ => node.eval(scope),
+            },
+            ParseNode::BinOp(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(scope), rhs.eval(scope));
+
+                match op {
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                    Operator::Pow => lhs.powf(rhs),
+                }
+            }
+            ParseNode::Function(name, args) => {
+                if name == "time" && args.is_empty() {
+                    return scope.time();
+                }
+
+                let args: Vec<f32> = args.iter().map(|arg| arg.eval(scope)).collect();
+                NativeFunc::eval(name, &args).unwrap_or(f32::NAN)
+            }
+        }
+    }
+}
@@ -5,20 +5,38 @@
  * solution
  */
 
-use js_sys::{Array, Date, Float32Array, Uint32Array};
+use js_sys::{Array, Date};
 use wasm_bindgen::{prelude::*, throw_str, JsCast};
-use web_sys::{
-    HtmlCanvasElement, WebGl2RenderingContext, WebGlVertexArrayObject,
-};
+use web_sys::{HtmlCanvasElement, WebGl2RenderingContext, WebGlProgram};
 
 use crate::{
+    eval::Scope,
     math::{
-        geometry::GraphEquation,
+        axes::{Axes, Axis, AXES_GLSL, UPRIGHT},
+        data::{DataPoint, Dataset},
+        domain::Domain,
+        field::{magnitude, Field},
+        fit::{drawn_values, Fit},
+        framing::{self, ViewFrame},
+        geometry::{GraphEquation, GraphKind},
         mat4::{Mat4, Transform},
+        primitives::Primitive,
+        region::Region,
         vec3::Vec3,
     },
+    mesh::{
+        breaks::may_break,
+        quadtree::{RefineOptions, Surface},
+        TriMesh,
+    },
+    parser::{lexer::Lexer, parser::Parser, ParseNode},
     render::{
+        arrows::{Arrows, INSTANCE_SIZE},
+        glutils::{compile_shader, link_program},
         grid::Grid,
+        mesh::Mesh,
+        planes::PlaneQuad,
+        points::{Billboard, Sprites, SPRITE_SIZE},
     },
     shaders::shader_generator::ShaderGenerator,
 };
@@ -27,24 +45,133 @@ use crate::{
 // DEBUG CONSTANTS
 // ----------------------------------------------------------------------------
 const DRAW_WIRE: bool = false;
-const DETAIL: usize = 300;
+
+// planes get a cell every few pixels they cover on screen, so zooming in adds
+// detail & small or distant planes are cheaper
+const PIXELS_PER_CELL: f32 = 3.;
+const MIN_DETAIL: usize = 16;
+const MAX_DETAIL: usize = 256;
 
 const ANIM_SPEED: f32 = 0.03;
 const CAM_ZOOM_OUT: f32 = 1.5;
+const FOV: f32 = 45.;
+
+// adaptive meshes, tolerances are portions of the z axis' length
+const ADAPTIVE_MAX_DEPTH: u32 = 8;
+const ADAPTIVE_TOLERANCE: f32 = 0.001;
+const EXPORT_MAX_DEPTH: u32 = 9;
+const EXPORT_TOLERANCE: f32 = 0.0002;
+// cells along each input of exported implicit & parametric surfaces
+const EXPORT_RESOLUTION: usize = 96;
+
+// adaptive planes & vector fields are built on the CPU, so they follow the
+// zoom once it hasn't changed for ZOOM_SETTLE_MILLIS rather than every frame
+const ZOOM_SETTLE_MILLIS: f64 = 250.;
+
+// curves are drawn as tubes, the radius is in world space
+const CURVE_RADIUS: f32 = 0.008;
+const CURVE_SAMPLES: usize = 256;
+const TUBE_SIDES: usize = 12;
+// the point moving along curves is wider than the tube & goes through the
+// range of t every POINT_PERIOD seconds
+const POINT_SCALE: f32 = 3.;
+const POINT_PERIOD: f32 = 5.;
+
+// cells along each side of the grid the volume of regions is estimated with
+const VOLUME_RESOLUTION: usize = 256;
+
+// vector fields get an arrow at the center of each cell of a lattice with
+// FIELD_LATTICE cells along each axis, the strongest arrows fill ARROW_FILL of
+// a cell
+const FIELD_LATTICE: usize = 8;
+const ARROW_FILL: f32 = 0.9;
+const ARROW_SIDES: usize = 8;
+// streamlines take up to STREAMLINE_STEPS steps each way from their seed, each
+// step a STREAMLINE_STEPS'th of the diagonal of the field's box
+const STREAMLINE_STEPS: usize = 256;
+
+// named points are discs with a radius of POINT_RADIUS in world space
+const POINT_RADIUS: f32 = 0.03;
+// measured points with a size of 1 are a little smaller
+const DATA_RADIUS: f32 = 0.02;
+
+const SPRITE_VERT: &str = include_str!("shaders/src/sprite.vert");
+const SPRITE_FRAG: &str = include_str!("shaders/src/sprite.frag");
 
 // ----------------------------------------------------------------------------
 // Structure Definition
 // ----------------------------------------------------------------------------
 
+// rectangle (in axis coordinates) & axes an adaptive plane was built for
+type AdaptiveKey = (((f32, f32), (f32, f32)), Axes);
+
+// (columns, rows) of a plane & whether its first or last column collapses
+// into a pole
+type PlaneKey = ((usize, usize), (bool, bool));
+
+// box (graph values), axes & streamline seeds the instances of a vector field
+// were built for
+type FieldKey = ([(f32, f32); 3], Axes, Vec<[f32; 3]>);
+
+// box (graph values) the shape of a vector, line or plane was clipped to
+type PrimitiveKey = [(f32, f32); 3];
+
+// what vectors, lines & planes are drawn with, points share the billboard
+enum Shape {
+    Arrow(Arrows),
+    Quad(PlaneQuad),
+}
+
 #[wasm_bindgen(js_name = GlobalGrapher)]
 pub struct Grapher {
     canvas: HtmlCanvasElement,
     gl: WebGl2RenderingContext,
     equations: Vec<GraphEquation>,
-    equation_plane_vao: Option<WebGlVertexArrayObject>,
-    plane_index_length: u32,
+    // planes shared between equations, keyed by their shape
+    planes: Vec<(PlaneKey, Mesh)>,
+    // domains set through the API, indexed by equation slot
+    domain_overrides: Vec<Domain>,
+    // adaptive planes of each equation, along with the rectangle & axes
+    // they were built for
+    adaptive_planes: Vec<Option<(AdaptiveKey, Mesh)>>,
+    // parameters fitted to data & names marked for fitting through the API,
+    // indexed by equation slot
+    fitted: Vec<Vec<(String, f32)>>,
+    fit_params: Vec<Vec<String>>,
+    // seeds of streamlines set through the API, indexed by equation slot
+    field_seeds: Vec<Vec<[f32; 3]>>,
+    // arrows & streamlines of each vector field, along with what they were
+    // built for
+    fields: Vec<Option<(FieldKey, Arrows, Arrows)>>,
+    // shapes of each vector, line & plane, along with the box they were
+    // clipped to
+    primitives: Vec<Option<(PrimitiveKey, Shape)>>,
+    // square every point is drawn with
+    billboard: Billboard,
+    // measured points set through the API, indexed by slot, with the sprites
+    // & color they are drawn with
+    datasets: Vec<Option<(Dataset, Sprites, [f32; 3])>>,
+    // program every dataset is drawn with
+    sprite_program: WebGlProgram,
+    // slot of the dataset & index of the point last picked
+    picked: Option<(usize, usize)>,
+    // box implicit surfaces are ray marched in
+    cube: Mesh,
+    // tube radius (world space) & rings along each curve
+    curve_radius: f32,
+    curve_samples: usize,
+    // whether a point moves along each curve
+    curve_point: bool,
+    adaptive: bool,
     old_to_new: f32,
     cam: Vec3,
+    // zoom the adaptive planes & vector fields are built for & the zoom with
+    // the time (ms) it was last changed
+    built_zoom: f32,
+    zoom_changed: (f32, f64),
+    cam_distance: f32,
+    amplitude: f32,
+    axes: Axes,
     animate: bool,
     grid: Grid,
 }
@@ -73,14 +200,45 @@ impl Grapher {
 
         let grid = Grid::new(&gl, 40.).unwrap();
 
+        let (verticies, indecies) = ShaderGenerator::generate_cube();
+        let cube = Mesh::new(&gl, &verticies, &indecies).unwrap();
+        let billboard = Billboard::new(&gl).unwrap();
+
+        let sprite_program = {
+            let vert = SPRITE_VERT.replace("$AXES$", AXES_GLSL);
+            let vert = compile_shader(&gl, WebGl2RenderingContext::VERTEX_SHADER, &vert);
+            let frag = compile_shader(&gl, WebGl2RenderingContext::FRAGMENT_SHADER, SPRITE_FRAG);
+            link_program(&gl, &vert.unwrap(), &frag.unwrap()).unwrap()
+        };
+
         Self {
             // gets context from canvas and dynamically converts to correct type
             canvas,
             gl,
-            equation_plane_vao: None,
             equations: vec![],
-            plane_index_length: 0,
+            planes: vec![],
+            domain_overrides: vec![],
+            adaptive_planes: vec![],
+            fitted: vec![],
+            fit_params: vec![],
+            field_seeds: vec![],
+            fields: vec![],
+            primitives: vec![],
+            billboard,
+            datasets: vec![],
+            sprite_program,
+            picked: None,
+            cube,
+            curve_radius: CURVE_RADIUS,
+            curve_samples: CURVE_SAMPLES,
+            curve_point: true,
+            adaptive: false,
             cam: Vec3::new(0., 0., 8.),
+            built_zoom: 8.,
+            zoom_changed: (8., 0.),
+            cam_distance: CAM_ZOOM_OUT,
+            amplitude: 1.,
+            axes: Axes::default(),
             old_to_new: 0.,
             animate: true,
             grid,
@@ -114,76 +272,6 @@ impl Grapher {
         // set clear color
         self.gl.clear_color(46. / 255., 52. / 255., 64. / 255., 1.);
 
-        // ---------------------------------------------------------------------
-        // Creates plane mesh which is used for all graphs
-        // ---------------------------------------------------------------------
-
-        // Create & bind VAO to record vert info
-        let vao = self.gl.create_vertex_array().unwrap_throw();
-        self.gl.bind_vertex_array(Some(&vao));
-
-        // stores VAO to use for every frame
-        self.equation_plane_vao = Some(vao);
-
-        let (verticies, indecies) = ShaderGenerator::generate_plane(DETAIL);
-        let verticies = verticies.as_slice();
-        let indecies = indecies.as_slice();
-
-        self.plane_index_length = indecies.len() as u32;
-
-        // Setup for vertex buffer --------------------------------------------
-        let vertex_buff = self.gl.create_buffer().ok_or("Failed to create buffer")?;
-        // binds created buffer
-        self.gl
-            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vertex_buff));
-
-        // fills vertex buff
-        unsafe {
-            // allocate view to put verticies in & fills in with vertex data
-            let vert_view = Float32Array::view(verticies);
-            // fill buffer with data
-            self.gl.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &vert_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-        // --------------------------------------------------------------------
-
-        // Setup for vertex indices buffer ------------------------------------
-        let index_buff = self.gl.create_buffer().ok_or("Failed to create buffer")?;
-        self.gl.bind_buffer(
-            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
-            Some(&index_buff),
-        );
-
-        // fill data into index view
-        unsafe {
-            let index_view = Uint32Array::view(indecies);
-
-            // fill view data into index buffer
-            self.gl.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
-                &index_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-        // --------------------------------------------------------------------
-
-        // Setup shader vertex attributes
-        self.gl.vertex_attrib_pointer_with_i32(
-            0,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            3 * std::mem::size_of::<f32>() as i32,
-            0,
-        );
-        self.gl.enable_vertex_attrib_array(0);
-
-        // unbind VAO
-        self.gl.bind_vertex_array(None);
-
         // Clears Screen
         self.gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
         self.gl.clear(WebGl2RenderingContext::DEPTH_BUFFER_BIT);
@@ -210,42 +298,13 @@ impl Grapher {
 
 
 
-        // camera position to use for lookAt matrix & lighting calculations
-        // inside fragment shader
-        let cam_pos = Vec3::new(0., 0., -CAM_ZOOM_OUT);
-
         // Matrix Calculations ------------------------------------------------
-
-
-        // World matrix for all graphs
-        let mut m_world = Mat4::IDENTITY.clone();
-        m_world.rotate_x(90f32.to_radians());
-        m_world.rotate_z(self.cam.1);
-        m_world.rotate_x(self.cam.0);
-
-        // View matrix for all graphs
-        let m_view = {
-            let mut m_view = Mat4::new();
-            m_view.look_at(cam_pos, Vec3::new(0., 0., 0.), Vec3::UP);
-            m_view
-        };
-
-
-        // Projection matrix for all graphs
-        let m_proj = {
-            let mut m_proj = Mat4::new();
-            // temp values
-            let client_width = self.canvas.client_width() as f32;
-            let client_height = self.canvas.client_height() as f32;
-
-            m_proj.perspective(
-                (45f32).to_radians(),
-                client_width / client_height,
-                0.01,
-                99999.,
-            );
-            m_proj
-        };
+        // world, view & projection matrices shared by every graph
+        let Transform {
+            proj: m_proj,
+            view: m_view,
+            model: m_world,
+        } = self.transform();
 
         // getting time value as seconds
         let time = {
@@ -270,20 +329,141 @@ impl Grapher {
         }
 
         self.grid.freq = self.cam.2;
+        self.grid.axes = self.axes;
 
         // --------------------------------------------------------------------
         // Rendering of each equation
         // --------------------------------------------------------------------
 
-        // binds VAO
-        let vao = self.equation_plane_vao.as_ref().unwrap_throw();
-        self.gl.bind_vertex_array(Some(vao));
+        // each equation is drawn over its own domain, with a plane that
+        // matches the domain's shape
+        let domains: Vec<Domain> = self
+            .equations
+            .iter()
+            .map(|eq| self.domain_of(eq))
+            .collect();
+
+        let transform = self.transform();
+
+        let resolutions: Vec<PlaneKey> = domains
+            .iter()
+            .zip(self.equations.iter())
+            .map(|(domain, equation)| {
+                // tubes are rolled from a plane with a column per ring
+                if equation.kind == GraphKind::Curve {
+                    return ((self.curve_samples, TUBE_SIDES), (false, false));
+                }
+
+                // fields & primitives are drawn with their own shapes instead
+                // of a plane
+                if equation.kind == GraphKind::Field || equation.primitive.is_some() {
+                    return ((1, 1), (false, false));
+                }
+
+                let axes = self.axes.oriented(equation.orientation);
+                let (width, height) = domain.world_size(&axes, self.grid.freq);
+                let pixels = self.pixels_covered(&transform, equation, domain);
+                let detail =
+                    Mesh::plane_detail(pixels, PIXELS_PER_CELL, (MIN_DETAIL, MAX_DETAIL));
+
+                // disks & spheres go around their poles, the first input
+                // collapses into a pole where it reaches the center or the
+                // top & bottom of the sphere
+                match equation.kind {
+                    GraphKind::Cylindrical | GraphKind::Spherical => {
+                        let [(min, max), _] = self.param_ranges(equation, domain);
+                        let top = (max - std::f32::consts::PI).abs() < 1e-6;
+                        let poles = (min == 0., equation.kind == GraphKind::Spherical && top);
+
+                        ((detail / 2, detail), poles)
+                    }
+                    _ => (Mesh::plane_resolution(width / height, detail), (false, false)),
+                }
+            })
+            .collect();
+
+        for resolution in resolutions.iter() {
+            self.plane(*resolution);
+        }
+
+        // walls of regions
+        let walls: Vec<PlaneKey> = self
+            .equations
+            .iter()
+            .zip(resolutions.iter())
+            .filter(|(equation, _)| equation.kind == GraphKind::Region)
+            .map(|(_, resolution)| walls_of(*resolution))
+            .collect();
+
+        for resolution in walls.iter() {
+            self.plane(*resolution);
+        }
+
+        // ball drawn for the point moving along curves
+        let point_resolution = ((TUBE_SIDES, TUBE_SIDES), (true, true));
+        self.plane(point_resolution);
+
+        // planes no graph is drawn with anymore (eg. after zooming or
+        // changing the curve samples) are dropped
+        let gl = &self.gl;
+        self.planes.retain(|(resolution, plane)| {
+            let used = *resolution == point_resolution
+                || resolutions.contains(resolution)
+                || walls.contains(resolution);
+            if !used {
+                plane.delete(gl);
+            }
+            used
+        });
+
+        let now = Date::now();
+        if self.cam.2 != self.zoom_changed.0 {
+            self.zoom_changed = (self.cam.2, now);
+        } else if now - self.zoom_changed.1 >= ZOOM_SETTLE_MILLIS {
+            self.built_zoom = self.cam.2;
+        }
+
+        if self.adaptive {
+            self.update_adaptive_planes(&domains);
+        }
+
+        self.update_fields(&domains);
+        self.update_primitives(&domains);
 
         let uni_loc =
             |eq: &GraphEquation, name: &str| self.gl.get_uniform_location(&eq.program, name);
 
 
-        for equation in self.equations.iter() {
+        // measured data is opaque, so it is drawn before translucent graphs
+        self.render_data(&m_world, &m_view, &m_proj);
+
+        // translucent regions & planes are drawn last without writing depth,
+        // so the graphs inside or behind them & their own back faces show
+        // through
+        let mut order: Vec<usize> = (0..self.equations.len()).collect();
+        order.sort_by_key(|&i| self.equations[i].kind.is_translucent());
+
+        for (i, equation) in order.into_iter().map(|i| (i, &self.equations[i])) {
+            // plane the equation is drawn with
+            let adaptive_plane = self
+                .adaptive_planes
+                .get(i)
+                .and_then(|plane| plane.as_ref())
+                .filter(|_| self.adaptive)
+                .map(|(_, plane)| plane);
+
+            let plane = if equation.kind == GraphKind::Implicit {
+                &self.cube
+            } else if let Some(plane) = adaptive_plane {
+                plane
+            } else {
+                self.planes
+                    .iter()
+                    .find(|(resolution, _)| *resolution == resolutions[i])
+                    .map(|(_, plane)| plane)
+                    .unwrap_throw()
+            };
+
             // tell gl state machine to use the equation program
             self.gl.use_program(Some(&equation.program));
 
@@ -301,8 +481,92 @@ impl Grapher {
             self.gl
                 .uniform_matrix4fv_with_f32_array(Some(&view_loc), false, &m_view.0);
 
-            if let Some(graph_freq_loc) = uni_loc(&equation, "graphFrequency") {
-                self.gl.uniform1f(Some(&graph_freq_loc), self.grid.freq);
+            // explicit surfaces are built in their own frame, see
+            // GraphEquation::drawn
+            let axes = self.axes.oriented(equation.orientation);
+            axes.set_uniforms(&self.gl, &equation.program, self.grid.freq);
+
+            // adaptive planes are refined to their deepest level around
+            // breaks, so neighbours are found at that spacing
+            if let Some(spacing_loc) = uni_loc(&equation, "breakSpacing") {
+                let ((cols, rows), _) = resolutions[i];
+                let (x, y) = if adaptive_plane.is_some() {
+                    let finest = 1. / (1u32 << ADAPTIVE_MAX_DEPTH) as f32;
+                    (finest, finest)
+                } else {
+                    (1. / cols as f32, 1. / rows as f32)
+                };
+                self.gl.uniform2f(Some(&spacing_loc), x, y);
+            }
+
+            // adaptive planes are built without the triangles spanning
+            // breaks, regular planes are only checked for them when the
+            // graph (or the one it is animating from) may have some
+            if let Some(loc) = uni_loc(equation, "checkBreaks") {
+                let check = adaptive_plane.is_none()
+                    && (self.old_to_new < 1.
+                        || may_break(
+                            &equation.surface,
+                            &Scope::new(),
+                            domains[i].rect(&axes, self.grid.freq),
+                        ));
+                self.gl.uniform1i(Some(&loc), check as i32);
+            }
+
+            // parametric surfaces are stretched over the ranges of their
+            // inputs instead of the x & y axes
+            if let Some(domain_loc) = uni_loc(&equation, "domain") {
+                let ((x_min, x_max), (y_min, y_max)) = match equation.kind {
+                    GraphKind::Parametric
+                    | GraphKind::Cylindrical
+                    | GraphKind::Spherical
+                    | GraphKind::Revolution(_) => {
+                        let [u, v] = self.param_ranges(equation, &domains[i]);
+                        (u, v)
+                    }
+                    GraphKind::Curve => (domains[i].param_range(&equation.params[0]), (0., 0.)),
+                    _ => domains[i].axis_rect(&axes, self.grid.freq),
+                };
+                self.gl
+                    .uniform4f(Some(&domain_loc), x_min, x_max, y_min, y_max);
+            }
+
+            if equation.kind == GraphKind::Implicit {
+                let (min, max) = self.world_box(&domains[i]);
+                if let Some(loc) = uni_loc(equation, "boxMin") {
+                    self.gl.uniform3f(Some(&loc), min.0, min.1, min.2);
+                }
+                if let Some(loc) = uni_loc(equation, "boxMax") {
+                    self.gl.uniform3f(Some(&loc), max.0, max.1, max.2);
+                }
+            }
+
+            if let Some(loc) = uni_loc(equation, "valueRange") {
+                let [_, _, (min, max)] = domains[i].bounds(&axes, self.grid.freq);
+                self.gl.uniform2f(Some(&loc), min, max);
+            }
+
+            if let Some(loc) = uni_loc(equation, "tubeRadius") {
+                self.gl.uniform1f(Some(&loc), self.curve_radius);
+            }
+            if let Some(loc) = uni_loc(equation, "tubeRings") {
+                self.gl.uniform1f(Some(&loc), self.curve_samples as f32);
+            }
+
+            // the strongest arrows fill their cell of the lattice along its
+            // shortest side
+            if let Some(loc) = uni_loc(equation, "arrowLength") {
+                let (min, max) = self.world_box(&domains[i]);
+                let cell = (max.0 - min.0).min(max.1 - min.1).min(max.2 - min.2);
+                self.gl
+                    .uniform1f(Some(&loc), cell / FIELD_LATTICE as f32 * ARROW_FILL);
+            }
+            if let Some(loc) = uni_loc(equation, "isPoint") {
+                self.gl.uniform1f(Some(&loc), 0.);
+            }
+
+            if let Some(amplitude_loc) = uni_loc(&equation, "amplitude") {
+                self.gl.uniform1f(Some(&amplitude_loc), self.amplitude);
             }
 
             // setting lighting position
@@ -323,20 +587,109 @@ impl Grapher {
 
             // Draw Call ------------------------------------------------------
             // draws program triangles (wiremesh of DRAW_WIRE debug constant is on)
-            self.gl.draw_elements_with_i32(
-                if DRAW_WIRE {
-                    WebGl2RenderingContext::LINES
-                } else {
-                    WebGl2RenderingContext::TRIANGLES
-                },
-                self.plane_index_length as i32,
-                WebGl2RenderingContext::UNSIGNED_INT,
-                0,
-            );
+            let mode = if DRAW_WIRE {
+                WebGl2RenderingContext::LINES
+            } else {
+                WebGl2RenderingContext::TRIANGLES
+            };
+            self.gl.depth_mask(!equation.kind.is_translucent());
+
+            // fields draw their arrows, then their streamlines with the same
+            // program
+            if equation.kind == GraphKind::Field {
+                let instances = self.fields.get(i).and_then(Option::as_ref);
+                if let Some((_, arrows, streamlines)) = instances {
+                    let streamline_loc = uni_loc(equation, "isStreamline");
+                    self.gl.uniform1f(streamline_loc.as_ref(), 0.);
+                    arrows.render(&self.gl, mode);
+
+                    self.gl.uniform1f(streamline_loc.as_ref(), 1.);
+                    streamlines.render(&self.gl, mode);
+                }
+                continue;
+            }
+
+            // points move the billboard to their center, vectors, lines &
+            // planes draw the shapes they were clipped to
+            match equation.primitive {
+                Some(Primitive::Point(center)) => {
+                    let center_loc = uni_loc(equation, "center");
+                    self.gl
+                        .uniform3f(center_loc.as_ref(), center[0], center[1], center[2]);
+                    let radius_loc = uni_loc(equation, "pointRadius");
+                    self.gl.uniform1f(radius_loc.as_ref(), POINT_RADIUS);
+
+                    self.billboard.render(&self.gl);
+                    continue;
+                }
+                Some(primitive) => {
+                    if let Primitive::Plane { normal, .. } = primitive {
+                        let normal_loc = uni_loc(equation, "planeNormal");
+                        self.gl
+                            .uniform3f(normal_loc.as_ref(), normal[0], normal[1], normal[2]);
+                    }
+                    if let Some(loc) = uni_loc(equation, "isLine") {
+                        let is_line = matches!(primitive, Primitive::Line { .. });
+                        self.gl.uniform1f(Some(&loc), is_line as u8 as f32);
+                    }
+
+                    match self.primitives.get(i).and_then(Option::as_ref) {
+                        Some((_, Shape::Arrow(arrows))) => arrows.render(&self.gl, mode),
+                        Some((_, Shape::Quad(quad))) => quad.render(&self.gl, mode),
+                        None => {}
+                    }
+                    continue;
+                }
+                None => {}
+            }
+
+            if let Some(loc) = uni_loc(equation, "face") {
+                self.gl.uniform1f(Some(&loc), 0.);
+            }
+            plane.render(&self.gl, mode);
+
+            // regions draw their upper face with the same plane, then the
+            // walls around them
+            if equation.kind == GraphKind::Region {
+                let face_loc = uni_loc(equation, "face");
+                self.gl.uniform1f(face_loc.as_ref(), 1.);
+                plane.render(&self.gl, mode);
+
+                self.gl.uniform1f(face_loc.as_ref(), 2.);
+                self.planes
+                    .iter()
+                    .find(|(resolution, _)| *resolution == walls_of(resolutions[i]))
+                    .map(|(_, plane)| plane)
+                    .unwrap_throw()
+                    .render(&self.gl, mode);
+            }
+
+            // the same program draws the moving point by wrapping a plane
+            // into a ball around the curve
+            if equation.kind == GraphKind::Curve && self.curve_point {
+                let (min, max) = domains[i].param_range(&equation.params[0]);
+                let travel = (time / POINT_PERIOD).rem_euclid(1.);
+
+                if let Some(loc) = uni_loc(equation, "isPoint") {
+                    self.gl.uniform1f(Some(&loc), 1.);
+                }
+                if let Some(loc) = uni_loc(equation, "pointParam") {
+                    self.gl.uniform1f(Some(&loc), min + (max - min) * travel);
+                }
+                if let Some(loc) = uni_loc(equation, "pointRadius") {
+                    self.gl.uniform1f(Some(&loc), self.curve_radius * POINT_SCALE);
+                }
+
+                self.planes
+                    .iter()
+                    .find(|(resolution, _)| *resolution == point_resolution)
+                    .map(|(_, plane)| plane)
+                    .unwrap_throw()
+                    .render(&self.gl, mode);
+            }
         }
 
-        // unbinds VAO
-        self.gl.bind_vertex_array(None);
+        self.gl.depth_mask(true);
 
         // --------------------------------------------------------------------
         // Renders Grid
@@ -368,6 +721,9 @@ impl Grapher {
         // empties equation vectors
 
         let mut new_equations = vec![];
+        // points, vectors & planes named so far, the equations below them
+        // can use their names
+        let mut defined: Vec<(String, Primitive)> = vec![];
 
         // enumerates for every element in array
         for (i, js_equation) in equations.iter().enumerate() {
@@ -387,13 +743,31 @@ impl Grapher {
 
             // attempts to get the old graph for animation
             let old = if let Some(old_eq) = self.equations.iter().nth(i) {
-                Some(old_eq.ast.clone())
+                Some((old_eq.ast.clone(), old_eq.kind))
             } else {
                 None
             };
 
+            // fits are kept until the equation they were made for changes
+            let changed = !self
+                .equations
+                .iter()
+                .any(|eq| eq.slot == i && eq.source == ascii);
+            if changed && i < self.fitted.len() {
+                self.fitted[i].clear();
+            }
+            let fitted = self.fitted.get(i).map_or(&[][..], Vec::as_slice);
+            let marked = self.fit_params.get(i).map_or(&[][..], Vec::as_slice);
+            let values = drawn_values(marked, fitted);
+
             // attempts to create graph equation
-            let equation = GraphEquation::new(&self.gl, ascii, i as u8 % 5, old);
+            let equation =
+                GraphEquation::new(&self.gl, ascii, i, i as u8 % 5, old, &defined, &values);
+
+            let equation = equation.and_then(|equation| {
+                self.check_domain(&equation, &self.axes, self.domain_overrides.get(i))?;
+                Ok(equation)
+            });
 
             // return error in failure, add to equations list otherwise
             if let Err(err) = equation {
@@ -404,6 +778,7 @@ impl Grapher {
                 )
                 .into());
             } else if let Ok(equation) = equation {
+                defined.extend(definition(&equation));
                 new_equations.push(equation)
             }
         }
@@ -414,12 +789,210 @@ impl Grapher {
         }
         self.equations.clear();
 
+        for (_, plane) in self.adaptive_planes.drain(..).flatten() {
+            plane.delete(&self.gl);
+        }
+
+        for (_, arrows, streamlines) in self.fields.drain(..).flatten() {
+            arrows.delete(&self.gl);
+            streamlines.delete(&self.gl);
+        }
+
+        for (_, shape) in self.primitives.drain(..).flatten() {
+            shape.delete(&self.gl);
+        }
+
         self.equations = new_equations;
         self.old_to_new = 0.;
 
         Ok(())
     }
 
+    /// Returns the simplified form of each enabled equation as a JS Array of
+    /// strings, in the same order they were given to set_equations
+    #[wasm_bindgen]
+    pub fn simplified_equations(&self) -> Array {
+        self.equations
+            .iter()
+            .map(|eq| JsValue::from_str(eq.simplified.as_str()))
+            .collect()
+    }
+
+    /// Scales the graphs vertically & moves the camera so every enabled
+    /// equation is framed, graphs that are already short are not stretched
+    #[wasm_bindgen]
+    pub fn fit_view(&mut self) {
+        let domains: Vec<Domain> = self.equations.iter().map(|eq| self.domain_of(eq)).collect();
+        // only upright explicit graphs are framed, implicit surfaces stay
+        // within their box which is inside the axes
+        let upright = |eq: &GraphEquation| eq.orientation == UPRIGHT;
+        let graphs: Vec<_> = self
+            .equations
+            .iter()
+            .zip(domains.iter())
+            .filter(|(eq, _)| eq.kind == GraphKind::Explicit && upright(eq))
+            .map(|(eq, domain)| (&eq.surface, domain))
+            .collect();
+
+        let z_range =
+            framing::z_range(&graphs, &self.axes, self.cam.2, &Scope::new()).unwrap_or((0., 0.));
+
+        // every graph reaches at least as far as the axes, sideways surfaces
+        // aren't framed
+        let extent = self
+            .equations
+            .iter()
+            .zip(domains.iter())
+            .filter(|(eq, _)| upright(eq))
+            .fold(
+                (self.axes.x.stretch / 2., self.axes.y.stretch / 2.),
+                |(x, y), (_, domain)| {
+                    let (dx, dy) = domain.world_extent(&self.axes, self.cam.2);
+                    (x.max(dx), y.max(dy))
+                },
+            );
+
+        let aspect = self.canvas.client_width() as f32 / self.canvas.client_height() as f32;
+        let frame = ViewFrame::fit(
+            z_range,
+            extent,
+            &self.axes,
+            self.cam.2,
+            FOV.to_radians(),
+            aspect,
+        );
+
+        self.amplitude = frame.amplitude;
+        self.cam_distance = frame.cam_distance;
+    }
+
+    /// Undoes fit_view, going back to the default stretch & camera distance
+    #[wasm_bindgen]
+    pub fn reset_view(&mut self) {
+        self.amplitude = 1.;
+        self.cam_distance = CAM_ZOOM_OUT;
+    }
+
+    /// Sets the scaling of the "x", "y" or "z" axis. When min & max are both
+    /// given the axis covers that range, when neither is it follows the zoom.
+    /// Stretch lengthens the axis on screen & log makes it logarithmic
+    #[wasm_bindgen]
+    pub fn set_axis(
+        &mut self,
+        name: &str,
+        min: Option<f32>,
+        max: Option<f32>,
+        stretch: f32,
+        log: bool,
+    ) -> Result<(), JsValue> {
+        let axis = Axis::new(Axis::range(min, max)?, stretch, log)?;
+
+        let mut axes = self.axes;
+        match name {
+            "x" => axes.x = axis,
+            "y" => axes.y = axis,
+            "z" => axes.z = axis,
+            _ => return Err(format!("Unknown axis {}", name).into()),
+        }
+
+        for equation in self.equations.iter() {
+            self.check_domain(equation, &axes, self.domain_overrides.get(equation.slot))?;
+        }
+        self.axes = axes;
+
+        Ok(())
+    }
+
+    /// Sets the range of an input (eg. "x") for the equation at index in the
+    /// array given to set_equations, overriding any range in its text. The
+    /// override is cleared when min or max is missing
+    #[wasm_bindgen]
+    pub fn set_domain(
+        &mut self,
+        index: usize,
+        var: &str,
+        min: Option<f32>,
+        max: Option<f32>,
+    ) -> Result<(), JsValue> {
+        if self.domain_overrides.len() <= index {
+            self.domain_overrides.resize(index + 1, Domain::new());
+        }
+
+        let mut domain = self.domain_overrides[index].clone();
+        match min.zip(max) {
+            Some(range) => domain.set(var, range)?,
+            None => domain.clear(var),
+        }
+
+        for equation in self.equations.iter().filter(|eq| eq.slot == index) {
+            self.check_domain(equation, &self.axes, Some(&domain))?;
+        }
+        self.domain_overrides[index] = domain;
+
+        Ok(())
+    }
+
+    /// Sets the points the streamlines of the vector field at index in the
+    /// array given to set_equations are traced from, packed as x, y & z. The
+    /// streamlines are removed when seeds is empty
+    #[wasm_bindgen]
+    pub fn set_field_seeds(&mut self, index: usize, seeds: Vec<f32>) -> Result<(), JsValue> {
+        let chunks = seeds.chunks_exact(3);
+        if !chunks.remainder().is_empty() {
+            return Err("Seeds need 3 components (x, y, z)".into());
+        }
+
+        if self.field_seeds.len() <= index {
+            self.field_seeds.resize(index + 1, vec![]);
+        }
+
+        self.field_seeds[index] = chunks.map(|p| [p[0], p[1], p[2]]).collect();
+
+        Ok(())
+    }
+
+    /// Marks names as parameters of the equation at index in the array given
+    /// to set_equations (eg. a & b in f(x, y) = a*x + b), they are drawn at a
+    /// starting value until fit_data fits them. Any other name that isn't an
+    /// input or a constant isn't defined
+    #[wasm_bindgen]
+    pub fn set_fit_params(&mut self, index: usize, names: Array) -> Result<(), JsValue> {
+        let names: Option<Vec<String>> = names.iter().map(|name| name.as_string()).collect();
+        let names = names.ok_or_else(|| JsValue::from_str("Parameters need to be names"))?;
+
+        if self.fit_params.len() <= index {
+            self.fit_params.resize(index + 1, vec![]);
+        }
+
+        self.fit_params[index] = names;
+
+        Ok(())
+    }
+
+    /// Sets the radius (in world space) of the tubes curves are drawn as, &
+    /// how many rings each tube has along its curve
+    #[wasm_bindgen]
+    pub fn set_curve_style(&mut self, radius: f32, samples: usize) -> Result<(), JsValue> {
+        if !radius.is_finite() || radius <= 0. {
+            return Err(format!("Invalid curve radius {}", radius).into());
+        }
+
+        if samples < 2 {
+            return Err("Curves need at least 2 samples".into());
+        }
+
+        self.curve_radius = radius;
+        self.curve_samples = samples;
+
+        Ok(())
+    }
+
+    /// Shows or hides the point moving along each curve
+    #[wasm_bindgen]
+    pub fn set_curve_point(&mut self, v: bool) {
+        self.curve_point = v;
+    }
+
     #[wasm_bindgen]
     pub fn set_viewport(&self) {
         self.gl.viewport(
@@ -441,6 +1014,274 @@ impl Grapher {
         self.cam.2 = zoom;
     }
 
+    /// Switches between the uniform plane & planes refined where each
+    /// surface needs detail
+    #[wasm_bindgen]
+    pub fn set_adaptive_mesh(&mut self, v: bool) {
+        self.adaptive = v;
+    }
+
+    /// Sets the measured points at index from CSV (columns x, y, z & an
+    /// optional size, with or without a header) or JSON (an array of
+    /// [x, y, z] or {"x", "y", "z", "size"}). Returns how many points were
+    /// read
+    #[wasm_bindgen]
+    pub fn set_data(&mut self, index: usize, text: String) -> Result<usize, JsValue> {
+        let dataset = Dataset::parse(&text)?;
+
+        let mut sprites = Vec::with_capacity(dataset.points.len() * SPRITE_SIZE);
+        for (i, point) in dataset.points.iter().enumerate() {
+            sprites.extend_from_slice(&point.position);
+            sprites.push(point.size);
+            sprites.push(i as f32);
+        }
+        let sprites = Sprites::new(&self.gl, &self.sprite_program, &self.billboard, &sprites)?;
+
+        // colored like the equation in the same slot
+        let rgb_arr = crate::theme(11 + index as u32 % 5);
+        let channel = |i: u32| rgb_arr.get(i).as_f64().unwrap_throw() as f32;
+        let color = [channel(0), channel(1), channel(2)];
+
+        self.clear_data(index);
+        if self.datasets.len() <= index {
+            self.datasets.resize_with(index + 1, || None);
+        }
+
+        let count = dataset.points.len();
+        self.datasets[index] = Some((dataset, sprites, color));
+
+        Ok(count)
+    }
+
+    /// Removes the measured points at index
+    #[wasm_bindgen]
+    pub fn clear_data(&mut self, index: usize) {
+        if let Some((_, sprites, _)) = self.datasets.get_mut(index).and_then(Option::take) {
+            sprites.delete(&self.gl);
+        }
+
+        if matches!(self.picked, Some((set, _)) if set == index) {
+            self.picked = None;
+        }
+    }
+
+    /// Picks the measured point under (x, y), in pixels from the top left of
+    /// the canvas, which is drawn highlighted. Returns [slot, index, x, y, z]
+    /// with the slot of its dataset, its index & coordinates (graph values),
+    /// or undefined if there is no point under the cursor
+    #[wasm_bindgen]
+    pub fn pick_data(&mut self, x: f32, y: f32) -> Option<Array> {
+        let transform = self.transform();
+        let width = self.canvas.client_width() as f32;
+        let height = self.canvas.client_height() as f32;
+
+        // matches sprite.vert, which spreads the sprite out in view space
+        let project = |point: &DataPoint| {
+            let [x, y, z] = self.graph_to_world(point.position);
+            let view = transform.view.transform(transform.model.transform([x, y, z, 1.]));
+            let [x, y, _, w] = transform.proj.transform(view);
+
+            if w <= 0. {
+                return None;
+            }
+
+            let screen = [(x / w + 1.) / 2. * width, (1. - y / w) / 2. * height];
+            let radius = DATA_RADIUS * point.size * transform.proj.0[5] / w * height / 2.;
+            Some((screen, radius, w))
+        };
+
+        self.picked = self
+            .datasets
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, data)| {
+                let (dataset, ..) = data.as_ref()?;
+                let (i, depth) = dataset.pick([x, y], project)?;
+                Some(((slot, i), depth))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(picked, _)| picked);
+
+        let (slot, i) = self.picked?;
+        let [x, y, z] = self.datasets[slot].as_ref()?.0.points[i].position;
+
+        Some(
+            [slot as f32, i as f32, x, y, z]
+                .iter()
+                .map(|&v| JsValue::from_f64(v as f64))
+                .collect(),
+        )
+    }
+
+    /// Fits the free parameters of the equation at index (in the same order
+    /// as simplified_equations, eg. a, b & c in f(x, y) = a*x^2 + b*y + c) to
+    /// the measured points at data, then draws it with the fitted values.
+    /// The parameters are marked with set_fit_params first. Returns
+    /// [params, residuals, r2] with params as [name, value] pairs & the
+    /// residual of each point
+    #[wasm_bindgen]
+    pub fn fit_data(&mut self, index: usize, data: usize) -> Result<Array, JsValue> {
+        let equation = self
+            .equations
+            .get(index)
+            .ok_or_else(|| JsValue::from_str("No equation to fit"))?;
+        let (dataset, ..) = self
+            .datasets
+            .get(data)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| JsValue::from_str("No data to fit to"))?;
+
+        // the equation as written is fitted, starting from its last fit
+        let tokens = Lexer::new(equation.source.clone())
+            .tokenize()
+            .map_err(|err| format!("{:?}", err))?;
+        let ast = Parser::new(tokens)
+            .parse()
+            .map_err(|err| format!("{:?}", err))?;
+
+        let guesses = self.fitted.get(equation.slot).cloned().unwrap_or_default();
+        let points: Vec<[f32; 3]> = dataset.points.iter().map(|p| p.position).collect();
+        let fit = Fit::new(&ast, &points, &guesses)?;
+
+        // rebuilt with the fitted values, animating from the old ones
+        let defined: Vec<(String, Primitive)> = self.equations[..index]
+            .iter()
+            .filter_map(definition)
+            .collect();
+        let rebuilt = GraphEquation::new(
+            &self.gl,
+            equation.source.clone(),
+            equation.slot,
+            equation.color,
+            Some((equation.ast.clone(), equation.kind)),
+            &defined,
+            &fit.params,
+        )?;
+
+        if self.fitted.len() <= rebuilt.slot {
+            self.fitted.resize(rebuilt.slot + 1, vec![]);
+        }
+        self.fitted[rebuilt.slot] = fit.params.clone();
+
+        let old = std::mem::replace(&mut self.equations[index], rebuilt);
+        self.gl.delete_program(Some(&old.program));
+        if let Some((_, plane)) = self.adaptive_planes.get_mut(index).and_then(Option::take) {
+            plane.delete(&self.gl);
+        }
+        self.old_to_new = 0.;
+
+        let params: Array = fit
+            .params
+            .iter()
+            .map(|(name, val)| {
+                Array::of2(&JsValue::from_str(name), &JsValue::from_f64(*val as f64))
+            })
+            .collect();
+        let residuals: Array = fit
+            .residuals
+            .iter()
+            .map(|&r| JsValue::from_f64(r as f64))
+            .collect();
+
+        Ok(Array::of3(
+            &params,
+            &residuals,
+            &JsValue::from_f64(fit.r_squared as f64),
+        ))
+    }
+
+    /// Exports the equation at index (in the same order as
+    /// simplified_equations) over its domain as a Wavefront OBJ mesh, in
+    /// graph coordinates
+    #[wasm_bindgen]
+    pub fn export_obj(&self, index: usize) -> Result<String, JsValue> {
+        let equation = self
+            .equations
+            .get(index)
+            .ok_or_else(|| JsValue::from_str("No equation to export"))?;
+
+        let scope = Scope::new();
+        let domain = self.domain_of(equation);
+
+        let mesh = match equation.kind {
+            GraphKind::Implicit => {
+                let bounds = domain.bounds(&self.axes, self.cam.2);
+                TriMesh::implicit_surface(&equation.surface, &scope, bounds, EXPORT_RESOLUTION)
+            }
+            GraphKind::Parametric
+            | GraphKind::Cylindrical
+            | GraphKind::Spherical
+            | GraphKind::Revolution(_) => {
+                let ranges = self.param_ranges(equation, &domain);
+                TriMesh::parametric_surface(
+                    &equation.surface,
+                    &scope,
+                    &equation.params,
+                    ranges,
+                    EXPORT_RESOLUTION,
+                )
+            }
+            GraphKind::Curve => {
+                // the tube keeps its on screen radius, measured along x
+                let (_, scale) = self.axes.x.span(self.cam.2);
+                let radius = self.curve_radius * scale / self.axes.x.stretch;
+
+                TriMesh::curve_tube(
+                    &equation.surface,
+                    &scope,
+                    &equation.params[0],
+                    domain.param_range(&equation.params[0]),
+                    (self.curve_samples, TUBE_SIDES),
+                    radius,
+                )
+            }
+            GraphKind::Explicit => {
+                let axes = self.axes.oriented(equation.orientation);
+                let surface = Surface {
+                    equation: &equation.surface,
+                    scope: &scope,
+                    axes: &axes,
+                    rect: domain.axis_rect(&axes, self.cam.2),
+                };
+
+                let options = refine_options(&axes, self.cam.2, EXPORT_MAX_DEPTH, EXPORT_TOLERANCE);
+                TriMesh::adaptive_surface(&surface, &options).oriented(equation.orientation)
+            }
+            GraphKind::Region => {
+                let axes = self.axes.oriented(equation.orientation);
+                let [x, y, range] = domain.bounds(&axes, self.cam.2);
+                let region = Region::new(&equation.surface, &scope, range).unwrap_throw();
+
+                TriMesh::region_solid(&region, (x, y), EXPORT_RESOLUTION)
+                    .oriented(equation.orientation)
+            }
+            GraphKind::Field => return Err("Vector fields can't be exported".into()),
+            GraphKind::Point | GraphKind::Vector | GraphKind::Line | GraphKind::Plane => {
+                return Err("Points, vectors, lines & planes can't be exported".into())
+            }
+        };
+
+        Ok(mesh.to_obj())
+    }
+
+    /// Estimates the volume of the inequality at index (in the same order as
+    /// simplified_equations) over its domain, in graph units
+    #[wasm_bindgen]
+    pub fn region_volume(&self, index: usize) -> Result<f32, JsValue> {
+        let equation = self
+            .equations
+            .get(index)
+            .ok_or_else(|| JsValue::from_str("No equation to measure"))?;
+
+        let scope = Scope::new();
+        let axes = self.axes.oriented(equation.orientation);
+        let [x, y, range] = self.domain_of(equation).bounds(&axes, self.cam.2);
+        let region = Region::new(&equation.surface, &scope, range)
+            .ok_or_else(|| JsValue::from_str("Only inequalities have a volume"))?;
+
+        Ok(region.volume((x, y), VOLUME_RESOLUTION))
+    }
+
     #[wasm_bindgen]
     pub fn set_animate(&mut self, v: bool) {
         self.animate = v;
@@ -460,4 +1301,441 @@ impl Grapher {
     pub fn cam_zoom(&self) -> f32 {
         self.cam.2
     }
+
+    #[wasm_bindgen]
+    pub fn amplitude(&self) -> f32 {
+        self.amplitude
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Internal Helpers
+// ----------------------------------------------------------------------------
+impl Grapher {
+    /// World, view & projection matrices the graphs are drawn with
+    fn transform(&self) -> Transform {
+        // camera position to use for lookAt matrix & lighting calculations
+        // inside fragment shader
+        let cam_pos = Vec3::new(0., 0., -self.cam_distance);
+
+        // World matrix for all graphs
+        let mut m_world = Mat4::IDENTITY.clone();
+        m_world.rotate_x(90f32.to_radians());
+        m_world.rotate_z(self.cam.1);
+        m_world.rotate_x(self.cam.0);
+
+        // View matrix for all graphs
+        let m_view = {
+            let mut m_view = Mat4::new();
+            m_view.look_at(cam_pos, Vec3::new(0., 0., 0.), Vec3::UP);
+            m_view
+        };
+
+        // Projection matrix for all graphs
+        let m_proj = {
+            let mut m_proj = Mat4::new();
+            // temp values
+            let client_width = self.canvas.client_width() as f32;
+            let client_height = self.canvas.client_height() as f32;
+
+            m_proj.perspective(
+                FOV.to_radians(),
+                client_width / client_height,
+                0.01,
+                99999.,
+            );
+            m_proj
+        };
+
+        Transform {
+            proj: m_proj,
+            view: m_view,
+            model: m_world,
+        }
+    }
+
+    /// Draws every dataset's sprites, with the picked point highlighted
+    fn render_data(&self, m_world: &Mat4, m_view: &Mat4, m_proj: &Mat4) {
+        let program = &self.sprite_program;
+        let uni_loc = |name: &str| self.gl.get_uniform_location(program, name);
+
+        self.gl.use_program(Some(program));
+        self.gl
+            .uniform_matrix4fv_with_f32_array(uni_loc("mWorld").as_ref(), false, &m_world.0);
+        self.gl
+            .uniform_matrix4fv_with_f32_array(uni_loc("mView").as_ref(), false, &m_view.0);
+        self.gl
+            .uniform_matrix4fv_with_f32_array(uni_loc("mProj").as_ref(), false, &m_proj.0);
+        self.gl.uniform1f(uni_loc("amplitude").as_ref(), self.amplitude);
+        self.gl.uniform1f(uni_loc("pointRadius").as_ref(), DATA_RADIUS);
+        self.axes.set_uniforms(&self.gl, program, self.cam.2);
+
+        for (slot, data) in self.datasets.iter().enumerate() {
+            let (_, sprites, [r, g, b]) = match data {
+                Some(data) => data,
+                None => continue,
+            };
+
+            let picked = match self.picked {
+                Some((set, i)) if set == slot => i as f32,
+                _ => -1.,
+            };
+            self.gl.uniform1f(uni_loc("picked").as_ref(), picked);
+            self.gl.uniform4f(uni_loc("graphColor").as_ref(), *r, *g, *b, 1.);
+
+            sprites.render(&self.gl, &self.billboard);
+        }
+    }
+
+    /// Point in graph space placed in world space, where z is flipped
+    fn graph_to_world(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let freq = self.cam.2;
+
+        [
+            self.axes.x.graph_to_world(x, freq),
+            self.axes.y.graph_to_world(y, freq),
+            self.axes.z.graph_to_world(z, freq) * -self.amplitude,
+        ]
+    }
+
+    /// Domain of an equation in the frame it is drawn in, API overrides take
+    /// priority over its text
+    fn domain_of(&self, equation: &GraphEquation) -> Domain {
+        let domain = match self.domain_overrides.get(equation.slot) {
+            Some(overrides) => equation.domain.merged(overrides),
+            None => equation.domain.clone(),
+        };
+
+        domain.oriented(equation.orientation)
+    }
+
+    /// Checks an equation's domain, with overrides taking priority, fits on
+    /// log axes. Parametric inputs aren't on the axes so aren't checked
+    fn check_domain(
+        &self,
+        equation: &GraphEquation,
+        axes: &Axes,
+        overrides: Option<&Domain>,
+    ) -> Result<(), String> {
+        if !equation.kind.has_axis_inputs() {
+            return Ok(());
+        }
+
+        match overrides {
+            Some(overrides) => equation.domain.merged(overrides).check_log(axes),
+            None => equation.domain.check_log(axes),
+        }
+    }
+
+    /// Ranges of the 2 inputs of a parametric, cylindrical, spherical or
+    /// revolved surface, in the order of its params
+    fn param_ranges(&self, equation: &GraphEquation, domain: &Domain) -> [(f32, f32); 2] {
+        let params = (equation.params[0].as_str(), equation.params[1].as_str());
+
+        match equation.kind {
+            GraphKind::Cylindrical => domain.cylindrical_ranges(params, &self.axes, self.cam.2),
+            GraphKind::Spherical => domain.spherical_ranges(params),
+            GraphKind::Revolution(_) => domain.revolution_ranges(params, &self.axes, self.cam.2),
+            _ => [domain.param_range(params.0), domain.param_range(params.1)],
+        }
+    }
+
+    /// Rebuilds the adaptive plane of any equation whose rectangle or axes
+    /// changed since it was built. Animated equations change every frame, so
+    /// they are drawn with the regular planes instead
+    fn update_adaptive_planes(&mut self, domains: &[Domain]) {
+        self.adaptive_planes.resize_with(self.equations.len(), || None);
+        let scope = Scope::new();
+
+        for (i, domain) in domains.iter().enumerate() {
+            let equation = &self.equations[i];
+            if equation.kind != GraphKind::Explicit || equation.surface.is_animated() {
+                continue;
+            }
+
+            let axes = self.axes.oriented(equation.orientation);
+            let key = (domain.axis_rect(&axes, self.built_zoom), axes);
+
+            if matches!(&self.adaptive_planes[i], Some((old, _)) if *old == key) {
+                continue;
+            }
+
+            let surface = Surface {
+                equation: &self.equations[i].surface,
+                scope: &scope,
+                axes: &axes,
+                rect: key.0,
+            };
+
+            let options =
+                refine_options(&axes, self.built_zoom, ADAPTIVE_MAX_DEPTH, ADAPTIVE_TOLERANCE);
+            let plane = TriMesh::adaptive_plane(&surface, &options);
+            let plane = Mesh::new(&self.gl, &plane.positions, &plane.indices).unwrap_throw();
+
+            if let Some((_, old)) = self.adaptive_planes[i].replace((key, plane)) {
+                old.delete(&self.gl);
+            }
+        }
+    }
+
+    /// Rebuilds the arrows & streamlines of any vector field whose box, axes
+    /// or seeds changed since they were built
+    fn update_fields(&mut self, domains: &[Domain]) {
+        self.fields.resize_with(self.equations.len(), || None);
+        let scope = Scope::new();
+
+        for (i, domain) in domains.iter().enumerate() {
+            let equation = &self.equations[i];
+            if equation.kind != GraphKind::Field {
+                continue;
+            }
+
+            let seeds = self.field_seeds.get(equation.slot).cloned().unwrap_or_default();
+            let key = (domain.bounds(&self.axes, self.built_zoom), self.axes, seeds);
+
+            if matches!(&self.fields[i], Some((old, ..)) if *old == key) {
+                continue;
+            }
+
+            let field = Field::new(&equation.surface, &scope).unwrap_throw();
+            let (arrows, streamlines) = field_instances(&field, &key);
+
+            let glyph = |(verticies, indecies): (Vec<f32>, Vec<u32>), instances: &[f32]| {
+                Arrows::new(&self.gl, &equation.program, (&verticies, &indecies), instances)
+                    .unwrap_throw()
+            };
+            let arrows = glyph(ShaderGenerator::generate_arrow(ARROW_SIDES), &arrows);
+            let streamlines = glyph(ShaderGenerator::generate_tube(TUBE_SIDES), &streamlines);
+
+            if let Some((_, arrows, streamlines)) =
+                self.fields[i].replace((key, arrows, streamlines))
+            {
+                arrows.delete(&self.gl);
+                streamlines.delete(&self.gl);
+            }
+        }
+    }
+
+    /// Rebuilds the shapes of any vector, line or plane whose box changed
+    /// since they were built
+    fn update_primitives(&mut self, domains: &[Domain]) {
+        self.primitives.resize_with(self.equations.len(), || None);
+
+        for (i, domain) in domains.iter().enumerate() {
+            let equation = &self.equations[i];
+            let bounds = domain.bounds(&self.axes, self.cam.2);
+
+            if matches!(&self.primitives[i], Some((old, _)) if *old == bounds) {
+                continue;
+            }
+
+            // a single instance from one end to the other (see render::arrows)
+            let instance = |from: [f32; 3], to: [f32; 3]| {
+                let vector = [to[0] - from[0], to[1] - from[1], to[2] - from[2]];
+                [&from[..], &vector[..], &[1.]].concat()
+            };
+            let glyph = |(verticies, indecies): (Vec<f32>, Vec<u32>), instances: &[f32]| {
+                let arrows =
+                    Arrows::new(&self.gl, &equation.program, (&verticies, &indecies), instances);
+                Shape::Arrow(arrows.unwrap_throw())
+            };
+
+            let shape = match equation.primitive {
+                Some(Primitive::Vector { tail, head }) => {
+                    glyph(ShaderGenerator::generate_arrow(ARROW_SIDES), &instance(tail, head))
+                }
+                // lines that miss the box have no instances
+                Some(Primitive::Line { point, direction }) => {
+                    let instances = Primitive::clip_line(point, direction, bounds)
+                        .map(|(start, end)| instance(start, end))
+                        .unwrap_or_default();
+                    glyph(ShaderGenerator::generate_tube(TUBE_SIDES), &instances)
+                }
+                Some(Primitive::Plane { normal, point }) => {
+                    let corners = Primitive::clip_plane(normal, point, bounds);
+                    Shape::Quad(PlaneQuad::new(&self.gl, &corners).unwrap_throw())
+                }
+                _ => continue,
+            };
+
+            if let Some((_, old)) = self.primitives[i].replace((bounds, shape)) {
+                old.delete(&self.gl);
+            }
+        }
+    }
+
+    /// Pixels the box around an equation's domain (reaching the ends of the
+    /// z axis) covers on screen along its longer side, measured between its
+    /// projected corners. Boxes reaching behind the camera fill the screen
+    fn pixels_covered(
+        &self,
+        transform: &Transform,
+        equation: &GraphEquation,
+        domain: &Domain,
+    ) -> f32 {
+        let freq = self.grid.freq;
+        let axes = self.axes.oriented(equation.orientation);
+        let ((x_min, x_max), (y_min, y_max)) = domain.rect(&axes, freq);
+        let height = 0.5 * axes.z.stretch * self.amplitude;
+
+        let local_min = [
+            axes.x.graph_to_world(x_min, freq),
+            axes.y.graph_to_world(y_min, freq),
+            -height,
+        ];
+        let local_max = [
+            axes.x.graph_to_world(x_max, freq),
+            axes.y.graph_to_world(y_max, freq),
+            height,
+        ];
+
+        let (width, screen_height) = (
+            self.canvas.client_width() as f32,
+            self.canvas.client_height() as f32,
+        );
+        let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+
+        for corner in 0..8 {
+            // the box is in the frame of the surface, see GraphEquation::drawn
+            let mut point = [0., 0., 0., 1.];
+            for (k, &axis) in equation.orientation.iter().enumerate() {
+                let ends = (local_min[k], local_max[k]);
+                point[axis] = if corner & (1 << k) == 0 { ends.0 } else { ends.1 };
+            }
+
+            let view = transform.view.transform(transform.model.transform(point));
+            let [x, y, _, w] = transform.proj.transform(view);
+            if w <= 0. {
+                return f32::INFINITY;
+            }
+
+            let screen = [(x / w + 1.) / 2. * width, (1. - y / w) / 2. * screen_height];
+            for i in 0..2 {
+                min[i] = min[i].min(screen[i]);
+                max[i] = max[i].max(screen[i]);
+            }
+        }
+
+        (max[0] - min[0]).max(max[1] - min[1])
+    }
+
+    /// Corners of the box an implicit surface is drawn in, in world space
+    fn world_box(&self, domain: &Domain) -> ((f32, f32, f32), (f32, f32, f32)) {
+        let freq = self.cam.2;
+        let [x, y, z] = domain.bounds(&self.axes, freq);
+        let ends = |(min, max): (f32, f32), to_world: &dyn Fn(f32) -> f32| {
+            let (a, b) = (to_world(min), to_world(max));
+            (a.min(b), a.max(b))
+        };
+
+        let x = ends(x, &|v| self.axes.x.graph_to_world(v, freq));
+        let y = ends(y, &|v| self.axes.y.graph_to_world(v, freq));
+        let z = ends(z, &|v| self.axes.z.graph_to_world(v, freq) * -self.amplitude);
+
+        ((x.0, y.0, z.0), (x.1, y.1, z.1))
+    }
+
+    /// Plane mesh with the given (columns, rows) & poles, created the first
+    /// time it is needed
+    fn plane(&mut self, resolution: PlaneKey) -> &Mesh {
+        if let Some(i) = self.planes.iter().position(|(res, _)| *res == resolution) {
+            return &self.planes[i].1;
+        }
+
+        let ((cols, rows), poles) = resolution;
+        let (verticies, indecies) = match poles {
+            (false, false) => ShaderGenerator::generate_grid(cols, rows),
+            poles => ShaderGenerator::generate_polar(cols, rows, poles),
+        };
+        let plane = Mesh::new(&self.gl, &verticies, &indecies).unwrap_throw();
+
+        self.planes.push((resolution, plane));
+        &self.planes[self.planes.len() - 1].1
+    }
+}
+
+impl Shape {
+    fn delete(&self, gl: &WebGl2RenderingContext) {
+        match self {
+            Shape::Arrow(arrows) => arrows.delete(gl),
+            Shape::Quad(quad) => quad.delete(gl),
+        }
+    }
+}
+
+/// Name & primitive of an equation that names a point, vector or plane, which
+/// the equations below it can use
+fn definition(equation: &GraphEquation) -> Option<(String, Primitive)> {
+    match (equation.primitive, &equation.ast) {
+        (Some(primitive), ParseNode::FunctionDefine(name, args, _)) if args.is_empty() => {
+            Some((name.clone(), primitive))
+        }
+        _ => None,
+    }
+}
+
+/// Plane the walls of a region are drawn with, wrapped around the edge of the
+/// plane its faces are drawn with. Each side gets as many cells as the longer
+/// side of the faces so the corners land on vertices
+fn walls_of(((cols, rows), _): PlaneKey) -> PlaneKey {
+    ((4 * cols.max(rows), 1), (false, false))
+}
+
+/// Instances of the arrows on the lattice of a vector field & the pieces of
+/// its streamlines (see render::arrows). Strengths are relative to the
+/// strongest arrow
+fn field_instances(field: &Field, (bounds, axes, seeds): &FieldKey) -> (Vec<f32>, Vec<f32>) {
+    let lattice: Vec<([f32; 3], [f32; 3])> = Field::lattice(*bounds, axes, FIELD_LATTICE)
+        .into_iter()
+        .map(|p| (p, field.at(p)))
+        .collect();
+
+    let strongest = lattice
+        .iter()
+        .map(|(_, v)| magnitude(*v))
+        .filter(|m| m.is_finite())
+        .fold(0f32, f32::max);
+    let strength = |v: [f32; 3]| match magnitude(v) / strongest {
+        s if s.is_finite() => s.min(1.),
+        _ => 0.,
+    };
+
+    let mut arrows = Vec::with_capacity(lattice.len() * INSTANCE_SIZE);
+    for (p, v) in lattice.iter() {
+        arrows.extend_from_slice(p);
+        arrows.extend_from_slice(v);
+        arrows.push(strength(*v));
+    }
+
+    let diagonal = bounds
+        .iter()
+        .map(|(min, max)| (max - min) * (max - min))
+        .sum::<f32>()
+        .sqrt();
+    let step = diagonal / STREAMLINE_STEPS as f32;
+
+    let mut streamlines = vec![];
+    for seed in seeds.iter() {
+        let line = field.streamline(*seed, step, STREAMLINE_STEPS, *bounds);
+
+        for pair in line.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            streamlines.extend_from_slice(&a);
+            streamlines.extend_from_slice(&[b[0] - a[0], b[1] - a[1], b[2] - a[2]]);
+            streamlines.push(strength(field.at(a)));
+        }
+    }
+
+    (arrows, streamlines)
+}
+
+/// Refinement settings with tolerances scaled to the z axis
+fn refine_options(axes: &Axes, freq: f32, max_depth: u32, tolerance: f32) -> RefineOptions {
+    let (_, z_scale) = axes.z.span(freq);
+
+    RefineOptions {
+        min_depth: 3,
+        max_depth,
+        tolerance: z_scale * tolerance,
+    }
 }
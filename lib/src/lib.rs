@@ -1,5 +1,7 @@
+mod eval;
 mod grapher;
 mod math;
+mod mesh;
 mod parser;
 mod render;
 mod shaders;
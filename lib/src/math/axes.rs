@@ -0,0 +1,176 @@
+/*
+ * Per axis scaling of the graph, each axis can have its own range, stretch &
+ * be logarithmic. Mirrors the mapping in shaders/src/axes.glsl so CPU
+ * evaluation lines up with what is rendered
+ */
+
+use web_sys::{WebGl2RenderingContext, WebGlProgram};
+
+pub const AXES_GLSL: &str = include_str!("../shaders/src/axes.glsl");
+
+/// Names of the axes, in order
+pub const AXIS_NAMES: [&str; 3] = ["x", "y", "z"];
+
+/// Axis (0 for x, 1 for y & 2 for z) that the 2 inputs & the value of an
+/// explicit surface lie along, so x(y, z) = ... is [1, 2, 0]
+pub type Orientation = [usize; 3];
+
+/// Orientation of z = f(x, y)
+pub const UPRIGHT: Orientation = [0, 1, 2];
+
+/// Scaling of a single axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Axis {
+    /// Graph values at either end of the axis, if None the axis is centered
+    /// on the origin & as wide as the zoom
+    pub range: Option<(f32, f32)>,
+    /// Length of the axis in world space, relative to the default
+    pub stretch: f32,
+    /// Log axes space out powers of 10 evenly
+    pub log: bool,
+}
+
+impl Default for Axis {
+    fn default() -> Self {
+        Self {
+            range: None,
+            stretch: 1.,
+            log: false,
+        }
+    }
+}
+
+impl Axis {
+    /// Pairs up the ends of a range, which are given together or not at all
+    pub fn range(min: Option<f32>, max: Option<f32>) -> Result<Option<(f32, f32)>, String> {
+        match (min, max) {
+            (Some(min), Some(max)) => Ok(Some((min, max))),
+            (None, None) => Ok(None),
+            _ => Err("Axis ranges need both a min & a max".into()),
+        }
+    }
+
+    pub fn new(range: Option<(f32, f32)>, stretch: f32, log: bool) -> Result<Self, String> {
+        if let Some((min, max)) = range {
+            if !(min.is_finite() && max.is_finite()) || min >= max {
+                return Err(format!("Invalid axis range [{}, {}]", min, max));
+            }
+
+            if log && min <= 0. {
+                return Err("Logarithmic axes must only contain positive values".into());
+            }
+        }
+
+        if !stretch.is_finite() || stretch <= 0. {
+            return Err("Axis stretch must be positive".into());
+        }
+
+        Ok(Self {
+            range,
+            stretch,
+            log,
+        })
+    }
+
+    /// Graph value -> axis coordinate, which is log10 on log axes
+    pub fn axis_coord(&self, value: f32) -> f32 {
+        if self.log {
+            value.log10()
+        } else {
+            value
+        }
+    }
+
+    /// Axis coordinate -> graph value
+    pub fn axis_value(&self, coord: f32) -> f32 {
+        if self.log {
+            10f32.powf(coord)
+        } else {
+            coord
+        }
+    }
+
+    /// Center & length of the axis in axis coordinates, freq is the width
+    /// used when no range is set
+    pub fn span(&self, freq: f32) -> (f32, f32) {
+        match self.range {
+            Some((min, max)) => {
+                let (min, max) = (self.axis_coord(min), self.axis_coord(max));
+                ((min + max) / 2., max - min)
+            }
+            None => (0., freq),
+        }
+    }
+
+    /// Point on the unit plane (-0.5 to 0.5) -> graph value
+    pub fn plane_to_graph(&self, plane: f32, freq: f32) -> f32 {
+        let (offset, scale) = self.span(freq);
+        self.axis_value(offset + plane * scale)
+    }
+
+    /// Graph value -> world position, NaN when outside of a log axis
+    pub fn graph_to_world(&self, value: f32, freq: f32) -> f32 {
+        let (offset, scale) = self.span(freq);
+        (self.axis_coord(value) - offset) / scale * self.stretch
+    }
+}
+
+/// Scaling of all 3 axes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Axes {
+    pub x: Axis,
+    pub y: Axis,
+    pub z: Axis,
+}
+
+impl Axes {
+    /// Graph values at either end of the x & y axes
+    pub fn domain(&self, freq: f32) -> ((f32, f32), (f32, f32)) {
+        let ends = |axis: &Axis| {
+            (
+                axis.plane_to_graph(-0.5, freq),
+                axis.plane_to_graph(0.5, freq),
+            )
+        };
+        (ends(&self.x), ends(&self.y))
+    }
+
+    /// Axes in the frame of a surface's orientation, the axes its inputs lie
+    /// along become x & y & the axis of its value becomes z
+    pub fn oriented(&self, orientation: Orientation) -> Axes {
+        let axes = [self.x, self.y, self.z];
+
+        Axes {
+            x: axes[orientation[0]],
+            y: axes[orientation[1]],
+            z: axes[orientation[2]],
+        }
+    }
+
+    /// Sets the axis uniforms declared in axes.glsl, if the program uses them
+    pub fn set_uniforms(&self, gl: &WebGl2RenderingContext, program: &WebGlProgram, freq: f32) {
+        let spans = [self.x.span(freq), self.y.span(freq), self.z.span(freq)];
+        let flag = |log: bool| if log { 1. } else { 0. };
+
+        if let Some(loc) = gl.get_uniform_location(program, "axisOffset") {
+            gl.uniform3f(Some(&loc), spans[0].0, spans[1].0, spans[2].0);
+        }
+
+        if let Some(loc) = gl.get_uniform_location(program, "axisScale") {
+            gl.uniform3f(Some(&loc), spans[0].1, spans[1].1, spans[2].1);
+        }
+
+        if let Some(loc) = gl.get_uniform_location(program, "axisStretch") {
+            gl.uniform3f(Some(&loc), self.x.stretch, self.y.stretch, self.z.stretch);
+        }
+
+        if let Some(loc) = gl.get_uniform_location(program, "axisLog") {
+            gl.uniform3f(
+                Some(&loc),
+                flag(self.x.log),
+                flag(self.y.log),
+                flag(self.z.log),
+            );
+        }
+    }
+}
@@ -0,0 +1,400 @@
+/*
+ * Measured (x, y, z) points read from CSV or JSON, drawn as sprites over the
+ * graphs & picked under the cursor for their coordinates
+ */
+
+// cells of a CSV file are separated by the first of these in its first row,
+// or by spaces & tabs when there are none
+const DELIMITERS: [char; 3] = [',', '\t', ';'];
+
+// points are only 3 levels deep ({"points": [[x, y, z]]}), JSON nested past
+// JSON_NESTING is refused rather than read
+const JSON_NESTING: usize = 64;
+
+/// Measured point in graph space, size scales the sprite it is drawn with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataPoint {
+    pub position: [f32; 3],
+    pub size: f32,
+}
+
+/// Points read from a single file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub points: Vec<DataPoint>,
+}
+
+impl Dataset {
+    /// Reads JSON if text starts with [ or {, CSV otherwise
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let points = match text.trim_start().chars().next() {
+            Some('[') | Some('{') => Self::from_json(text)?.points,
+            _ => Self::from_csv(text)?.points,
+        };
+
+        if points.is_empty() {
+            return Err("No points in the data".into());
+        }
+
+        Ok(Self { points })
+    }
+
+    /// Reads rows of x, y, z & an optional size, separated by commas, tabs,
+    /// semicolons or spaces. A header row picks the columns by name,
+    /// otherwise they are taken in that order. Empty rows & rows starting
+    /// with # are skipped
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let rows: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+
+        // every row is split the same way, empty cells are kept so the ones
+        // after them stay in their column
+        let delimiter = rows
+            .first()
+            .and_then(|(_, line)| DELIMITERS.iter().copied().find(|&d| line.contains(d)));
+        let split = |line: &str| -> Vec<String> {
+            let cells: Vec<&str> = match delimiter {
+                Some(delimiter) => line.split(delimiter).collect(),
+                None => line.split_whitespace().collect(),
+            };
+            cells
+                .iter()
+                .map(|cell| cell.trim().trim_matches('"').to_string())
+                .collect()
+        };
+
+        let mut columns = [Some(0), Some(1), Some(2), Some(3)];
+        let mut points = vec![];
+
+        for (n, (line_number, line)) in rows.into_iter().enumerate() {
+            let cells = split(line);
+
+            // header names the columns, x, y & z have to be there
+            let named = |cell: &String| !cell.is_empty() && cell.parse::<f32>().is_err();
+            if n == 0 && cells.iter().any(named) {
+                let find = |name: &str| {
+                    cells
+                        .iter()
+                        .position(|cell| cell.eq_ignore_ascii_case(name))
+                };
+
+                columns = [find("x"), find("y"), find("z"), find("size")];
+                if columns[..3].iter().any(Option::is_none) {
+                    return Err("The header needs x, y & z columns".into());
+                }
+                continue;
+            }
+
+            let cell = |column: Option<usize>| -> Result<Option<f32>, String> {
+                match column.and_then(|i| cells.get(i)) {
+                    Some(cell) if cell.is_empty() => {
+                        Err(format!("Line {}: missing value", line_number))
+                    }
+                    Some(cell) => match cell.parse::<f32>() {
+                        Ok(value) => Ok(Some(value)),
+                        Err(_) => Err(format!("Line {}: {} isn't a number", line_number, cell)),
+                    },
+                    None => Ok(None),
+                }
+            };
+
+            let mut position = [0.; 3];
+            for (axis, column) in columns[..3].iter().enumerate() {
+                position[axis] = cell(*column)?
+                    .ok_or_else(|| format!("Line {} needs 3 numbers (x, y, z)", line_number))?;
+            }
+
+            points.push(DataPoint {
+                position,
+                size: cell(columns[3])?.unwrap_or(1.),
+            });
+        }
+
+        Ok(Self { points })
+    }
+
+    /// Reads an array of points, each either [x, y, z] (with an optional
+    /// size) or {"x": .., "y": .., "z": .., "size": ..}. A single object
+    /// with a "points" array is read the same way
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let json = Json::parse(text)?;
+
+        let items = match json {
+            Json::List(items) => items,
+            Json::Object(fields) => match fields.into_iter().find(|(key, _)| key == "points") {
+                Some((_, Json::List(items))) => items,
+                _ => return Err("Expected an array of points".into()),
+            },
+            _ => return Err("Expected an array of points".into()),
+        };
+
+        let points = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let numbers: Vec<Option<f32>> = match item {
+                    Json::List(values) => values.iter().map(Json::number).collect(),
+                    Json::Object(fields) => ["x", "y", "z", "size"]
+                        .iter()
+                        .map(|name| {
+                            fields
+                                .iter()
+                                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                                .and_then(|(_, value)| value.number())
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+
+                match numbers.as_slice() {
+                    [Some(x), Some(y), Some(z), rest @ ..] => Ok(DataPoint {
+                        position: [*x, *y, *z],
+                        size: rest.first().copied().flatten().unwrap_or(1.),
+                    }),
+                    _ => Err(format!("Point {} needs 3 numbers (x, y, z)", i + 1)),
+                }
+            })
+            .collect::<Result<Vec<DataPoint>, String>>()?;
+
+        Ok(Self { points })
+    }
+
+    /// Index & depth of the point under the cursor (in pixels), project
+    /// places a point on screen as its center, the radius of its sprite (in
+    /// pixels) & its depth, or None when it is behind the camera. The point
+    /// closest to the camera wins where sprites overlap
+    pub fn pick<F>(&self, cursor: [f32; 2], project: F) -> Option<(usize, f32)>
+    where
+        F: Fn(&DataPoint) -> Option<([f32; 2], f32, f32)>,
+    {
+        self.points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, point)| {
+                let ([x, y], radius, depth) = project(point)?;
+                let (dx, dy) = (x - cursor[0], y - cursor[1]);
+
+                if dx * dx + dy * dy <= radius * radius {
+                    Some((i, depth))
+                } else {
+                    None
+                }
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+// ----------------------------------------------------------------------------
+// JSON
+// ----------------------------------------------------------------------------
+
+// the parts of JSON points are written with, anything else is kept as Other
+enum Json {
+    Number(f32),
+    List(Vec<Json>),
+    Object(Vec<(String, Json)>),
+    Other,
+}
+
+impl Json {
+    fn number(&self) -> Option<f32> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Parses text, which is entirely 1 JSON value
+    fn parse(text: &str) -> Result<Json, String> {
+        let mut reader = Reader {
+            bytes: text.as_bytes(),
+            at: 0,
+        };
+        let json = reader.value(JSON_NESTING)?;
+
+        reader.skip_space();
+        if reader.at < reader.bytes.len() {
+            return Err(format!("Unexpected {} in the JSON", reader.rest()));
+        }
+
+        Ok(json)
+    }
+}
+
+// reads a JSON value from bytes, starting at at
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    // reads up to depth levels of lists & objects, deeper ones are an error
+    fn value(&mut self, depth: usize) -> Result<Json, String> {
+        self.skip_space();
+
+        let nested = matches!(self.peek(), Some(b'[') | Some(b'{'));
+        if nested && depth == 0 {
+            return Err("The JSON is nested too deeply".into());
+        }
+
+        match self.peek() {
+            Some(b'[') => {
+                self.at += 1;
+                let mut items = vec![];
+                self.list(b']', |reader| {
+                    items.push(reader.value(depth - 1)?);
+                    Ok(())
+                })?;
+                Ok(Json::List(items))
+            }
+            Some(b'{') => {
+                self.at += 1;
+                let mut fields = vec![];
+                self.list(b'}', |reader| {
+                    reader.skip_space();
+                    if reader.peek() != Some(b'"') {
+                        return Err("Expected a name in the JSON".into());
+                    }
+                    let key = reader.text()?;
+                    reader.expect(b':')?;
+                    fields.push((key, reader.value(depth - 1)?));
+                    Ok(())
+                })?;
+                Ok(Json::Object(fields))
+            }
+            Some(b'"') => self.text().map(|_| Json::Other),
+            Some(b'-') | Some(b'0'..=b'9') => {
+                let start = self.at;
+                while matches!(self.peek(), Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')) {
+                    self.at += 1;
+                }
+
+                let number = String::from_utf8_lossy(&self.bytes[start..self.at]);
+                number
+                    .parse::<f32>()
+                    .map(Json::Number)
+                    .map_err(|_| format!("{} isn't a number", number))
+            }
+            Some(b't') | Some(b'f') | Some(b'n') => {
+                let start = self.at;
+                while matches!(self.peek(), Some(b) if b.is_ascii_alphabetic()) {
+                    self.at += 1;
+                }
+
+                match &self.bytes[start..self.at] {
+                    b"true" | b"false" | b"null" => Ok(Json::Other),
+                    _ => Err(format!("Unexpected {} in the JSON", self.rest())),
+                }
+            }
+            Some(_) => Err(format!("Unexpected {} in the JSON", self.rest())),
+            None => Err("The JSON ended early".into()),
+        }
+    }
+
+    // reads a quoted string at at, decoding its escapes (eg. \" or \u00e9)
+    fn text(&mut self) -> Result<String, String> {
+        self.at += 1;
+        let mut units: Vec<u16> = vec![];
+        let mut start = self.at;
+
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let plain = String::from_utf8_lossy(&self.bytes[start..self.at]);
+                    units.extend(plain.encode_utf16());
+
+                    let escaped = match self.bytes.get(self.at + 1) {
+                        Some(b'"') => '"' as u16,
+                        Some(b'\\') => '\\' as u16,
+                        Some(b'/') => '/' as u16,
+                        Some(b'b') => 0x08,
+                        Some(b'f') => 0x0c,
+                        Some(b'n') => '\n' as u16,
+                        Some(b'r') => '\r' as u16,
+                        Some(b't') => '\t' as u16,
+                        Some(b'u') => {
+                            let hex = self.bytes.get(self.at + 2..self.at + 6).unwrap_or(&[]);
+                            let hex = String::from_utf8_lossy(hex);
+                            let unit = u16::from_str_radix(&hex, 16)
+                                .map_err(|_| format!("\\u{} isn't a character", hex))?;
+                            self.at += 4;
+                            unit
+                        }
+                        _ => return Err(format!("Unexpected {} in the JSON", self.rest())),
+                    };
+
+                    units.push(escaped);
+                    self.at += 2;
+                    start = self.at;
+                }
+                Some(_) => self.at += 1,
+                None => return Err("Missing \" in the JSON".into()),
+            }
+        }
+
+        let plain = String::from_utf8_lossy(&self.bytes[start..self.at]);
+        units.extend(plain.encode_utf16());
+        self.at += 1;
+
+        // escaped surrogate pairs (eg. \ud83d\ude00) join into 1 character
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    // reads comma separated items with item until close
+    fn list<F>(&mut self, close: u8, mut item: F) -> Result<(), String>
+    where
+        F: FnMut(&mut Self) -> Result<(), String>,
+    {
+        self.skip_space();
+        if self.peek() == Some(close) {
+            self.at += 1;
+            return Ok(());
+        }
+
+        loop {
+            item(self)?;
+            self.skip_space();
+
+            match self.peek() {
+                Some(b',') => self.at += 1,
+                Some(b) if b == close => {
+                    self.at += 1;
+                    return Ok(());
+                }
+                Some(_) => return Err(format!("Unexpected {} in the JSON", self.rest())),
+                None => return Err("The JSON ended early".into()),
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_space();
+        if self.peek() != Some(byte) {
+            return Err(format!("Missing {} in the JSON", byte as char));
+        }
+
+        self.at += 1;
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.at).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while matches!(self.peek(), Some(b) if b.is_ascii_whitespace()) {
+            self.at += 1;
+        }
+    }
+
+    // a few characters from at, to point to errors
+    fn rest(&self) -> String {
+        let end = (self.at + 8).min(self.bytes.len());
+        String::from_utf8_lossy(&self.bytes[self.at..end]).into_owned()
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Ranges of the inputs an equation is drawn over, set from the equation text
+ * (eg. `f(x, y) = x*y, x in [0, 1]`) or through the API
+ */
+
+use super::axes::{Axes, Axis, Orientation, AXIS_NAMES};
+use crate::parser::REVOLUTION_ANGLE;
+
+/// Range of parametric inputs without a range, enough to go around a circle
+pub const DEFAULT_PARAM_RANGE: (f32, f32) = (0., std::f32::consts::TAU);
+
+/// Named input ranges, any input without a range follows its axis
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Domain {
+    ranges: Vec<(String, (f32, f32))>,
+}
+
+impl Domain {
+    pub fn new() -> Self {
+        Self { ranges: vec![] }
+    }
+
+    /// Sets the range of an input, overwriting any previous range
+    pub fn set(&mut self, var: &str, range: (f32, f32)) -> Result<(), String> {
+        let (min, max) = range;
+
+        if !(min.is_finite() && max.is_finite()) || min >= max {
+            return Err(format!("Invalid range for {}, [{}, {}]", var, min, max));
+        }
+
+        match self.ranges.iter_mut().find(|(name, _)| name == var) {
+            Some((_, old)) => *old = range,
+            None => self.ranges.push((var.into(), range)),
+        }
+
+        Ok(())
+    }
+
+    /// Checks the ranges of inputs named after log axes only cover positive
+    /// values, as their logs would be NaN otherwise
+    pub fn check_log(&self, axes: &Axes) -> Result<(), String> {
+        let log = [axes.x.log, axes.y.log, axes.z.log];
+
+        for (var, (min, max)) in self.ranges.iter() {
+            let on_log = AXIS_NAMES
+                .iter()
+                .zip(log.iter())
+                .any(|(name, &log)| name == var && log);
+
+            if on_log && *min <= 0. {
+                return Err(format!(
+                    "{} is on a logarithmic axis, so its range [{}, {}] must be positive",
+                    var, min, max
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the range of an input, so it follows its axis again
+    pub fn clear(&mut self, var: &str) {
+        self.ranges.retain(|(name, _)| name != var);
+    }
+
+    pub fn get(&self, var: &str) -> Option<(f32, f32)> {
+        self.ranges
+            .iter()
+            .find(|(name, _)| name == var)
+            .map(|(_, range)| *range)
+    }
+
+    /// Copy of the domain with the ranges of other taking priority
+    pub fn merged(&self, other: &Domain) -> Domain {
+        let mut out = self.clone();
+
+        for (var, range) in other.ranges.iter() {
+            match out.ranges.iter_mut().find(|(name, _)| name == var) {
+                Some((_, old)) => *old = *range,
+                None => out.ranges.push((var.clone(), *range)),
+            }
+        }
+
+        out
+    }
+
+    /// Domain in the frame of a surface's orientation, ranges of the axes
+    /// its inputs lie along are renamed to x & y
+    pub fn oriented(&self, orientation: Orientation) -> Domain {
+        let local = |var: &str| {
+            let found = orientation.iter().position(|&axis| AXIS_NAMES[axis] == var);
+            found.map_or(var.to_string(), |local| AXIS_NAMES[local].to_string())
+        };
+
+        Domain {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|(var, range)| (local(var), *range))
+                .collect(),
+        }
+    }
+
+    /// Range of an input of a parametric equation
+    pub fn param_range(&self, var: &str) -> (f32, f32) {
+        self.get(var).unwrap_or(DEFAULT_PARAM_RANGE)
+    }
+
+    /// Ranges of the radius & angle of a cylindrical surface. Without a range
+    /// the radius reaches the nearest edge of the visible x & y axes
+    pub fn cylindrical_ranges(
+        &self,
+        (r, theta): (&str, &str),
+        axes: &Axes,
+        freq: f32,
+    ) -> [(f32, f32); 2] {
+        let ((x_min, x_max), (y_min, y_max)) = axes.domain(freq);
+        let radius = (x_max - x_min).abs().min((y_max - y_min).abs()) / 2.;
+
+        [self.get(r).unwrap_or((0., radius)), self.param_range(theta)]
+    }
+
+    /// Ranges of the angle from the pole & around the pole of a spherical
+    /// surface, covering the whole sphere without ranges
+    pub fn spherical_ranges(&self, (phi, theta): (&str, &str)) -> [(f32, f32); 2] {
+        [
+            self.get(phi).unwrap_or((0., std::f32::consts::PI)),
+            self.param_range(theta),
+        ]
+    }
+
+    /// Ranges of the inputs of a surface of revolution, in the given order.
+    /// Without ranges the profile covers the visible x axis & the sweep goes
+    /// all the way around
+    pub fn revolution_ranges(
+        &self,
+        (a, b): (&str, &str),
+        axes: &Axes,
+        freq: f32,
+    ) -> [(f32, f32); 2] {
+        let (x, _) = axes.domain(freq);
+        let range = |var: &str| match var {
+            REVOLUTION_ANGLE => self.param_range(var),
+            _ => self.get(var).unwrap_or(x),
+        };
+
+        [range(a), range(b)]
+    }
+
+    /// Rectangle of graph values the x & y inputs cover, falling back to the
+    /// visible part of the axes for inputs without a range
+    pub fn rect(&self, axes: &Axes, freq: f32) -> ((f32, f32), (f32, f32)) {
+        let (x, y) = axes.domain(freq);
+        (self.get("x").unwrap_or(x), self.get("y").unwrap_or(y))
+    }
+
+    /// Same as rect, but in axis coordinates (log10 on log axes)
+    pub fn axis_rect(&self, axes: &Axes, freq: f32) -> ((f32, f32), (f32, f32)) {
+        let ((x_min, x_max), (y_min, y_max)) = self.rect(axes, freq);
+        let coords = |axis: &Axis, min: f32, max: f32| (axis.axis_coord(min), axis.axis_coord(max));
+
+        (coords(&axes.x, x_min, x_max), coords(&axes.y, y_min, y_max))
+    }
+
+    /// Size of the x & y inputs' rectangle in world space
+    pub fn world_size(&self, axes: &Axes, freq: f32) -> (f32, f32) {
+        let ((x_min, x_max), (y_min, y_max)) = self.rect(axes, freq);
+        let size = |axis: &Axis, min: f32, max: f32| {
+            (axis.graph_to_world(max, freq) - axis.graph_to_world(min, freq)).abs()
+        };
+
+        (size(&axes.x, x_min, x_max), size(&axes.y, y_min, y_max))
+    }
+
+    /// Furthest the x & y inputs' rectangle reaches from the origin in world
+    /// space, along each axis
+    pub fn world_extent(&self, axes: &Axes, freq: f32) -> (f32, f32) {
+        let ((x_min, x_max), (y_min, y_max)) = self.rect(axes, freq);
+        let extent = |axis: &Axis, min: f32, max: f32| {
+            let (min, max) = (
+                axis.graph_to_world(min, freq),
+                axis.graph_to_world(max, freq),
+            );
+            min.abs().max(max.abs())
+        };
+
+        (extent(&axes.x, x_min, x_max), extent(&axes.y, y_min, y_max))
+    }
+
+    /// Box of graph values the x, y & z inputs of an implicit surface cover,
+    /// falling back to the visible part of the axes
+    pub fn bounds(&self, axes: &Axes, freq: f32) -> [(f32, f32); 3] {
+        let (x, y) = self.rect(axes, freq);
+        let z = self.get("z").unwrap_or((
+            axes.z.plane_to_graph(-0.5, freq),
+            axes.z.plane_to_graph(0.5, freq),
+        ));
+
+        [x, y, z]
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ * Vector fields F(x, y, z) evaluated on the CPU, for the lattice of arrows
+ * they are drawn with & the streamlines traced through them
+ */
+
+use crate::{eval::Scope, math::axes::Axes, parser::ParseNode};
+
+/// Field F(x, y, z) = (fx, fy, fz), in graph units
+pub struct Field<'a> {
+    pub components: &'a [ParseNode],
+    pub scope: &'a Scope,
+}
+
+impl<'a> Field<'a> {
+    /// Field of an equation, None if the equation isn't a vector field
+    pub fn new(equation: &'a ParseNode, scope: &'a Scope) -> Option<Self> {
+        match equation {
+            ParseNode::FunctionDefine(_, _, body) if equation.is_field() => match &**body {
+                ParseNode::Tuple(components) if components.len() == 3 => {
+                    Some(Self { components, scope })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Vector at p, components are NaN where it is undefined
+    pub fn at(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let scope = self.scope.clone().with("x", x).with("y", y).with("z", z);
+        let value = |i: usize| self.components[i].eval(&scope);
+
+        [value(0), value(1), value(2)]
+    }
+
+    /// Points spread evenly (in axis coordinates) through the box bounds,
+    /// with count points along each axis placed at the centers of the cells
+    pub fn lattice(bounds: [(f32, f32); 3], axes: &Axes, count: usize) -> Vec<[f32; 3]> {
+        let axes = [&axes.x, &axes.y, &axes.z];
+        let ticks: Vec<Vec<f32>> = axes
+            .iter()
+            .zip(bounds.iter())
+            .map(|(axis, &(min, max))| {
+                let (min, max) = (axis.axis_coord(min), axis.axis_coord(max));
+                (0..count)
+                    .map(|i| axis.axis_value(min + (i as f32 + 0.5) / count as f32 * (max - min)))
+                    .collect()
+            })
+            .collect();
+
+        let mut points = Vec::with_capacity(count * count * count);
+        for &z in ticks[2].iter() {
+            for &y in ticks[1].iter() {
+                for &x in ticks[0].iter() {
+                    points.push([x, y, z]);
+                }
+            }
+        }
+
+        points
+    }
+
+    /// Direction of the field at p, None where it vanishes or is undefined
+    fn direction(&self, p: [f32; 3]) -> Option<[f32; 3]> {
+        let v = self.at(p);
+        let length = magnitude(v);
+
+        if !length.is_finite() || length < 1e-6 {
+            return None;
+        }
+
+        Some(v.map(|c| c / length))
+    }
+
+    /// Streamline through seed, traced with RK4 steps of length step (in
+    /// graph units) both along & against the field. Stops after steps in
+    /// each direction, when leaving bounds or where the field vanishes. The
+    /// points run in the direction of the field
+    pub fn streamline(
+        &self,
+        seed: [f32; 3],
+        step: f32,
+        steps: usize,
+        bounds: [(f32, f32); 3],
+    ) -> Vec<[f32; 3]> {
+        let inside = |p: [f32; 3]| {
+            p.iter()
+                .zip(bounds.iter())
+                .all(|(&v, &(min, max))| v >= min.min(max) && v <= min.max(max))
+        };
+
+        if !inside(seed) {
+            return vec![];
+        }
+
+        let trace = |h: f32| {
+            let mut points = vec![];
+            let mut p = seed;
+
+            for _ in 0..steps {
+                match self.rk4(p, h) {
+                    Some(next) if inside(next) => {
+                        points.push(next);
+                        p = next;
+                    }
+                    _ => break,
+                }
+            }
+
+            points
+        };
+
+        let mut line: Vec<[f32; 3]> = trace(-step).into_iter().rev().collect();
+        line.push(seed);
+        line.extend(trace(step));
+
+        line
+    }
+
+    /// Classic 4th order Runge-Kutta step of length h along the direction of
+    /// the field, so streamlines are evenly sampled whatever its strength
+    fn rk4(&self, p: [f32; 3], h: f32) -> Option<[f32; 3]> {
+        let offset = |k: [f32; 3], by: f32| [p[0] + k[0] * by, p[1] + k[1] * by, p[2] + k[2] * by];
+
+        let k1 = self.direction(p)?;
+        let k2 = self.direction(offset(k1, h / 2.))?;
+        let k3 = self.direction(offset(k2, h / 2.))?;
+        let k4 = self.direction(offset(k3, h))?;
+
+        let mut next = p;
+        for i in 0..3 {
+            next[i] += h / 6. * (k1[i] + 2. * k2[i] + 2. * k3[i] + k4[i]);
+        }
+
+        Some(next)
+    }
+}
+
+/// Length of a vector
+pub fn magnitude(v: [f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
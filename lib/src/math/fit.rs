@@ -0,0 +1,284 @@
+/*
+ * Least squares fitting of the free parameters of explicit surfaces (eg. a, b
+ * & c in f(x, y) = a*x^2 + b*y + c) to measured points, with
+ * Levenberg-Marquardt steps & derivatives from the dual evaluator
+ */
+
+use super::geometry::{orientation_of, GraphKind};
+use crate::{
+    eval::Scope,
+    parser::{native::NativeConstant, ParseNode},
+};
+
+const MAX_ITERATIONS: usize = 200;
+// the fit stops once a step lowers the squared error by less than TOLERANCE
+// of itself, or the damping needed to lower it at all passes MAX_DAMPING
+const TOLERANCE: f64 = 1e-9;
+const INITIAL_DAMPING: f64 = 1e-3;
+const MAX_DAMPING: f64 = 1e10;
+// fitting starts from STARTING_VALUE, parameters marked for fitting are drawn
+// with it until they are fitted
+const STARTING_VALUE: f32 = 1.;
+
+/// Fitted parameters of an equation & how well they match the data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fit {
+    /// (name, value) of each free parameter, in the order they first appear
+    pub params: Vec<(String, f32)>,
+    /// Measured - fitted value of each point, along the axis the surface
+    /// gives values on
+    pub residuals: Vec<f32>,
+    /// Coefficient of determination, 1 when every point is on the surface
+    pub r_squared: f32,
+}
+
+impl Fit {
+    /// Fits the free parameters of an explicit surface to points (graph
+    /// values), starting from guesses. Parameters without a guess start at 1
+    pub fn new(
+        equation: &ParseNode,
+        points: &[[f32; 3]],
+        guesses: &[(String, f32)],
+    ) -> Result<Self, String> {
+        let inputs = match equation {
+            ParseNode::FunctionDefine(_, args, _)
+                if args.len() == 2 && GraphKind::of(equation) == GraphKind::Explicit =>
+            {
+                args
+            }
+            _ => return Err("Only surfaces like f(x, y) = a*x + b can be fitted".into()),
+        };
+
+        let names = free_params(equation);
+        if names.is_empty() {
+            return Err("There are no parameters to fit (eg. a & b in f(x, y) = a*x + b)".into());
+        }
+        if points.len() < names.len() {
+            return Err(format!(
+                "Fitting {} parameters needs at least {} points",
+                names.len(),
+                names.len()
+            ));
+        }
+
+        // inputs are read from the axes the head picks, see orientation_of
+        let [a, b, value] = orientation_of(equation);
+        let wrt: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        // residuals & their derivatives with respect to each parameter
+        let evaluate = |params: &[f64]| -> (Vec<f64>, Vec<Vec<f64>>) {
+            let mut scope = Scope::new();
+            for (name, val) in wrt.iter().zip(params.iter()) {
+                scope.set(name, *val as f32);
+            }
+
+            points
+                .iter()
+                .map(|point| {
+                    scope.set(&inputs[0], point[a]);
+                    scope.set(&inputs[1], point[b]);
+
+                    let d = equation.eval_dual(&scope, &wrt, false);
+                    let gradient = d.gradient.iter().map(|&g| g as f64).collect();
+                    (point[value] as f64 - d.value as f64, gradient)
+                })
+                .unzip()
+        };
+        let cost = |residuals: &[f64]| residuals.iter().map(|r| r * r).sum::<f64>();
+
+        let mut params: Vec<f64> = param_values(equation, guesses)
+            .iter()
+            .map(|(_, val)| *val as f64)
+            .collect();
+
+        let (mut residuals, mut jacobian) = evaluate(&params);
+        let mut error = cost(&residuals);
+        if !error.is_finite() {
+            return Err("The equation can't be evaluated at every point".into());
+        }
+
+        // steps solve (JᵀJ + damping * diag(JᵀJ)) step = Jᵀr, leaning towards
+        // gradient descent while the damping is high & Gauss-Newton when low
+        let mut damping = INITIAL_DAMPING;
+        for _ in 0..MAX_ITERATIONS {
+            let n = params.len();
+            let mut normal = vec![vec![0.; n]; n];
+            let mut rhs = vec![0.; n];
+
+            for (r, row) in residuals.iter().zip(jacobian.iter()) {
+                for i in 0..n {
+                    rhs[i] += row[i] * r;
+                    for j in 0..n {
+                        normal[i][j] += row[i] * row[j];
+                    }
+                }
+            }
+
+            let improved = loop {
+                let mut damped = normal.clone();
+                for (i, row) in damped.iter_mut().enumerate() {
+                    row[i] += damping * normal[i][i].max(1e-12);
+                }
+
+                let tried = solve(damped, rhs.clone()).map(|step| {
+                    let next: Vec<f64> =
+                        params.iter().zip(step.iter()).map(|(p, s)| p + s).collect();
+                    let (residuals, jacobian) = evaluate(&next);
+                    (cost(&residuals), next, residuals, jacobian)
+                });
+
+                match tried {
+                    Some((next_error, next, next_residuals, next_jacobian))
+                        if next_error.is_finite() && next_error < error =>
+                    {
+                        let gain = error - next_error;
+
+                        params = next;
+                        residuals = next_residuals;
+                        jacobian = next_jacobian;
+                        error = next_error;
+                        damping = (damping / 10.).max(1e-12);
+
+                        break gain > TOLERANCE * error;
+                    }
+                    _ if damping < MAX_DAMPING => damping *= 10.,
+                    _ => break false,
+                }
+            };
+
+            if !improved {
+                break;
+            }
+        }
+
+        let measured: Vec<f64> = points.iter().map(|point| point[value] as f64).collect();
+        let mean = measured.iter().sum::<f64>() / measured.len() as f64;
+        let total = measured
+            .iter()
+            .map(|m| (m - mean) * (m - mean))
+            .sum::<f64>();
+
+        let r_squared = if total > 0. {
+            1. - error / total
+        } else if error == 0. {
+            1.
+        } else {
+            0.
+        };
+
+        Ok(Self {
+            params: names
+                .into_iter()
+                .zip(params.iter().map(|&p| p as f32))
+                .collect(),
+            residuals: residuals.iter().map(|&r| r as f32).collect(),
+            r_squared: r_squared as f32,
+        })
+    }
+}
+
+/// Names used in an equation's body that aren't its inputs, constants or
+/// time, in the order they first appear
+pub fn free_params(equation: &ParseNode) -> Vec<String> {
+    fn collect(node: &ParseNode, inputs: &[String], names: &mut Vec<String>) {
+        match node {
+            ParseNode::Identifier(name) => {
+                let known = inputs.contains(name)
+                    || NativeConstant::value(name).is_some()
+                    || name == "t"
+                    || name == "TIME";
+
+                if !known && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            ParseNode::Function(_, args) | ParseNode::Tuple(args) => {
+                args.iter().for_each(|arg| collect(arg, inputs, names))
+            }
+            ParseNode::FunctionDefine(_, _, body) | ParseNode::UnaryOp(_, body) => {
+                collect(body, inputs, names)
+            }
+            ParseNode::BinOp(lhs, _, rhs) => {
+                collect(lhs, inputs, names);
+                collect(rhs, inputs, names);
+            }
+            ParseNode::Factor(..) => {}
+        }
+    }
+
+    let mut names = vec![];
+    if let ParseNode::FunctionDefine(_, inputs, body) = equation {
+        collect(body, inputs, &mut names);
+    }
+    names
+}
+
+/// Value of each free parameter of an equation, in the order they first
+/// appear. Parameters without a value in values start at STARTING_VALUE
+pub fn param_values(equation: &ParseNode, values: &[(String, f32)]) -> Vec<(String, f32)> {
+    free_params(equation)
+        .into_iter()
+        .map(|name| {
+            let val = match values.iter().find(|(param, _)| *param == name) {
+                Some((_, val)) => *val,
+                None => STARTING_VALUE,
+            };
+            (name, val)
+        })
+        .collect()
+}
+
+/// Values equations are drawn with, the fitted ones & STARTING_VALUE for the
+/// names marked for fitting that haven't been fitted yet
+pub fn drawn_values(marked: &[String], fitted: &[(String, f32)]) -> Vec<(String, f32)> {
+    let unfitted = marked
+        .iter()
+        .filter(|name| !fitted.iter().any(|(param, _)| param == *name))
+        .map(|name| (name.clone(), STARTING_VALUE));
+
+    fitted.iter().cloned().chain(unfitted).collect()
+}
+
+/// Equation with its free parameters filled in with values, any other free
+/// name isn't defined (eg. yy in f(x, y) = x + yy)
+pub fn fill_params(equation: &ParseNode, values: &[(String, f32)]) -> Result<ParseNode, String> {
+    let known = |name: &String| values.iter().any(|(param, _)| param == name);
+
+    match free_params(equation).into_iter().find(|name| !known(name)) {
+        Some(name) => Err(format!("{} isn't defined", name)),
+        None => Ok(equation.substitute(values)),
+    }
+}
+
+/// Solves a * x = b with gaussian elimination & partial pivoting, None if a
+/// is singular
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-300 || !a[pivot][col].is_finite() {
+            return None;
+        }
+
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot = a[col].clone();
+        for row in (col + 1)..n {
+            let factor = a[row][col] / pivot[col];
+            for (val, above) in a[row].iter_mut().zip(pivot.iter()).skip(col) {
+                *val -= factor * above;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.; n];
+    for row in (0..n).rev() {
+        let known: f64 = ((row + 1)..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - known) / a[row][row];
+    }
+
+    Some(x)
+}
@@ -0,0 +1,134 @@
+/*
+ * Calculations for fitting the view around the graphed equations, picks a
+ * vertical stretch & camera distance so that every surface is on screen
+ */
+
+use super::{axes::Axes, domain::Domain};
+use crate::{
+    eval::{interval::Interval, Scope},
+    parser::ParseNode,
+};
+
+// how many samples are taken along each side of the graph
+const FIT_SAMPLES: usize = 64;
+
+// portion of samples thrown away on each end when a graph has poles
+const OUTLIER_PERCENTILE: f32 = 0.02;
+
+/// Vertical stretch & camera distance that frames the graphs
+#[derive(Debug, Clone, Copy)]
+pub struct ViewFrame {
+    pub amplitude: f32,
+    pub cam_distance: f32,
+}
+
+impl ViewFrame {
+    /// Fits a frame around equations with z values in z_range, placed on the
+    /// axes with a zoom of freq. extent is how far the graphs reach from the
+    /// origin along x & y in world space, fov is the vertical field of view &
+    /// aspect is the width / height of the canvas
+    pub fn fit(
+        z_range: (f32, f32),
+        extent: (f32, f32),
+        axes: &Axes,
+        freq: f32,
+        fov: f32,
+        aspect: f32,
+    ) -> Self {
+        let tallest = [z_range.0, z_range.1]
+            .iter()
+            .map(|z| axes.z.graph_to_world(*z, freq).abs())
+            .filter(|z| z.is_finite())
+            .fold(0f32, f32::max);
+
+        // z is scaled down to fit inside of the same box as x & y, but never
+        // scaled up so flat graphs are not exaggerated
+        let amplitude = if tallest > 0. {
+            (0.5 * axes.z.stretch / tallest).min(1.)
+        } else {
+            1.
+        };
+
+        // bounding sphere of the box the graphs are drawn inside of, which
+        // stays the same size however the world is rotated
+        let (half_width, half_depth) = extent;
+        let half_height = tallest * amplitude;
+        let radius =
+            (half_width * half_width + half_depth * half_depth + half_height * half_height).sqrt();
+
+        // the sphere has to fit in the narrowest of the two fovs
+        let horizontal_fov = 2. * ((fov / 2.).tan() * aspect).atan();
+        let narrowest = fov.min(horizontal_fov);
+
+        Self {
+            amplitude,
+            cam_distance: radius / (narrowest / 2.).sin(),
+        }
+    }
+}
+
+/// Finds the range of z values of every equation over its domain, with a
+/// zoom of freq. Graphs with poles ignore their most extreme samples & values
+/// that can't be placed on the z axis are skipped. Returns None if no
+/// equation has a drawable value
+pub fn z_range(
+    equations: &[(&ParseNode, &Domain)],
+    axes: &Axes,
+    freq: f32,
+    scope: &Scope,
+) -> Option<(f32, f32)> {
+    let mut range: Option<(f32, f32)> = None;
+
+    for (equation, domain) in equations {
+        let mut samples = vec![];
+        let ((x_lo, x_hi), (y_lo, y_hi)) = domain.axis_rect(axes, freq);
+
+        for i in 0..=FIT_SAMPLES {
+            for j in 0..=FIT_SAMPLES {
+                // samples are spaced evenly on the axes, not the graph
+                let along =
+                    |n: usize, lo: f32, hi: f32| lo + (hi - lo) * n as f32 / FIT_SAMPLES as f32;
+                let x = axes.x.axis_value(along(i, x_lo, x_hi));
+                let y = axes.y.axis_value(along(j, y_lo, y_hi));
+
+                let scope = scope.clone().with("x", x).with("y", y);
+                let z = equation.eval(&scope);
+
+                if axes.z.graph_to_world(z, freq).is_finite() {
+                    samples.push(z);
+                }
+            }
+        }
+
+        if samples.is_empty() {
+            continue;
+        }
+
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // if the bounds say the graph could blow up, the extremes are most
+        // likely samples next to a pole so they're thrown away
+        let ((x_min, x_max), (y_min, y_max)) = domain.rect(axes, freq);
+        let ranges = [
+            ("x", Interval::new(x_min, x_max)),
+            ("y", Interval::new(y_min, y_max)),
+        ];
+        let bounds = equation.eval_interval(scope, &ranges);
+
+        let skip = if bounds.undefined {
+            (samples.len() as f32 * OUTLIER_PERCENTILE) as usize
+        } else {
+            0
+        };
+
+        let lo = samples[skip];
+        let hi = samples[samples.len() - 1 - skip];
+
+        range = Some(match range {
+            Some((old_lo, old_hi)) => (old_lo.min(lo), old_hi.max(hi)),
+            None => (lo, hi),
+        });
+    }
+
+    range
+}
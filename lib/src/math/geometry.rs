@@ -2,18 +2,223 @@ use wasm_bindgen::{UnwrapThrowExt, JsValue};
 use web_sys::{WebGl2RenderingContext, WebGlProgram};
 
 use crate::{
-    parser::{lexer::Lexer, parser::Parser, ParseNode},
+    math::{
+        axes::{Orientation, AXES_GLSL, AXIS_NAMES, UPRIGHT},
+        domain::Domain,
+        fit::fill_params,
+        primitives::Primitive,
+    },
+    parser::{lexer::Lexer, parser::Parser, Operator, ParseNode, REVOLUTION_ANGLE},
     render::glutils::{compile_shader, link_program},
 };
 
 // template
 const FRAG_SHADER: &str = include_str!("../shaders/src/equation.frag");
 const VERT_TEMPLATE: &str = include_str!("../shaders/src/equation.vert");
+const IMPLICIT_VERT: &str = include_str!("../shaders/src/implicit.vert");
+const IMPLICIT_FRAG_TEMPLATE: &str = include_str!("../shaders/src/implicit.frag");
+const PARAMETRIC_VERT_TEMPLATE: &str = include_str!("../shaders/src/parametric.vert");
+const CURVE_VERT_TEMPLATE: &str = include_str!("../shaders/src/curve.vert");
+const REGION_VERT_TEMPLATE: &str = include_str!("../shaders/src/region.vert");
+const FIELD_VERT: &str = include_str!("../shaders/src/field.vert");
+const FIELD_FRAG: &str = include_str!("../shaders/src/field.frag");
+const POINT_VERT: &str = include_str!("../shaders/src/point.vert");
+const POINT_FRAG: &str = include_str!("../shaders/src/point.frag");
+const VECTOR_VERT: &str = include_str!("../shaders/src/vector.vert");
+const PLANE_VERT: &str = include_str!("../shaders/src/plane.vert");
+const PRIMITIVE_FRAG: &str = include_str!("../shaders/src/primitive.frag");
+
+// opacity of graphs, regions & planes are see through so what they enclose
+// or hide shows
+const GRAPH_ALPHA: f32 = 0.99;
+const REGION_ALPHA: f32 = 0.45;
+
+/// How an equation is drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphKind {
+    /// z = f(x, y), a plane displaced by the function
+    Explicit,
+    /// F(x, y, z) = 0, ray marched inside of a box
+    Implicit,
+    /// (x, y, z) = p(u, v), a plane stretched over the inputs' ranges & bent
+    /// into the surface
+    Parametric,
+    /// (x, y, z) = c(t), a tube swept along the curve
+    Curve,
+    /// z = f(r, θ), drawn as a parametric surface over a disk
+    Cylindrical,
+    /// ρ = f(θ, φ), drawn as a parametric surface over a sphere
+    Spherical,
+    /// Profile g(x) revolved around an axis (0 for x, 1 for y & 2 for z),
+    /// drawn as a parametric surface. Set by the parser rather than the AST
+    Revolution(usize),
+    /// lower <= z <= upper, a solid between 2 explicit surfaces with walls
+    /// around the edge of its domain
+    Region,
+    /// F(x, y, z) = (fx, fy, fz), a lattice of arrows evaluated on the CPU
+    /// along with any streamlines traced through it
+    Field,
+    /// Named point, drawn as a disc facing the camera
+    Point,
+    /// Named vector, an arrow from its tail to its head
+    Vector,
+    /// Line through a point, clipped to the box it is drawn in
+    Line,
+    /// Plane through a point, clipped to the box it is drawn in
+    Plane,
+}
+
+impl GraphKind {
+    pub fn of(ast: &ParseNode) -> Self {
+        if ast.is_implicit() {
+            GraphKind::Implicit
+        } else if ast.is_region() {
+            GraphKind::Region
+        } else if ast.is_field() {
+            GraphKind::Field
+        } else if ast.is_parametric() {
+            match ast {
+                ParseNode::FunctionDefine(_, args, _) if args.len() == 1 => GraphKind::Curve,
+                _ => GraphKind::Parametric,
+            }
+        } else {
+            match ast {
+                // spherical surfaces are marked by their head, or either mode
+                // by the names of its inputs
+                ParseNode::FunctionDefine(name, args, _) if args.len() == 2 => {
+                    match (name.as_str(), args[0].as_str(), args[1].as_str()) {
+                        ("rho", ..) | (_, "theta", "phi") => GraphKind::Spherical,
+                        (_, "r", "theta") => GraphKind::Cylindrical,
+                        _ => GraphKind::Explicit,
+                    }
+                }
+                _ => GraphKind::Explicit,
+            }
+        }
+    }
+
+    /// Whether the head picks the axes the equation lies along, see
+    /// orientation_of
+    pub fn is_oriented(&self) -> bool {
+        matches!(self, GraphKind::Explicit | GraphKind::Region)
+    }
+
+    /// Whether the inputs are graph axes, so their ranges are on the axes'
+    /// scales rather than parameter ranges
+    pub fn has_axis_inputs(&self) -> bool {
+        !matches!(
+            self,
+            GraphKind::Parametric
+                | GraphKind::Curve
+                | GraphKind::Cylindrical
+                | GraphKind::Spherical
+                | GraphKind::Revolution(_)
+        )
+    }
+
+    /// Kind a point, vector, line or plane is drawn as
+    pub fn of_primitive(primitive: &Primitive) -> Self {
+        match primitive {
+            Primitive::Point(..) => GraphKind::Point,
+            Primitive::Vector { .. } => GraphKind::Vector,
+            Primitive::Line { .. } => GraphKind::Line,
+            Primitive::Plane { .. } => GraphKind::Plane,
+        }
+    }
+
+    /// Whether the graph is see through, which are drawn after the others
+    /// without hiding what is behind them
+    pub fn is_translucent(&self) -> bool {
+        matches!(self, GraphKind::Region | GraphKind::Plane)
+    }
+
+    /// Rewrites cylindrical, spherical & revolved equations as parametric
+    /// surfaces, others are returned as they are. Inputs are reordered so the
+    /// first one is the radius (or angle from the pole), which is where the
+    /// mesh collapses into a single point. Revolved surfaces are ordered so
+    /// their normals point away from the axis
+    pub fn to_parametric(self, ast: &ParseNode) -> ParseNode {
+        let (name, args, body) = match ast {
+            ParseNode::FunctionDefine(name, args, body) => (name, args, &**body),
+            _ => return ast.clone(),
+        };
+
+        let var = |i: usize| ParseNode::Identifier(args[i].clone());
+        let func = |name: &str, arg: ParseNode| ParseNode::Function(name.into(), vec![arg]);
+        let mul = |lhs: ParseNode, rhs: ParseNode| {
+            ParseNode::BinOp(Box::new(lhs), Operator::Mul, Box::new(rhs))
+        };
+
+        let (params, components) = match self {
+            // (r cos θ, r sin θ, f)
+            GraphKind::Cylindrical => (
+                vec![args[0].clone(), args[1].clone()],
+                vec![
+                    mul(var(0), func("cos", var(1))),
+                    mul(var(0), func("sin", var(1))),
+                    body.clone(),
+                ],
+            ),
+            // (ρ sin φ cos θ, ρ sin φ sin θ, ρ cos φ)
+            GraphKind::Spherical => (
+                vec![args[1].clone(), args[0].clone()],
+                vec![
+                    mul(mul(body.clone(), func("sin", var(1))), func("cos", var(0))),
+                    mul(mul(body.clone(), func("sin", var(1))), func("sin", var(0))),
+                    mul(body.clone(), func("cos", var(1))),
+                ],
+            ),
+            // the profile is in the plane of x & the axis, y = g(x) around x
+            GraphKind::Revolution(axis) => {
+                let angle = || ParseNode::Identifier(REVOLUTION_ANGLE.into());
+                let around = |radius: ParseNode| {
+                    (
+                        mul(radius.clone(), func("cos", angle())),
+                        mul(radius, func("sin", angle())),
+                    )
+                };
+
+                let (along, sweep) = (args[0].clone(), REVOLUTION_ANGLE.to_string());
+                match axis {
+                    0 => {
+                        let (y, z) = around(body.clone());
+                        (vec![sweep, along], vec![var(0), y, z])
+                    }
+                    1 => {
+                        let (x, z) = around(var(0));
+                        (vec![along, sweep], vec![x, body.clone(), z])
+                    }
+                    _ => {
+                        let (x, y) = around(var(0));
+                        (vec![sweep, along], vec![x, y, body.clone()])
+                    }
+                }
+            }
+            _ => return ast.clone(),
+        };
+
+        ParseNode::FunctionDefine(name.clone(), params, Box::new(ParseNode::Tuple(components)))
+            .simplify()
+    }
+}
 
 pub struct GraphEquation {
     pub ast: ParseNode,
     pub source: String, // TODO ammend to criterion B
+    pub simplified: String,
     pub color: u8,
+    pub slot: usize,
+    pub domain: Domain,
+    pub kind: GraphKind,
+    // axes the inputs & value of explicit surfaces lie along
+    pub orientation: Orientation,
+    // form of the equation that is drawn, see GraphEquation::drawn
+    pub surface: ParseNode,
+    // inputs of the equation in the order its mesh runs along, the radius (or
+    // angle from the pole) comes first for cylindrical & spherical surfaces
+    pub params: Vec<String>,
+    // what points, vectors, lines & planes resolved to, see math/primitives
+    pub primitive: Option<Primitive>,
     pub program: WebGlProgram,
     pub old: ParseNode,
 }
@@ -22,9 +227,74 @@ impl GraphEquation {
     pub fn new(
         gl: &WebGl2RenderingContext,
         eq: String,
+        slot: usize,
         color: u8,
-        old: Option<ParseNode>,
+        old: Option<(ParseNode, GraphKind)>,
+        primitives: &[(String, Primitive)],
+        values: &[(String, f32)],
     ) -> Result<Self, JsValue> {
+        let (mut equation, vert_shader, frag_shader) =
+            Self::generate(eq, slot, color, old, primitives, values)?;
+
+        if let ParseNode::FunctionDefine(_, _, body) = &equation.surface {
+            crate::log(format!("Rendering: {}", body).as_str());
+        }
+
+        // Setting up rendering program --------------------------------------
+
+        // Compiles shaders
+        let fragment_shader =
+            compile_shader(gl, WebGl2RenderingContext::FRAGMENT_SHADER, frag_shader.as_str())?;
+
+        let vertex_shader = compile_shader(
+            gl,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            vert_shader.as_str(),
+        )?;
+
+        // Binds fragment & vertex shader into the program
+        // as well as links
+        // Throws an error if there was any issues
+        let program = link_program(&gl, &vertex_shader, &fragment_shader)?;
+
+        gl.use_program(Some(&program));
+
+        // asigns color
+        if let Some(color_loc) = gl.get_uniform_location(&program, "graphColor") {
+            // gets color theme from dom
+            //
+            let rgb_arr = crate::theme(11 + color as u32);
+
+            // converts js values doubles to f32
+            let r = rgb_arr.get(0).as_f64().unwrap_throw() as f32;
+            let g = rgb_arr.get(1).as_f64().unwrap_throw() as f32;
+            let b = rgb_arr.get(2).as_f64().unwrap_throw() as f32;
+
+            let alpha = if equation.kind.is_translucent() {
+                REGION_ALPHA
+            } else {
+                GRAPH_ALPHA
+            };
+
+            // pases to shader
+            gl.uniform4f(Some(&color_loc), r, g, b, alpha);
+        }
+
+        equation.program = program;
+        Ok(equation)
+    }
+
+    /// Equation parsed from text along with the source of the vertex &
+    /// fragment shaders it is drawn with, its program is left empty until
+    /// they are compiled (see GraphEquation::new)
+    pub fn generate(
+        eq: String,
+        slot: usize,
+        color: u8,
+        old: Option<(ParseNode, GraphKind)>,
+        primitives: &[(String, Primitive)],
+        values: &[(String, f32)],
+    ) -> Result<(Self, String, String), JsValue> {
         // Parsing Text -------------------------------------------------------
 
         // tokenizes text
@@ -49,72 +319,304 @@ impl GraphEquation {
         }
 
         let ast = ast.unwrap();
-        Parser::validate(&ast)?;
-        let ast_body = match &ast {
+        let domain = parser.domain().clone();
+
+        // points, vectors, lines & planes use the names defined above them
+        // instead of functions. Free parameters of anything else are filled
+        // in with values (see Grapher::set_fit_params & math/fit)
+        let primitive = Primitive::resolve(&ast, primitives)?;
+        let ast = match primitive {
+            Some(_) => ast,
+            None => {
+                let ast = fill_params(&ast, values)?;
+                Parser::validate(&ast)?;
+                ast
+            }
+        };
+
+        // folds constants & applies identities before generating code
+        let ast = ast.simplify();
+        let simplified = ast.readable();
+        let kind = match (&primitive, parser.revolution()) {
+            (Some(primitive), _) => GraphKind::of_primitive(primitive),
+            (None, Some(axis)) => GraphKind::Revolution(axis),
+            (None, None) => GraphKind::of(&ast),
+        };
+        let orientation = if kind.is_oriented() {
+            orientation_of(&ast)
+        } else {
+            UPRIGHT
+        };
+
+        let surface = Self::drawn(&ast, kind, orientation);
+        let params = Parser::get_function_args(&surface);
+
+        let ast_body = match &surface {
             ParseNode::FunctionDefine(_, _, body) => body,
             _ => panic!("Invalid State"),
         };
 
-        crate::log(format!("Rendering: {}", ast_body).as_str());
+        // unwraps old if exists, and if not set old to current. Graphs of
+        // different kinds or inputs can't be lerped between, so they aren't
+        // animated
+        let same_shape = |old: &ParseNode, old_kind: GraphKind| {
+            old_kind == kind
+                && (!kind.is_oriented() || orientation_of(old) == orientation)
+                && Parser::get_function_args(&Self::drawn(old, kind, orientation)) == params
+        };
 
-        // unwraps old if exists, and if not set old to current
-        let old_ast = if let Some(old) = old {
-            // grabs body from old function AST
-            match &old {
-                ParseNode::FunctionDefine(_, _, body) => *body.clone(),
-                _ => panic!("Invalid State"),
+        let old_ast = match old {
+            Some((old, old_kind)) if same_shape(&old, old_kind) => {
+                // grabs body from old function AST
+                match Self::drawn(&old, kind, orientation) {
+                    ParseNode::FunctionDefine(_, _, body) => *body,
+                    _ => panic!("Invalid State"),
+                }
             }
-        } else {
-            *ast_body.clone()
+            _ => *ast_body.clone(),
         };
 
         // Shader Generation -------------------------------------------------
-        let vert_shader = VERT_TEMPLATE
-            .to_string()
-            .replace("$CURRENT_FUNCTION$", format!("{}", ast_body).as_str())
-            .replace("$OLD_FUNCTION$", format!("{}", old_ast).as_str())
-            .replace("$EXTERN_FUNCTIONS$", "");
 
-        // Setting up rendering program --------------------------------------
+        let (vert_shader, frag_shader) = match kind {
+            GraphKind::Implicit => {
+                let frag_shader = IMPLICIT_FRAG_TEMPLATE
+                .to_string()
+                .replace("$CURRENT_FUNCTION$", format!("{}", ast_body).as_str())
+                .replace("$OLD_FUNCTION$", format!("{}", old_ast).as_str())
+                .replace("$EXTERN_FUNCTIONS$", "")
+                .replace("$AXES$", AXES_GLSL);
 
-        // Compiles shaders
-        let fragment_shader =
-            compile_shader(gl, WebGl2RenderingContext::FRAGMENT_SHADER, FRAG_SHADER)?;
+                (IMPLICIT_VERT.to_string(), frag_shader)
+            }
+            GraphKind::Parametric
+            | GraphKind::Cylindrical
+            | GraphKind::Spherical
+            | GraphKind::Revolution(_) => (
+                parametric_vert(ast_body, &old_ast, &params),
+                FRAG_SHADER.to_string(),
+            ),
+            GraphKind::Curve => (
+                curve_vert(ast_body, &old_ast, &params[0]),
+                FRAG_SHADER.to_string(),
+            ),
+            GraphKind::Explicit => (
+                explicit_vert(ast_body, &old_ast, orientation),
+                FRAG_SHADER.to_string(),
+            ),
+            GraphKind::Region => (
+                region_vert(ast_body, &old_ast, orientation),
+                FRAG_SHADER.to_string(),
+            ),
+            GraphKind::Field => (
+                FIELD_VERT.replace("$AXES$", AXES_GLSL),
+                FIELD_FRAG.to_string(),
+            ),
+            GraphKind::Point => (
+                POINT_VERT.replace("$AXES$", AXES_GLSL),
+                POINT_FRAG.to_string(),
+            ),
+            GraphKind::Vector | GraphKind::Line => (
+                VECTOR_VERT.replace("$AXES$", AXES_GLSL),
+                PRIMITIVE_FRAG.to_string(),
+            ),
+            GraphKind::Plane => (
+                PLANE_VERT.replace("$AXES$", AXES_GLSL),
+                PRIMITIVE_FRAG.to_string(),
+            ),
+        };
 
-        let vertex_shader = compile_shader(
-            gl,
-            WebGl2RenderingContext::VERTEX_SHADER,
-            vert_shader.as_str(),
-        )?;
+        let equation = Self {
+            ast,
+            program: JsValue::NULL.into(),
+            source: eq,
+            simplified,
+            color,
+            slot,
+            domain,
+            kind,
+            orientation,
+            surface,
+            params,
+            primitive,
+            old: old_ast,
+        };
 
-        // Binds fragment & vertex shader into the program
-        // as well as links
-        // Throws an error if there was any issues
-        let program = link_program(&gl, &vertex_shader, &fragment_shader)?;
+        Ok((equation, vert_shader, frag_shader))
+    }
 
-        gl.use_program(Some(&program));
+    /// Form of an equation that is drawn & evaluated. Cylindrical & spherical
+    /// surfaces become parametric ones, explicit surfaces are put in the frame
+    /// of their orientation
+    pub fn drawn(ast: &ParseNode, kind: GraphKind, orientation: Orientation) -> ParseNode {
+        let local: Vec<(&str, &str)> = orientation
+            .iter()
+            .zip(AXIS_NAMES.iter())
+            .map(|(&axis, &local)| (AXIS_NAMES[axis], local))
+            .collect();
 
-        // asigns color
-        if let Some(color_loc) = gl.get_uniform_location(&program, "graphColor") {
-            // gets color theme from dom
-            //
-            let rgb_arr = crate::theme(11 + color as u32);
+        kind.to_parametric(ast).rename(&local)
+    }
+}
 
-            // converts js values doubles to f32
-            let r = rgb_arr.get(0).as_f64().unwrap_throw() as f32;
-            let g = rgb_arr.get(1).as_f64().unwrap_throw() as f32;
-            let b = rgb_arr.get(2).as_f64().unwrap_throw() as f32;
+/// Orientation of an explicit surface, set by a head named after the axis of
+/// its value with the other 2 axes as inputs (eg. x(y, z) = y^2 + z^2). For
+/// regions the value is the bounded axis
+pub fn orientation_of(ast: &ParseNode) -> Orientation {
+    let axis = |name: &str| AXIS_NAMES.iter().position(|&axis| axis == name);
 
-            // pases to shader
-            gl.uniform4f(Some(&color_loc), r, g, b, 0.99);
+    match ast {
+        ParseNode::FunctionDefine(_, args, _) if ast.is_region() => {
+            match (axis(&args[0]), axis(&args[1]), axis(&args[2])) {
+                (Some(a), Some(b), Some(value)) => [a, b, value],
+                _ => UPRIGHT,
+            }
         }
+        ParseNode::FunctionDefine(name, args, _) if args.len() == 2 => {
+            match (axis(&args[0]), axis(&args[1]), axis(name)) {
+                (Some(a), Some(b), Some(value)) if a != b && a != value && b != value => {
+                    [a, b, value]
+                }
+                _ => UPRIGHT,
+            }
+        }
+        _ => UPRIGHT,
+    }
+}
 
-        Ok(Self {
-            ast,
-            program,
-            source: eq,
-            color,
-            old: old_ast,
-        })
+/// Partial derivatives of an equation body with respect to x & y, or None if
+/// the body can not be differentiated
+fn gradient(body: &ParseNode) -> Option<(ParseNode, ParseNode)> {
+    let dx = body.derivative("x").ok()?;
+    let dy = body.derivative("y").ok()?;
+    Some((dx, dy))
+}
+
+/// Vertex shader that displaces the plane by an explicit surface z = f(x, y),
+/// in the frame of its orientation
+fn explicit_vert(body: &ParseNode, old: &ParseNode, orientation: Orientation) -> String {
+    // swizzles from the frame of the surface to the graph & back, the names
+    // of the axes double as glsl swizzles
+    let swizzle = |axes: [usize; 3]| axes.iter().map(|&i| AXIS_NAMES[i]).collect::<String>();
+    let local_of = |axis: usize| orientation.iter().position(|&i| i == axis).unwrap_or(axis);
+    let orient = swizzle(UPRIGHT.map(local_of));
+    let to_local = swizzle(orientation);
+
+    // partial derivatives for normals, if either graph can't be
+    // differentiated the shader falls back to sampling normals
+    let gradients = gradient(body).zip(gradient(old));
+
+    let (analytic, (current_dx, current_dy), (old_dx, old_dy)) = match gradients {
+        Some((current, old)) => ("1", current, old),
+        None => {
+            let zero = || ParseNode::Factor(0.);
+            ("0", (zero(), zero()), (zero(), zero()))
+        }
+    };
+
+    VERT_TEMPLATE
+        .to_string()
+        .replace("$CURRENT_FUNCTION$", format!("{}", body).as_str())
+        .replace("$OLD_FUNCTION$", format!("{}", old).as_str())
+        .replace("$ANALYTIC_NORMALS$", analytic)
+        .replace("$CURRENT_DX$", format!("{}", current_dx).as_str())
+        .replace("$CURRENT_DY$", format!("{}", current_dy).as_str())
+        .replace("$OLD_DX$", format!("{}", old_dx).as_str())
+        .replace("$OLD_DY$", format!("{}", old_dy).as_str())
+        .replace("$ORIENT$", orient.as_str())
+        .replace("$TO_LOCAL$", to_local.as_str())
+        .replace("$EXTERN_FUNCTIONS$", "")
+        .replace("$AXES$", AXES_GLSL)
+}
+
+/// Vertex shader that draws the faces & walls of a region lower <= z <= upper
+/// from planes, in the frame of its orientation
+fn region_vert(body: &ParseNode, old: &ParseNode, orientation: Orientation) -> String {
+    let swizzle = |axes: [usize; 3]| axes.iter().map(|&i| AXIS_NAMES[i]).collect::<String>();
+    let local_of = |axis: usize| orientation.iter().position(|&i| i == axis).unwrap_or(axis);
+
+    REGION_VERT_TEMPLATE
+        .to_string()
+        .replace("$CURRENT_FUNCTION$", format!("{}", body).as_str())
+        .replace("$OLD_FUNCTION$", format!("{}", old).as_str())
+        .replace("$ORIENT$", swizzle(UPRIGHT.map(local_of)).as_str())
+        .replace("$EXTERN_FUNCTIONS$", "")
+        .replace("$AXES$", AXES_GLSL)
+}
+
+/// Vertex shader that bends the plane into a parametric surface
+/// (x, y, z) = p(u, v), params are the names of u & v
+fn parametric_vert(body: &ParseNode, old: &ParseNode, params: &[String]) -> String {
+    // inputs are read from the point on the plane, t stays the time unless it
+    // is one of the inputs
+    let mut inputs: Vec<String> = params
+        .iter()
+        .zip(["x", "y"].iter())
+        .map(|(param, component)| format!("float {} = param.{};", param, component))
+        .collect();
+
+    if !params.iter().any(|param| param == "t") {
+        inputs.push("float t = TIME;".into());
+    }
+
+    // derivatives for normals, if either graph can't be differentiated the
+    // shader falls back to sampling normals
+    let derivatives = |node: &ParseNode| {
+        let du = node.derivative(&params[0]).ok()?;
+        let dv = node.derivative(&params[1]).ok()?;
+        Some((du, dv))
+    };
+
+    let (analytic, (current_du, current_dv), (old_du, old_dv)) =
+        match derivatives(body).zip(derivatives(old)) {
+            Some((current, old)) => ("1", current, old),
+            None => {
+                let zero = || ParseNode::Tuple(vec![ParseNode::Factor(0.); 3]);
+                ("0", (zero(), zero()), (zero(), zero()))
+            }
+        };
+
+    PARAMETRIC_VERT_TEMPLATE
+        .to_string()
+        .replace("$PARAMS$", inputs.join("\n    ").as_str())
+        .replace("$CURRENT_FUNCTION$", format!("{}", body).as_str())
+        .replace("$OLD_FUNCTION$", format!("{}", old).as_str())
+        .replace("$ANALYTIC_NORMALS$", analytic)
+        .replace("$CURRENT_DU$", format!("{}", current_du).as_str())
+        .replace("$CURRENT_DV$", format!("{}", current_dv).as_str())
+        .replace("$OLD_DU$", format!("{}", old_du).as_str())
+        .replace("$OLD_DV$", format!("{}", old_dv).as_str())
+        .replace("$EXTERN_FUNCTIONS$", "")
+        .replace("$AXES$", AXES_GLSL)
+}
+
+/// Vertex shader that sweeps a tube along a curve (x, y, z) = c(t), param is
+/// the name of t
+fn curve_vert(body: &ParseNode, old: &ParseNode, param: &str) -> String {
+    let mut inputs = format!("float {} = param;", param);
+    if param != "t" {
+        inputs.push_str("\n    float t = TIME;");
     }
+
+    // derivatives for tangents, if either graph can't be differentiated the
+    // shader falls back to sampling tangents
+    let (analytic, current_dt, old_dt) =
+        match body.derivative(param).ok().zip(old.derivative(param).ok()) {
+            Some((current, old)) => ("1", current, old),
+            None => {
+                let zero = || ParseNode::Tuple(vec![ParseNode::Factor(0.); 3]);
+                ("0", zero(), zero())
+            }
+        };
+
+    CURVE_VERT_TEMPLATE
+        .to_string()
+        .replace("$PARAMS$", inputs.as_str())
+        .replace("$CURRENT_FUNCTION$", format!("{}", body).as_str())
+        .replace("$OLD_FUNCTION$", format!("{}", old).as_str())
+        .replace("$ANALYTIC_TANGENTS$", analytic)
+        .replace("$CURRENT_DT$", format!("{}", current_dt).as_str())
+        .replace("$OLD_DT$", format!("{}", old_dt).as_str())
+        .replace("$EXTERN_FUNCTIONS$", "")
+        .replace("$AXES$", AXES_GLSL)
 }
@@ -187,6 +187,17 @@ impl Mat4 {
             1.,
         ];
     }
+
+    /// Multiplies (x, y, z, w) by the matrix, the same as shaders do
+    pub fn transform(&self, point: [f32; 4]) -> [f32; 4] {
+        let mut out = [0.; 4];
+        for (row, out) in out.iter_mut().enumerate() {
+            *out = (0..MAT4_SIDE_LENGTH)
+                .map(|column| self.0[column * MAT4_SIDE_LENGTH + row] * point[column])
+                .sum();
+        }
+        out
+    }
 }
 
 // Operator Overloads for mat4 and mat4
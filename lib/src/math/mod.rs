@@ -1,3 +1,11 @@
+pub mod axes;
+pub mod data;
+pub mod domain;
+pub mod field;
+pub mod fit;
+pub mod framing;
 pub mod geometry;
 pub mod mat4;
+pub mod primitives;
+pub mod region;
 pub mod vec3;
@@ -0,0 +1,380 @@
+/*
+ * Points, vectors, lines & planes for analytic geometry. They are defined by
+ * name (eg. P = (1, 2, 3), v = vector(P, (1, 1, 0))) & can be used in the
+ * equations below them (eg. L(t) = P + t*v), which are resolved on the CPU
+ */
+
+use crate::{
+    eval::Scope,
+    parser::{Operator, ParseNode, PLANE, VECTOR},
+};
+
+/// Geometric primitive, in graph space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Primitive {
+    Point([f32; 3]),
+    /// Arrow from tail to head
+    Vector {
+        tail: [f32; 3],
+        head: [f32; 3],
+    },
+    /// Line through point along direction
+    Line {
+        point: [f32; 3],
+        direction: [f32; 3],
+    },
+    /// Plane through point facing normal
+    Plane {
+        normal: [f32; 3],
+        point: [f32; 3],
+    },
+}
+
+/// Value of an expression mixing numbers, points & vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Scalar(f32),
+    Point([f32; 3]),
+    Vector([f32; 3]),
+}
+
+impl Primitive {
+    /// Primitive an equation defines, using the named primitives defined
+    /// before it. None if the equation isn't a primitive, which is a named
+    /// point, vector or plane (eg. P = (1, 2, 3)) or a line with a single
+    /// input written with vectors (eg. L(t) = P + t*v)
+    pub fn resolve(
+        ast: &ParseNode,
+        defined: &[(String, Primitive)],
+    ) -> Result<Option<Primitive>, String> {
+        let (args, body) = match ast {
+            ParseNode::FunctionDefine(_, args, body) => (args, &**body),
+            _ => return Ok(None),
+        };
+
+        // only definitions are given without inputs (see Parser::parse), so
+        // names that aren't defined are reported here
+        let scope = Scope::new();
+        if args.is_empty() {
+            return Self::definition(body, defined, &scope).map(Some);
+        }
+        if is_scalar(body, defined) {
+            return Ok(None);
+        }
+
+        match args.len() {
+            // lines are affine in their input, written as vector arithmetic
+            // rather than a vector of components (which are curves)
+            1 if !matches!(body, ParseNode::Tuple(..)) => {
+                let at = |t: f32| value(body, defined, &scope.clone().with(&args[0], t));
+
+                let (start, end, far) = match (at(0.)?, at(1.)?, at(2.)?) {
+                    (Value::Point(a), Value::Point(b), Value::Point(c)) => (a, b, c),
+                    _ => {
+                        let msg = "Lines need a point & a direction (eg. L(t) = P + t*v)";
+                        return Err(msg.into());
+                    }
+                };
+
+                let direction = sub(end, start);
+                let bend = sub(sub(far, end), direction);
+                if length(bend) > 1e-4 * length(direction).max(1.) {
+                    return Err("Lines need to be straight (eg. L(t) = P + t*v)".into());
+                }
+
+                if length(direction) == 0. {
+                    return Err("Lines need a direction that isn't 0".into());
+                }
+
+                Primitive::Line {
+                    point: start,
+                    direction,
+                }
+                .finite()
+                .map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Primitive given a name, its body is a point, vector or plane
+    fn definition(
+        body: &ParseNode,
+        defined: &[(String, Primitive)],
+        scope: &Scope,
+    ) -> Result<Primitive, String> {
+        let coords = |node: &ParseNode| match value(node, defined, scope)? {
+            Value::Point(v) | Value::Vector(v) => Ok(v),
+            Value::Scalar(_) => Err(format!(
+                "Expected a point or vector, found {}",
+                node.readable()
+            )),
+        };
+
+        let primitive: Result<Primitive, String> = match body {
+            ParseNode::Function(name, args) if name == VECTOR => match args.as_slice() {
+                [tail, head] => Ok(Primitive::Vector {
+                    tail: coords(tail)?,
+                    head: coords(head)?,
+                }),
+                _ => Err("vector expects (tail, head)".into()),
+            },
+            ParseNode::Function(name, args) if name == PLANE => match args.as_slice() {
+                [normal, point] => {
+                    let normal = coords(normal)?;
+                    if length(normal) == 0. {
+                        return Err("Planes need a normal that isn't 0".into());
+                    }
+
+                    Ok(Primitive::Plane {
+                        normal,
+                        point: coords(point)?,
+                    })
+                }
+                _ => Err("plane expects (normal, point)".into()),
+            },
+            body => match value(body, defined, scope)? {
+                Value::Point(point) => Ok(Primitive::Point(point)),
+                Value::Vector(head) => Ok(Primitive::Vector {
+                    tail: [0.; 3],
+                    head,
+                }),
+                Value::Scalar(_) => Err(
+                    "Names can only be given to points, vectors & planes (eg. P = (1, 2, 3))"
+                        .into(),
+                ),
+            },
+        };
+
+        primitive?.finite()
+    }
+
+    /// The primitive, or an error if any of its coordinates aren't finite
+    /// (eg. P = (1/0, 0, 0))
+    fn finite(self) -> Result<Primitive, String> {
+        let coords = match self {
+            Primitive::Point(point) => [point, [0.; 3]],
+            Primitive::Vector { tail, head } => [tail, head],
+            Primitive::Line { point, direction } => [point, direction],
+            Primitive::Plane { normal, point } => [normal, point],
+        };
+
+        if coords.iter().flatten().all(|v| v.is_finite()) {
+            Ok(self)
+        } else {
+            Err("Points, vectors, lines & planes need finite coordinates".into())
+        }
+    }
+
+    /// Part of a line inside of bounds (graph values of x, y & z) as its 2
+    /// ends, None if it misses the box
+    pub fn clip_line(
+        point: [f32; 3],
+        direction: [f32; 3],
+        bounds: [(f32, f32); 3],
+    ) -> Option<([f32; 3], [f32; 3])> {
+        let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+
+        for axis in 0..3 {
+            let (min, max) = bounds[axis];
+
+            if direction[axis] == 0. {
+                if point[axis] < min || point[axis] > max {
+                    return None;
+                }
+                continue;
+            }
+
+            let a = (min - point[axis]) / direction[axis];
+            let b = (max - point[axis]) / direction[axis];
+            enter = enter.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+
+        if enter > exit {
+            return None;
+        }
+
+        let at = |t: f32| add(point, scale(direction, t));
+        Some((at(enter), at(exit)))
+    }
+
+    /// Polygon where a plane cuts through bounds (graph values of x, y & z),
+    /// wound counter clockwise around the normal. Empty if it misses the box
+    pub fn clip_plane(normal: [f32; 3], point: [f32; 3], bounds: [(f32, f32); 3]) -> Vec<[f32; 3]> {
+        let corner = |i: usize| {
+            let pick = |axis: usize| match i >> axis & 1 {
+                0 => bounds[axis].0,
+                _ => bounds[axis].1,
+            };
+            [pick(0), pick(1), pick(2)]
+        };
+        let height = |p: [f32; 3]| dot(normal, sub(p, point));
+
+        // the plane crosses each edge of the box whose ends are on either
+        // side of it, edges join corners 1 bit apart
+        let mut polygon: Vec<[f32; 3]> = vec![];
+        for i in 0..8 {
+            for axis in 0..3 {
+                let j = i | 1 << axis;
+                if j == i {
+                    continue;
+                }
+
+                let (a, b) = (corner(i), corner(j));
+                let (ha, hb) = (height(a), height(b));
+                if (ha > 0.) == (hb > 0.) && ha != 0. && hb != 0. {
+                    continue;
+                }
+
+                let travel = if ha == hb { 0. } else { ha / (ha - hb) };
+                let p = add(a, scale(sub(b, a), travel));
+
+                // corners on the plane are shared by several edges
+                if !polygon.iter().any(|q| length(sub(*q, p)) < 1e-6) {
+                    polygon.push(p);
+                }
+            }
+        }
+
+        if polygon.len() < 3 {
+            return vec![];
+        }
+
+        // sorts by the angle around the center, in a frame on the plane
+        let center = scale(
+            polygon.iter().fold([0.; 3], |sum, p| add(sum, *p)),
+            1. / polygon.len() as f32,
+        );
+        let helper = if normal[0].abs() < 0.9 * length(normal) {
+            [1., 0., 0.]
+        } else {
+            [0., 1., 0.]
+        };
+        let u = cross(normal, helper);
+        let v = cross(normal, u);
+        let angle = |p: &[f32; 3]| {
+            let offset = sub(*p, center);
+            dot(offset, v).atan2(dot(offset, u))
+        };
+
+        polygon.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        polygon
+    }
+}
+
+/// Whether a node is an ordinary number, without points, vectors or planes
+fn is_scalar(node: &ParseNode, defined: &[(String, Primitive)]) -> bool {
+    match node {
+        ParseNode::Tuple(..) => false,
+        ParseNode::Identifier(name) => !defined.iter().any(|(def, _)| def == name),
+        ParseNode::Function(name, args) => {
+            name != VECTOR && name != PLANE && args.iter().all(|arg| is_scalar(arg, defined))
+        }
+        ParseNode::FunctionDefine(_, _, body) | ParseNode::UnaryOp(_, body) => {
+            is_scalar(body, defined)
+        }
+        ParseNode::BinOp(lhs, _, rhs) => is_scalar(lhs, defined) && is_scalar(rhs, defined),
+        ParseNode::Factor(..) => true,
+    }
+}
+
+/// Evaluates arithmetic on points & vectors, a point moved by a vector is a
+/// point & the difference of 2 points is a vector
+fn value(
+    node: &ParseNode,
+    defined: &[(String, Primitive)],
+    scope: &Scope,
+) -> Result<Value, String> {
+    if is_scalar(node, defined) {
+        let val = node.eval(scope);
+
+        return match node {
+            ParseNode::Identifier(name) if val.is_nan() => Err(format!("{} isn't defined", name)),
+            _ if val.is_nan() => Err(format!("Can't evaluate {}", node.readable())),
+            _ => Ok(Value::Scalar(val)),
+        };
+    }
+
+    let scalar = |node: &ParseNode| match value(node, defined, scope)? {
+        Value::Scalar(val) => Ok(val),
+        _ => Err(format!("Expected a number, found {}", node.readable())),
+    };
+
+    match node {
+        ParseNode::Tuple(items) => match items.as_slice() {
+            [x, y, z] => Ok(Value::Point([scalar(x)?, scalar(y)?, scalar(z)?])),
+            _ => Err("Points need 3 coordinates (x, y, z)".into()),
+        },
+        ParseNode::Identifier(name) => match defined.iter().find(|(def, _)| def == name) {
+            Some((_, Primitive::Point(p))) => Ok(Value::Point(*p)),
+            Some((_, Primitive::Vector { tail, head })) => Ok(Value::Vector(sub(*head, *tail))),
+            _ => Err(format!("{} can't be used in arithmetic", name)),
+        },
+        ParseNode::Function(name, args) if name == VECTOR => match args.as_slice() {
+            [tail, head] => match (value(tail, defined, scope)?, value(head, defined, scope)?) {
+                (Value::Point(tail), Value::Point(head)) => Ok(Value::Vector(sub(head, tail))),
+                _ => Err("vector expects 2 points (tail, head)".into()),
+            },
+            _ => Err("vector expects (tail, head)".into()),
+        },
+        ParseNode::UnaryOp(Operator::Sub, node) => Ok(match value(node, defined, scope)? {
+            Value::Scalar(val) => Value::Scalar(-val),
+            Value::Point(p) => Value::Point(scale(p, -1.)),
+            Value::Vector(v) => Value::Vector(scale(v, -1.)),
+        }),
+        ParseNode::UnaryOp(_, node) => value(node, defined, scope),
+        ParseNode::BinOp(lhs, op, rhs) => {
+            let (lhs, rhs) = (value(lhs, defined, scope)?, value(rhs, defined, scope)?);
+
+            match (lhs, op, rhs) {
+                (Value::Point(p), Operator::Add, Value::Vector(v))
+                | (Value::Vector(v), Operator::Add, Value::Point(p)) => Ok(Value::Point(add(p, v))),
+                (Value::Point(p), Operator::Sub, Value::Vector(v)) => Ok(Value::Point(sub(p, v))),
+                (Value::Point(a), Operator::Sub, Value::Point(b)) => Ok(Value::Vector(sub(a, b))),
+                (Value::Vector(a), Operator::Add, Value::Vector(b)) => Ok(Value::Vector(add(a, b))),
+                (Value::Vector(a), Operator::Sub, Value::Vector(b)) => Ok(Value::Vector(sub(a, b))),
+                (Value::Scalar(s), Operator::Mul, Value::Vector(v))
+                | (Value::Vector(v), Operator::Mul, Value::Scalar(s)) => {
+                    Ok(Value::Vector(scale(v, s)))
+                }
+                (Value::Vector(v), Operator::Div, Value::Scalar(s)) => {
+                    Ok(Value::Vector(scale(v, 1. / s)))
+                }
+                _ => Err(format!("Can't apply {} to points & vectors like that", op)),
+            }
+        }
+        _ => Err(format!(
+            "Can't use {} with points & vectors",
+            node.readable()
+        )),
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], by: f32) -> [f32; 3] {
+    [a[0] * by, a[1] * by, a[2] * by]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
@@ -0,0 +1,86 @@
+/*
+ * Regions between a lower & upper bound on one axis (eg. x^2 + y^2 <= z <= 4)
+ * evaluated on the CPU, for their volume & exported meshes. Mirrors bounds &
+ * inside in region.vert
+ */
+
+use crate::{eval::Scope, parser::ParseNode};
+
+/// Region lower <= z <= upper over x & y, in the frame of its orientation
+pub struct Region<'a> {
+    pub lower: &'a ParseNode,
+    pub upper: &'a ParseNode,
+    pub scope: &'a Scope,
+    /// Values of z the bounds are clipped to
+    pub range: (f32, f32),
+}
+
+impl<'a> Region<'a> {
+    /// Region of an equation in the form it is drawn (see
+    /// GraphEquation::drawn), None if the equation isn't a region
+    pub fn new(equation: &'a ParseNode, scope: &'a Scope, range: (f32, f32)) -> Option<Self> {
+        let bounds = match equation {
+            ParseNode::FunctionDefine(_, _, body) if equation.is_region() => match &**body {
+                ParseNode::Tuple(bounds) if bounds.len() == 2 => bounds,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            lower: &bounds[0],
+            upper: &bounds[1],
+            scope,
+            range,
+        })
+    }
+
+    /// Lower & upper bound at (x, y) clipped to the range, both are NaN where
+    /// either is undefined
+    pub fn bounds(&self, x: f32, y: f32) -> (f32, f32) {
+        let scope = self.scope.clone().with("x", x).with("y", y);
+        let (lower, upper) = (self.lower.eval(&scope), self.upper.eval(&scope));
+
+        if lower.is_nan() || upper.is_nan() {
+            return (f32::NAN, f32::NAN);
+        }
+
+        let (min, max) = self.range;
+        (lower.clamp(min, max), upper.clamp(min, max))
+    }
+
+    /// Whether the region isn't empty at (x, y)
+    pub fn inside(&self, x: f32, y: f32) -> bool {
+        let (lower, upper) = self.bounds(x, y);
+        lower <= upper
+    }
+
+    /// Distance between the bounds at (x, y), 0 where the region is empty or
+    /// undefined
+    pub fn thickness(&self, x: f32, y: f32) -> f32 {
+        let (lower, upper) = self.bounds(x, y);
+        (upper - lower).max(0.)
+    }
+
+    /// Volume of the region over rect (graph values of x & y), sampled at the
+    /// center of each cell of a grid with resolution cells along each side
+    pub fn volume(&self, rect: ((f32, f32), (f32, f32)), resolution: usize) -> f32 {
+        let ((x_min, x_max), (y_min, y_max)) = rect;
+        let (dx, dy) = (
+            (x_max - x_min) / resolution as f32,
+            (y_max - y_min) / resolution as f32,
+        );
+
+        // summed in double precision, as there are many small cells
+        let mut sum = 0f64;
+        for i in 0..resolution {
+            for j in 0..resolution {
+                let x = x_min + (i as f32 + 0.5) * dx;
+                let y = y_min + (j as f32 + 0.5) * dy;
+                sum += self.thickness(x, y) as f64;
+            }
+        }
+
+        (sum * (dx * dy) as f64) as f32
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * Detection of breaks in a surface (jumps & poles that change sign) & of the
+ * edges of holes, so meshes aren't stitched across them. Mirrors spansBreak &
+ * snapToEdge in equation.vert
+ */
+
+use crate::{
+    eval::{interval::Interval, Scope},
+    parser::ParseNode,
+};
+
+/// How many times an edge is halved while looking for a break
+pub const BREAK_STEPS: usize = 8;
+
+/// How many times an edge is halved while looking for the edge of the graph
+pub const EDGE_STEPS: usize = 8;
+
+// functions that jump while staying defined, so their bounds can't show it
+const STEP_FUNCTIONS: &[&str] = &["round", "floor", "ceil", "sign", "fract", "mod"];
+
+/// Whether z = f(x, y) may break somewhere over the rectangle of the graph,
+/// so breaks are only looked for where there can be some. Poles leave the
+/// bounds undefined & jumps come from step functions. Animated graphs may
+/// break at any time
+pub fn may_break(equation: &ParseNode, scope: &Scope, rect: ((f32, f32), (f32, f32))) -> bool {
+    fn steps(node: &ParseNode) -> bool {
+        match node {
+            ParseNode::Function(name, args) => {
+                STEP_FUNCTIONS.contains(&name.as_str()) || args.iter().any(steps)
+            }
+            ParseNode::FunctionDefine(_, _, body) | ParseNode::UnaryOp(_, body) => steps(body),
+            ParseNode::BinOp(lhs, _, rhs) => steps(lhs) || steps(rhs),
+            ParseNode::Tuple(items) => items.iter().any(steps),
+            ParseNode::Identifier(..) | ParseNode::Factor(..) => false,
+        }
+    }
+
+    let ((x_min, x_max), (y_min, y_max)) = rect;
+    let ranges = [
+        ("x", Interval::new(x_min.min(x_max), x_min.max(x_max))),
+        ("y", Interval::new(y_min.min(y_max), y_min.max(y_max))),
+    ];
+
+    equation.is_animated()
+        || steps(equation)
+        || equation.eval_interval(scope, &ranges).undefined
+}
+
+/// Whether z = f(x, y) breaks between a & b. The edge is repeatedly halved
+/// towards the half with the larger change in z, a continuous surface changes
+/// half as much each time while across a break the change never shrinks.
+/// Poles that keep their sign (eg. 1/x^2) are drawn as spikes instead of
+/// breaks. Changes smaller than min_jump are never breaks
+pub fn spans_break(
+    f: impl Fn(f32, f32) -> f32,
+    a: (f32, f32),
+    b: (f32, f32),
+    min_jump: f32,
+) -> bool {
+    let (mut a, mut b) = (a, b);
+    let (mut za, mut zb) = (f(a.0, a.1), f(b.0, b.1));
+    let first = (zb - za).abs();
+
+    // points on a pole can't be connected to anything
+    if za.is_infinite() || zb.is_infinite() {
+        return true;
+    }
+
+    // undefined points are left to be handled separately
+    if first.is_nan() || first <= min_jump {
+        return false;
+    }
+
+    for _ in 0..BREAK_STEPS {
+        let m = ((a.0 + b.0) / 2., (a.1 + b.1) / 2.);
+        let zm = f(m.0, m.1);
+
+        if zm.is_nan() {
+            return false;
+        }
+
+        if (zm - za).abs() > (zb - zm).abs() {
+            b = m;
+            zb = zm;
+        } else {
+            a = m;
+            za = zm;
+        }
+    }
+
+    let ratio = (zb - za).abs() / first;
+    let flips = za.signum() != zb.signum();
+
+    ratio > 0.5 && (flips || ratio < 2.)
+}
+
+/// Point on the edge of the graph between outside (where z = f(x, y) isn't
+/// finite) & inside, along with how far it is from outside (0 to 1). The
+/// point returned is always inside the graph
+pub fn find_edge(
+    f: impl Fn(f32, f32) -> f32,
+    outside: (f32, f32),
+    inside: (f32, f32),
+) -> ((f32, f32), f32) {
+    let (mut outside, mut inside) = (outside, inside);
+    let (mut lo, mut hi) = (0., 1.);
+
+    for _ in 0..EDGE_STEPS {
+        let m = ((outside.0 + inside.0) / 2., (outside.1 + inside.1) / 2.);
+        let mid = (lo + hi) / 2.;
+
+        if f(m.0, m.1).is_finite() {
+            inside = m;
+            hi = mid;
+        } else {
+            outside = m;
+            lo = mid;
+        }
+    }
+
+    (inside, hi)
+}
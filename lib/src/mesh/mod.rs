@@ -0,0 +1,610 @@
+/*
+ * Meshes built on the CPU, either planes that are uploaded for the GPU to
+ * displace or full surfaces for exporting
+ */
+
+// Module Definitions ---------------------------------------------------------
+pub mod breaks;
+pub mod polygonize;
+pub mod quadtree;
+
+use std::fmt::Write;
+
+use breaks::find_edge;
+use polygonize::polygonize;
+use quadtree::{Quadtree, RefineOptions, Surface};
+
+use crate::{
+    eval::Scope,
+    math::{
+        axes::{Orientation, UPRIGHT},
+        region::Region,
+        vec3::Vec3,
+    },
+    parser::ParseNode,
+    shaders::shader_generator::ShaderGenerator,
+};
+
+// normals at poles of parametric surfaces are taken this far inside of the
+// plane (-0.5 to 0.5) instead. Partial derivatives are parallel when their
+// cross product is shorter than POLE_TOLERANCE relative to them
+const POLE_INSET: f32 = 0.001;
+const POLE_TOLERANCE: f32 = 1e-6;
+
+/// Triangle mesh, positions & normals are packed xyz. Normals are empty when
+/// the mesh doesn't have any
+#[derive(Debug, Clone, Default)]
+pub struct TriMesh {
+    pub positions: Vec<f32>,
+    pub normals: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl TriMesh {
+    /// Adaptive plane over the unit square centered on the origin (-0.5 to
+    /// 0.5), refined for the surface. Meant to be displaced by the shader,
+    /// triangles spanning a break are dropped so the shader needn't look
+    /// for them
+    pub fn adaptive_plane(surface: &Surface, options: &RefineOptions) -> Self {
+        let (points, indices) = Quadtree::build(surface, options).triangulate();
+        let graph: Vec<(f32, f32)> = points.iter().map(|(u, v)| surface.graph(*u, *v)).collect();
+
+        Self {
+            positions: points
+                .iter()
+                .flat_map(|(u, v)| vec![u - 0.5, v - 0.5, 0.])
+                .collect(),
+            normals: vec![],
+            indices: indices
+                .chunks(3)
+                .filter(|tri| {
+                    !(0..3).any(|k| {
+                        let (a, b) = (graph[tri[k] as usize], graph[tri[(k + 1) % 3] as usize]);
+                        surface.breaks_between(a, b, options.tolerance)
+                    })
+                })
+                .flatten()
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Adaptive mesh of the surface itself in graph space, with normals from
+    /// the gradient. Vertices outside of the graph (NaN) are moved onto its
+    /// edge when they border the graph, triangles spanning a break or
+    /// touching a vertex that is still outside are dropped
+    pub fn adaptive_surface(surface: &Surface, options: &RefineOptions) -> Self {
+        let (points, indices) = Quadtree::build(surface, options).triangulate();
+        let mut graph: Vec<(f32, f32)> =
+            points.iter().map(|(u, v)| surface.graph(*u, *v)).collect();
+        let values: Vec<f32> = graph.iter().map(|(x, y)| surface.value(*x, *y)).collect();
+
+        // snaps to the closest edge towards any vertex it shares a triangle with
+        let mut snapped: Vec<Option<((f32, f32), f32)>> = vec![None; graph.len()];
+        for tri in indices.chunks(3) {
+            for k in 0..3 {
+                let (a, b) = (tri[k] as usize, tri[(k + 1) % 3] as usize);
+
+                for &(out, inside) in [(a, b), (b, a)].iter() {
+                    if !(values[out].is_nan() && values[inside].is_finite()) {
+                        continue;
+                    }
+
+                    let edge = find_edge(|x, y| surface.value(x, y), graph[out], graph[inside]);
+                    if !matches!(snapped[out], Some((_, travel)) if travel <= edge.1) {
+                        snapped[out] = Some(edge);
+                    }
+                }
+            }
+        }
+
+        let mut valid: Vec<bool> = values.iter().map(|z| z.is_finite()).collect();
+        for (i, snap) in snapped.iter().enumerate() {
+            if let Some((point, _)) = snap {
+                graph[i] = *point;
+                valid[i] = true;
+            }
+        }
+
+        let mut out = Self::default();
+
+        for &(x, y) in graph.iter() {
+            let (nx, ny, nz) = surface.normal(x, y);
+            let z = surface.value(x, y);
+
+            // vertices outside of the graph are never referenced, they are
+            // kept finite so the indices don't need remapping
+            out.positions
+                .extend_from_slice(&[x, y, if z.is_finite() { z } else { 0. }]);
+            out.normals.extend_from_slice(&[nx, ny, nz]);
+        }
+
+        for tri in indices.chunks(3) {
+            if !tri.iter().all(|&i| valid[i as usize]) {
+                continue;
+            }
+
+            let breaks = (0..3).any(|k| {
+                let (a, b) = (graph[tri[k] as usize], graph[tri[(k + 1) % 3] as usize]);
+                surface.breaks_between(a, b, options.tolerance)
+            });
+
+            if !breaks {
+                out.indices.extend_from_slice(tri);
+            }
+        }
+
+        out
+    }
+
+    /// Mesh of the implicit surface F(x, y, z) = 0 in graph space, over the
+    /// bounds split into resolution cells along each axis. Normals point
+    /// towards where F is positive
+    pub fn implicit_surface(
+        equation: &ParseNode,
+        scope: &Scope,
+        bounds: [(f32, f32); 3],
+        resolution: usize,
+    ) -> Self {
+        let at = |x: f32, y: f32, z: f32| scope.clone().with("x", x).with("y", y).with("z", z);
+        let (positions, indices) =
+            polygonize(|x, y, z| equation.eval(&at(x, y, z)), bounds, resolution);
+
+        // differences are taken a tenth of a cell apart when the gradient
+        // can't be found exactly
+        let h: Vec<f32> = bounds
+            .iter()
+            .map(|(min, max)| (max - min) / resolution.max(1) as f32 / 10.)
+            .collect();
+
+        let mut normals = Vec::with_capacity(positions.len());
+        for p in positions.chunks(3) {
+            let (x, y, z) = (p[0], p[1], p[2]);
+            let mut grad = equation
+                .eval_dual(&at(x, y, z), &["x", "y", "z"], false)
+                .gradient;
+
+            if !grad.iter().all(|v| v.is_finite()) {
+                let f = |x: f32, y: f32, z: f32| equation.eval(&at(x, y, z));
+                grad = vec![
+                    f(x + h[0], y, z) - f(x - h[0], y, z),
+                    f(x, y + h[1], z) - f(x, y - h[1], z),
+                    f(x, y, z + h[2]) - f(x, y, z - h[2]),
+                ];
+            }
+
+            let len = grad.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if len.is_finite() && len > 0. {
+                normals.extend(grad.iter().map(|v| v / len));
+            } else {
+                normals.extend_from_slice(&[0., 0., 1.]);
+            }
+        }
+
+        Self {
+            positions,
+            normals,
+            indices,
+        }
+    }
+
+    /// Mesh of the parametric surface (x, y, z) = p(u, v) in graph space, params
+    /// are the names of u & v which are split into resolution cells over their
+    /// ranges. Triangles touching undefined points are dropped
+    pub fn parametric_surface(
+        equation: &ParseNode,
+        scope: &Scope,
+        params: &[String],
+        ranges: [(f32, f32); 2],
+        resolution: usize,
+    ) -> Self {
+        let (plane, indices) = ShaderGenerator::generate_grid(resolution, resolution);
+        let wrt = [params[0].as_str(), params[1].as_str()];
+        let components = match equation {
+            ParseNode::FunctionDefine(_, _, body) => match &**body {
+                ParseNode::Tuple(items) => items.as_slice(),
+                _ => &[],
+            },
+            _ => &[],
+        };
+
+        let mut out = Self::default();
+        let mut valid = vec![];
+
+        // position & normal at a point of the plane, the normal is None where
+        // the partial derivatives are parallel
+        let sample = |x: f32, y: f32| {
+            let lerp = |(min, max): (f32, f32), t: f32| min + (max - min) * (t + 0.5);
+            let scope = scope
+                .clone()
+                .with(wrt[0], lerp(ranges[0], x))
+                .with(wrt[1], lerp(ranges[1], y));
+
+            // position & partial derivatives of each component
+            let (mut point, mut du, mut dv) = ([0.; 3], [0.; 3], [0.; 3]);
+            for (i, component) in components.iter().enumerate().take(3) {
+                let d = component.eval_dual(&scope, &wrt, false);
+                point[i] = d.value;
+                du[i] = d.gradient[0];
+                dv[i] = d.gradient[1];
+            }
+
+            // normal of (x, y, z(x, y)) as a parametric surface is
+            // (-dz/dx, -dz/dy, 1), matching explicit surfaces
+            let normal = [
+                du[1] * dv[2] - du[2] * dv[1],
+                du[2] * dv[0] - du[0] * dv[2],
+                du[0] * dv[1] - du[1] * dv[0],
+            ];
+            let len = normal.iter().map(|v| v * v).sum::<f32>().sqrt();
+            let scale = du.iter().chain(dv.iter()).map(|v| v * v).sum::<f32>();
+
+            let normal = if len.is_finite() && len > POLE_TOLERANCE * scale {
+                Some(normal.map(|v| v / len))
+            } else {
+                None
+            };
+
+            (point, normal)
+        };
+
+        for p in plane.chunks(3) {
+            let (point, normal) = sample(p[0], p[1]);
+
+            // the surface doesn't move along one of the inputs at a pole, so
+            // the normal is taken from just inside of the ranges instead
+            let inset = |v: f32| v - v.signum() * POLE_INSET;
+            let normal = normal.or_else(|| sample(inset(p[0]), inset(p[1])).1);
+
+            let finite = point.iter().all(|v| v.is_finite());
+            valid.push(finite);
+            out.positions
+                .extend(point.iter().map(|&v| if finite { v } else { 0. }));
+            out.normals
+                .extend_from_slice(&normal.unwrap_or([0., 0., 1.]));
+        }
+
+        for tri in indices.chunks(3) {
+            if tri.iter().all(|&i| valid[i as usize]) {
+                out.indices.extend_from_slice(tri);
+            }
+        }
+
+        out
+    }
+
+    /// Tube of the given radius around a curve (x, y, z) = c(param) in graph
+    /// space, with samples rings along the range of param & sides vertices
+    /// around each ring. Triangles touching an undefined point are dropped
+    pub fn curve_tube(
+        equation: &ParseNode,
+        scope: &Scope,
+        param: &str,
+        range: (f32, f32),
+        (samples, sides): (usize, usize),
+        radius: f32,
+    ) -> Self {
+        let (plane, indices) = ShaderGenerator::generate_grid(samples, sides);
+        let components = match equation {
+            ParseNode::FunctionDefine(_, _, body) => match &**body {
+                ParseNode::Tuple(items) => items.as_slice(),
+                _ => &[],
+            },
+            _ => &[],
+        };
+
+        // center, direction & side of each ring, the side is carried from
+        // ring to ring so the tube doesn't twist
+        let (min, max) = range;
+        let mut rings: Vec<(Vec3, Vec3, Vec3)> = Vec::with_capacity(samples + 1);
+        for column in 0..=samples {
+            let t = min + (max - min) * column as f32 / samples as f32;
+            let scope = scope.clone().with(param, t);
+
+            // point & derivative of each component, falling back to central
+            // differences when the derivative is undefined
+            let (mut center, mut forward) = ([0.; 3], [0.; 3]);
+            for (i, component) in components.iter().enumerate().take(3) {
+                let d = component.eval_dual(&scope, &[param], false);
+                center[i] = d.value;
+                forward[i] = d.gradient[0];
+
+                if !forward[i].is_finite() {
+                    let delta = (max - min) * 1e-4;
+                    let at = |t: f32| component.eval(&scope.clone().with(param, t));
+                    forward[i] = (at(t + delta) - at(t - delta)) / (2. * delta);
+                }
+            }
+
+            let center = Vec3(center[0], center[1], center[2]);
+            let forward = tube_direction(Vec3(forward[0], forward[1], forward[2]));
+            let side = match rings.last() {
+                Some(&previous) => transport_side(previous, (center, forward)),
+                None => first_side(forward),
+            };
+            rings.push((center, forward, side));
+        }
+
+        let mut out = Self::default();
+        let mut valid = vec![];
+
+        for p in plane.chunks(3) {
+            let column = ((p[0] + 0.5) * samples as f32).round() as usize;
+            let around = (p[1] + 0.5) * std::f32::consts::TAU;
+            let (center, forward, side) = rings[column.min(samples)];
+
+            let (sin, cos) = around.sin_cos();
+            let ring = side * cos + forward * side * sin;
+            let point = center + ring * radius;
+
+            let finite = [point.0, point.1, point.2].iter().all(|v| v.is_finite());
+            valid.push(finite);
+            if finite {
+                out.positions.extend_from_slice(&[point.0, point.1, point.2]);
+            } else {
+                out.positions.extend_from_slice(&[0., 0., 0.]);
+            }
+            out.normals.extend_from_slice(&[ring.0, ring.1, ring.2]);
+        }
+
+        for tri in indices.chunks(3) {
+            if tri.iter().all(|&i| valid[i as usize]) {
+                out.indices.extend_from_slice(tri);
+            }
+        }
+
+        out
+    }
+
+    /// Closed mesh of a region in graph space, in the frame of its
+    /// orientation. The lower & upper faces are grids of resolution cells over
+    /// rect (graph values of x & y), clipped to where the region isn't empty
+    /// with vertices outside moved onto the edge where the faces meet. Walls
+    /// close it off along the edges of rect. Normals point out of the solid
+    pub fn region_solid(
+        region: &Region,
+        rect: ((f32, f32), (f32, f32)),
+        resolution: usize,
+    ) -> Self {
+        let (plane, indices) = ShaderGenerator::generate_grid(resolution, resolution);
+        let ((x_min, x_max), (y_min, y_max)) = rect;
+        let inside = |x: f32, y: f32| if region.inside(x, y) { 0. } else { f32::NAN };
+
+        let mut points: Vec<(f32, f32)> = plane
+            .chunks(3)
+            .map(|p| {
+                (
+                    x_min + (x_max - x_min) * (p[0] + 0.5),
+                    y_min + (y_max - y_min) * (p[1] + 0.5),
+                )
+            })
+            .collect();
+        let mut valid: Vec<bool> = points.iter().map(|&(x, y)| region.inside(x, y)).collect();
+
+        // snaps to the closest edge towards any vertex it shares a triangle with
+        let mut snapped: Vec<Option<((f32, f32), f32)>> = vec![None; points.len()];
+        for tri in indices.chunks(3) {
+            for k in 0..3 {
+                let (a, b) = (tri[k] as usize, tri[(k + 1) % 3] as usize);
+
+                for &(out, inner) in [(a, b), (b, a)].iter() {
+                    if valid[out] || !valid[inner] {
+                        continue;
+                    }
+
+                    let edge = find_edge(inside, points[out], points[inner]);
+                    if !matches!(snapped[out], Some((_, travel)) if travel <= edge.1) {
+                        snapped[out] = Some(edge);
+                    }
+                }
+            }
+        }
+
+        for (i, snap) in snapped.iter().enumerate() {
+            if let Some((point, _)) = snap {
+                points[i] = *point;
+                valid[i] = true;
+            }
+        }
+
+        let mut out = Self::default();
+        let normalize = |v: [f32; 3], fallback: [f32; 3]| {
+            let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+            if len.is_finite() && len > 0. {
+                v.map(|c| c / len)
+            } else {
+                fallback
+            }
+        };
+
+        // the upper face points up & the lower face down, so it is rewound
+        for &(bound, sign) in [(region.lower, -1f32), (region.upper, 1.)].iter() {
+            let offset = (out.positions.len() / 3) as u32;
+
+            for &(x, y) in points.iter() {
+                let scope = region.scope.clone().with("x", x).with("y", y);
+                let d = bound.eval_dual(&scope, &["x", "y"], false);
+
+                // the face is flat where the bound is clipped
+                let (min, max) = region.range;
+                let z = d.value.clamp(min, max);
+                let (fx, fy) = if d.value == z {
+                    (d.gradient[0], d.gradient[1])
+                } else {
+                    (0., 0.)
+                };
+
+                let up = [0., 0., sign];
+                out.positions
+                    .extend_from_slice(&[x, y, if z.is_finite() { z } else { 0. }]);
+                out.normals
+                    .extend_from_slice(&normalize([-fx * sign, -fy * sign, sign], up));
+            }
+
+            for tri in indices.chunks(3) {
+                if !tri.iter().all(|&i| valid[i as usize]) {
+                    continue;
+                }
+
+                let tri = tri.iter().map(|&i| i + offset);
+                if sign > 0. {
+                    out.indices.extend(tri);
+                } else {
+                    out.indices.extend(tri.rev());
+                }
+            }
+        }
+
+        // walls go counter clockwise around rect so they face outwards, & shrink
+        // away where the region is empty
+        let corners = [
+            (x_min, y_min),
+            (x_max, y_min),
+            (x_max, y_max),
+            (x_min, y_max),
+        ];
+        let outwards = [[0., -1., 0.], [1., 0., 0.], [0., 1., 0.], [-1., 0., 0.]];
+
+        for side in 0..4 {
+            let (from, to) = (corners[side], corners[(side + 1) % 4]);
+            let offset = (out.positions.len() / 3) as u32;
+            let mut heights = vec![];
+
+            for i in 0..=resolution {
+                let t = i as f32 / resolution as f32;
+                let (x, y) = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+                let (lower, upper) = region.bounds(x, y);
+                let height = (upper - lower).max(0.);
+
+                for &z in [lower, lower + height].iter() {
+                    out.positions
+                        .extend_from_slice(&[x, y, if z.is_finite() { z } else { 0. }]);
+                    out.normals.extend_from_slice(&outwards[side]);
+                }
+                heights.push(if lower.is_finite() { height } else { f32::NAN });
+            }
+
+            for i in 0..resolution {
+                // undefined or empty on both ends
+                if !(heights[i] > 0. || heights[i + 1] > 0.)
+                    || heights[i].is_nan()
+                    || heights[i + 1].is_nan()
+                {
+                    continue;
+                }
+
+                let (a, b) = (offset + 2 * i as u32, offset + 2 * (i as u32 + 1));
+                out.indices
+                    .extend_from_slice(&[a, b, b + 1, a, b + 1, a + 1]);
+            }
+        }
+
+        out
+    }
+
+    /// Mesh built in the frame of an explicit surface's orientation, moved
+    /// onto the axes its inputs & value lie along. Triangles are rewound when
+    /// the axes are mirrored so they keep facing along their normals
+    pub fn oriented(mut self, orientation: Orientation) -> Self {
+        let swizzle = |values: &mut Vec<f32>| {
+            for v in values.chunks_mut(3) {
+                let local = [v[0], v[1], v[2]];
+                for (i, &axis) in orientation.iter().enumerate() {
+                    v[axis] = local[i];
+                }
+            }
+        };
+
+        swizzle(&mut self.positions);
+        swizzle(&mut self.normals);
+
+        // swapping 2 axes mirrors the mesh, rotating all 3 doesn't
+        let mirrored = (0..3).filter(|&i| orientation[i] != UPRIGHT[i]).count() == 2;
+        if mirrored {
+            for tri in self.indices.chunks_mut(3) {
+                tri.swap(1, 2);
+            }
+        }
+
+        self
+    }
+
+    /// Wavefront OBJ text of the mesh
+    pub fn to_obj(&self) -> String {
+        let mut out = String::new();
+
+        for p in self.positions.chunks(3) {
+            writeln!(out, "v {} {} {}", p[0], p[1], p[2]).unwrap();
+        }
+
+        for n in self.normals.chunks(3) {
+            writeln!(out, "vn {} {} {}", n[0], n[1], n[2]).unwrap();
+        }
+
+        // obj indices start at 1
+        for tri in self.indices.chunks(3) {
+            let (a, b, c) = (tri[0] + 1, tri[1] + 1, tri[2] + 1);
+
+            if self.normals.is_empty() {
+                writeln!(out, "f {} {} {}", a, b, c).unwrap();
+            } else {
+                writeln!(out, "f {0}//{0} {1}//{1} {2}//{2}", a, b, c).unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+/// Unit direction of a tube, along the x axis where forward is undefined
+fn tube_direction(forward: Vec3) -> Vec3 {
+    let forward = forward.normalized();
+    if [forward.0, forward.1, forward.2].iter().all(|v| v.is_finite()) {
+        forward
+    } else {
+        Vec3(1., 0., 0.)
+    }
+}
+
+/// Side of the first ring of a tube, perpendicular to forward
+fn first_side(forward: Vec3) -> Vec3 {
+    let up = if forward.2.abs() < 0.9 {
+        Vec3(0., 0., 1.)
+    } else {
+        Vec3(1., 0., 0.)
+    };
+    (forward * up).normalized()
+}
+
+/// Side of the next ring of a tube, rotated as little as possible from the
+/// previous one by reflecting it twice (double reflection, Wang et al. 2008),
+/// matching curve.vert
+fn transport_side(
+    (center, forward, side): (Vec3, Vec3, Vec3),
+    (next, ahead): (Vec3, Vec3),
+) -> Vec3 {
+    let reflect = |v: Vec3, across: Vec3| {
+        let len = across.dot(across);
+        if len > 0. && len.is_finite() {
+            v - across * (2. * across.dot(v) / len)
+        } else {
+            v
+        }
+    };
+
+    // reflects across the plane between the centers, then across the one
+    // taking the reflected direction onto the next
+    let step = next - center;
+    let side_between = reflect(side, step);
+    let forward_between = reflect(forward, step);
+    let next_side = reflect(side_between, ahead - forward_between);
+
+    // kept perpendicular to the direction, starting over where the curve is
+    // undefined
+    let next_side = (next_side - ahead * ahead.dot(next_side)).normalized();
+    if [next_side.0, next_side.1, next_side.2].iter().all(|v| v.is_finite()) {
+        next_side
+    } else {
+        first_side(ahead)
+    }
+}
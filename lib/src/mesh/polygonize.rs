@@ -0,0 +1,152 @@
+/*
+ * Extraction of the surface F(x, y, z) = 0 from a scalar field with marching
+ * tetrahedra. Every cell of a regular grid is split into 6 tetrahedra along
+ * the same diagonal, so neighbouring cells cut their shared faces the same way
+ * & the mesh has no cracks
+ */
+
+use std::collections::HashMap;
+
+/// Tetrahedra of a cell, corners are numbered with x, y & z in their 1st, 2nd
+/// & 3rd bits. All of them share the diagonal from corner 0 to 7
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 3, 2, 7],
+    [0, 2, 6, 7],
+    [0, 6, 4, 7],
+    [0, 4, 5, 7],
+    [0, 5, 1, 7],
+];
+
+/// Triangles where f(x, y, z) = 0 inside of the bounds (min, max of each
+/// axis), which are split into resolution cells along every axis. Points are
+/// packed xyz & shared between neighbouring triangles, which face towards
+/// where f is positive, laid out like ShaderGenerator::generate_plane. Cells
+/// with undefined (NaN) corners are skipped
+pub fn polygonize(
+    f: impl Fn(f32, f32, f32) -> f32,
+    bounds: [(f32, f32); 3],
+    resolution: usize,
+) -> (Vec<f32>, Vec<u32>) {
+    let n = resolution.max(1);
+    let side = n + 1;
+
+    let lattice = |i: usize, axis: usize| {
+        let (min, max) = bounds[axis];
+        min + (max - min) * i as f32 / n as f32
+    };
+
+    // samples every lattice point once, indexed by x + y*side + z*side^2
+    let mut samples = Vec::with_capacity(side * side * side);
+    for k in 0..side {
+        for j in 0..side {
+            for i in 0..side {
+                samples.push(f(lattice(i, 0), lattice(j, 1), lattice(k, 2)));
+            }
+        }
+    }
+
+    let point = |idx: usize| {
+        let (i, j, k) = (idx % side, idx / side % side, idx / (side * side));
+        [lattice(i, 0), lattice(j, 1), lattice(k, 2)]
+    };
+
+    let mut points = vec![];
+    let mut indecies = vec![];
+
+    // crossings are keyed by the lattice edge they sit on, so triangles on
+    // either side of the edge share the vertex
+    let mut lookup = HashMap::new();
+    let mut crossing = |a: usize, b: usize, points: &mut Vec<f32>| -> u32 {
+        *lookup.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            let (fa, fb) = (samples[a], samples[b]);
+            let t = fa / (fa - fb);
+            let t = if t.is_finite() { t.clamp(0., 1.) } else { 0.5 };
+            let (pa, pb) = (point(a), point(b));
+
+            for axis in 0..3 {
+                points.push(pa[axis] + (pb[axis] - pa[axis]) * t);
+            }
+            (points.len() / 3 - 1) as u32
+        })
+    };
+
+    for k in 0..n {
+        for j in 0..n {
+            for i in 0..n {
+                let base = i + j * side + k * side * side;
+                let corners: Vec<usize> = (0..8)
+                    .map(|c| base + (c & 1) + (c >> 1 & 1) * side + (c >> 2 & 1) * side * side)
+                    .collect();
+
+                if corners.iter().any(|&c| samples[c].is_nan()) {
+                    continue;
+                }
+
+                for tet in TETRAHEDRA.iter() {
+                    let tet = tet.map(|c| corners[c]);
+                    let (inside, outside): (Vec<usize>, Vec<usize>) =
+                        tet.iter().partition(|&&c| samples[c] < 0.);
+
+                    let triangles = match (inside.len(), outside.len()) {
+                        (1, 3) => vec![[
+                            (inside[0], outside[0]),
+                            (inside[0], outside[1]),
+                            (inside[0], outside[2]),
+                        ]],
+                        (3, 1) => vec![[
+                            (inside[0], outside[0]),
+                            (inside[1], outside[0]),
+                            (inside[2], outside[0]),
+                        ]],
+                        (2, 2) => vec![
+                            [
+                                (inside[0], outside[0]),
+                                (inside[0], outside[1]),
+                                (inside[1], outside[1]),
+                            ],
+                            [
+                                (inside[0], outside[0]),
+                                (inside[1], outside[1]),
+                                (inside[1], outside[0]),
+                            ],
+                        ],
+                        _ => vec![],
+                    };
+
+                    for tri in triangles {
+                        let tri = tri.map(|(a, b)| crossing(a, b, &mut points));
+                        let outward = point(outside[0]);
+                        indecies.extend_from_slice(&orient(&points, tri, outward));
+                    }
+                }
+            }
+        }
+    }
+
+    (points, indecies)
+}
+
+/// Winds the triangle so it faces towards the point
+fn orient(points: &[f32], [a, b, c]: [u32; 3], towards: [f32; 3]) -> [u32; 3] {
+    let p = |i: u32| {
+        let i = i as usize * 3;
+        [points[i], points[i + 1], points[i + 2]]
+    };
+    let (pa, pb, pc) = (p(a), p(b), p(c));
+
+    let u = [pb[0] - pa[0], pb[1] - pa[1], pb[2] - pa[2]];
+    let v = [pc[0] - pa[0], pc[1] - pa[1], pc[2] - pa[2]];
+    let normal = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let to = [towards[0] - pa[0], towards[1] - pa[1], towards[2] - pa[2]];
+
+    if normal[0] * to[0] + normal[1] * to[1] + normal[2] * to[2] < 0. {
+        [a, c, b]
+    } else {
+        [a, b, c]
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * Adaptive quadtree over a rectangle of the graph, cells are split where the
+ * surface curves, is steep or its bounds can't be trusted. The tree is kept
+ * balanced (neighbouring leaves differ by at most 1 level) so it can be
+ * triangulated without cracks
+ */
+
+use std::collections::BTreeSet;
+
+use super::breaks::spans_break;
+use crate::{
+    eval::{interval::Interval, Scope},
+    math::axes::Axes,
+    parser::ParseNode,
+};
+
+/// Settings for adaptive refinement
+#[derive(Debug, Clone, Copy)]
+pub struct RefineOptions {
+    /// Every cell is split at least this many times
+    pub min_depth: u32,
+    /// No cell is split more than this many times
+    pub max_depth: u32,
+    /// Largest allowed distance between a flat cell & the surface, estimated
+    /// from the curvature & how much the gradient turns across the cell
+    pub tolerance: f32,
+}
+
+/// Surface z = f(x, y) over a rectangle given in axis coordinates, so cells
+/// are spaced evenly along log axes
+pub struct Surface<'a> {
+    pub equation: &'a ParseNode,
+    pub scope: &'a Scope,
+    pub axes: &'a Axes,
+    pub rect: ((f32, f32), (f32, f32)),
+}
+
+impl<'a> Surface<'a> {
+    /// Point on the unit square -> (x, y) on the graph
+    pub fn graph(&self, u: f32, v: f32) -> (f32, f32) {
+        let ((x_min, x_max), (y_min, y_max)) = self.rect;
+        (
+            self.axes.x.axis_value(x_min + (x_max - x_min) * u),
+            self.axes.y.axis_value(y_min + (y_max - y_min) * v),
+        )
+    }
+
+    pub fn value(&self, x: f32, y: f32) -> f32 {
+        self.equation
+            .eval(&self.scope.clone().with("x", x).with("y", y))
+    }
+
+    /// Partial derivatives of z with respect to x & y
+    pub fn gradient(&self, x: f32, y: f32) -> (f32, f32) {
+        let scope = self.scope.clone().with("x", x).with("y", y);
+        let d = self.equation.eval_dual(&scope, &["x", "y"], false);
+        (d.gradient[0], d.gradient[1])
+    }
+
+    /// Unit normal of the surface, (-dz/dx, -dz/dy, 1) normalized. Gradients
+    /// that blow up on the edge of the graph fall back to differences taken
+    /// on whichever side is inside the graph, so NaN never leaks in
+    pub fn normal(&self, x: f32, y: f32) -> (f32, f32, f32) {
+        let (mut fx, mut fy) = self.gradient(x, y);
+        let z = self.value(x, y);
+
+        if !(fx.is_finite() && fy.is_finite()) && z.is_finite() {
+            let (min, max) = (self.graph(0., 0.), self.graph(1., 1.));
+            let slope = |step: (f32, f32), h: f32| {
+                let forward = self.value(x + step.0, y + step.1);
+                let backward = self.value(x - step.0, y - step.1);
+
+                match (forward.is_finite(), backward.is_finite()) {
+                    (true, _) => (forward - z) / h,
+                    (false, true) => (z - backward) / h,
+                    _ => 0.,
+                }
+            };
+
+            // steps are a small part of the rectangle
+            let (hx, hy) = ((max.0 - min.0).abs() * 1e-4, (max.1 - min.1).abs() * 1e-4);
+            fx = slope((hx, 0.), hx);
+            fy = slope((0., hy), hy);
+        }
+
+        let len = (fx * fx + fy * fy + 1.).sqrt();
+        if len.is_finite() {
+            (-fx / len, -fy / len, 1. / len)
+        } else {
+            (0., 0., 1.)
+        }
+    }
+
+    /// Whether the surface breaks between the points a & b on the graph
+    pub fn breaks_between(&self, a: (f32, f32), b: (f32, f32), min_jump: f32) -> bool {
+        // bounds that are defined & narrow prove there is no break
+        let ranges = [
+            ("x", Interval::new(a.0.min(b.0), a.0.max(b.0))),
+            ("y", Interval::new(a.1.min(b.1), a.1.max(b.1))),
+        ];
+        let bounds = self.equation.eval_interval(self.scope, &ranges);
+
+        if !bounds.undefined && bounds.width() <= min_jump {
+            return false;
+        }
+
+        spans_break(|x, y| self.value(x, y), a, b, min_jump)
+    }
+}
+
+// leaves are identified by (level, x index, y index)
+type Cell = (u32, u32, u32);
+
+pub struct Quadtree {
+    max_depth: u32,
+    leaves: BTreeSet<Cell>,
+}
+
+impl Quadtree {
+    pub fn build(surface: &Surface, options: &RefineOptions) -> Self {
+        let mut tree = Self {
+            max_depth: options.max_depth,
+            leaves: BTreeSet::new(),
+        };
+
+        // depth first refinement from the root
+        let mut stack = vec![(0, 0, 0)];
+        while let Some(cell) = stack.pop() {
+            if Self::should_split(surface, options, cell) {
+                stack.extend(children(cell).iter());
+            } else {
+                tree.leaves.insert(cell);
+            }
+        }
+
+        tree.balance();
+        tree
+    }
+
+    fn should_split(surface: &Surface, options: &RefineOptions, (level, ix, iy): Cell) -> bool {
+        if level < options.min_depth {
+            return true;
+        }
+
+        if level >= options.max_depth {
+            return false;
+        }
+
+        let size = 1. / (1u32 << level) as f32;
+        let (u0, v0) = (ix as f32 * size, iy as f32 * size);
+        let (x0, y0) = surface.graph(u0, v0);
+        let (x1, y1) = surface.graph(u0 + size, v0 + size);
+        let (cx, cy) = surface.graph(u0 + size / 2., v0 + size / 2.);
+
+        // cells that are proven to be flat never need splitting
+        let ranges = [("x", Interval::new(x0, x1)), ("y", Interval::new(y0, y1))];
+        let bounds = surface.equation.eval_interval(surface.scope, &ranges);
+
+        if !bounds.undefined && bounds.width() <= options.tolerance {
+            return false;
+        }
+
+        let scope = surface.scope.clone().with("x", cx).with("y", cy);
+        let d = surface.equation.eval_dual(&scope, &["x", "y"], true);
+
+        // poles & edges of the graph are refined, unless the whole cell is
+        // undefined since there is nothing to draw
+        if bounds.undefined || !d.value.is_finite() {
+            let corners = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)];
+            return d.value.is_finite()
+                || corners
+                    .iter()
+                    .any(|(x, y)| surface.value(*x, *y).is_finite());
+        }
+
+        // breaks are refined so the gap left by dropping them is small, every
+        // edge the cell could be triangulated with is checked
+        let edges = [
+            ((x0, y0), (x1, y0)),
+            ((x1, y0), (x1, y1)),
+            ((x0, y1), (x1, y1)),
+            ((x0, y0), (x0, y1)),
+            ((x1, y0), (x0, y1)),
+        ];
+
+        if edges
+            .iter()
+            .any(|(a, b)| surface.breaks_between(*a, *b, options.tolerance))
+        {
+            return true;
+        }
+
+        let (hx, hy) = ((x1 - x0).abs(), (y1 - y0).abs());
+        let hessian = d.hessian.unwrap_or_default();
+        let (fxx, fxy, fyy) = (hessian[0][0], hessian[0][1], hessian[1][1]);
+
+        // error of linear interpolation is bounded by h^2 / 8 * f''
+        let curvature =
+            0.125 * (fxx.abs() * hx * hx + 2. * fxy.abs() * hx * hy + fyy.abs() * hy * hy);
+
+        if curvature > options.tolerance {
+            return true;
+        }
+
+        // the curvature is only sampled at the center, so features off center
+        // are found by how far the tangent planes at the corners stray from
+        // the tangent plane at the center
+        let (fx, fy) = (d.gradient[0], d.gradient[1]);
+        [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+            .iter()
+            .any(|&(x, y)| {
+                let (gx, gy) = surface.gradient(x, y);
+                let turn = ((gx - fx) * (x - cx) + (gy - fy) * (y - cy)).abs();
+                turn.is_nan() || turn > options.tolerance
+            })
+    }
+
+    /// Leaf that covers the cell, which may be the cell itself or one of its
+    /// ancestors
+    fn leaf_containing(&self, (level, ix, iy): Cell) -> Option<Cell> {
+        (0..=level)
+            .rev()
+            .map(|l| (l, ix >> (level - l), iy >> (level - l)))
+            .find(|cell| self.leaves.contains(cell))
+    }
+
+    /// Splits leaves until no leaf borders one that is more than 1 level finer
+    fn balance(&mut self) {
+        loop {
+            let mut coarse = BTreeSet::new();
+
+            for &(level, ix, iy) in self.leaves.iter() {
+                for (nx, ny) in neighbours((level, ix, iy)) {
+                    if let Some(leaf) = self.leaf_containing((level, nx, ny)) {
+                        if leaf.0 + 1 < level {
+                            coarse.insert(leaf);
+                        }
+                    }
+                }
+            }
+
+            if coarse.is_empty() {
+                break;
+            }
+
+            for leaf in coarse {
+                self.leaves.remove(&leaf);
+                self.leaves.extend(children(leaf).iter());
+            }
+        }
+    }
+
+    /// Whether the cell's neighbour (at the same level) has been split
+    fn is_split(&self, (level, nx, ny): Cell) -> bool {
+        let side = 1u32 << level;
+        nx < side && ny < side && self.leaf_containing((level, nx, ny)).is_none()
+    }
+
+    /// Crack free triangulation of the leaves, points are (u, v) on the unit
+    /// square. Leaves next to finer leaves share the vertices on their
+    /// common edge by fanning out from their center
+    pub fn triangulate(&self) -> (Vec<(f32, f32)>, Vec<u32>) {
+        let mut points = vec![];
+        let mut indecies = vec![];
+
+        // vertices sit on a lattice twice as fine as the deepest level, so
+        // centers & edge midpoints of the smallest cells are whole numbers
+        let lattice = (1u32 << (self.max_depth + 1)) as f32;
+        let mut lookup = std::collections::BTreeMap::new();
+        let mut vertex = |x: u32, y: u32| -> u32 {
+            *lookup.entry((x, y)).or_insert_with(|| {
+                points.push((x as f32 / lattice, y as f32 / lattice));
+                (points.len() - 1) as u32
+            })
+        };
+
+        for &(level, ix, iy) in self.leaves.iter() {
+            let s = 1u32 << (self.max_depth + 1 - level);
+            let (x0, y0) = (ix * s, iy * s);
+            let h = s / 2;
+
+            // midpoints are only needed on edges shared with finer leaves
+            let below = iy > 0 && self.is_split((level, ix, iy - 1));
+            let right = self.is_split((level, ix + 1, iy));
+            let above = self.is_split((level, ix, iy + 1));
+            let left = ix > 0 && self.is_split((level, ix - 1, iy));
+
+            if !(below || right || above || left) {
+                let (a, b) = (vertex(x0, y0), vertex(x0 + s, y0));
+                let (c, d) = (vertex(x0 + s, y0 + s), vertex(x0, y0 + s));
+                indecies.extend_from_slice(&[a, b, d, d, b, c]);
+                continue;
+            }
+
+            // ring of vertices around the cell, counter clockwise
+            let mut ring = vec![vertex(x0, y0)];
+            if below {
+                ring.push(vertex(x0 + h, y0));
+            }
+            ring.push(vertex(x0 + s, y0));
+            if right {
+                ring.push(vertex(x0 + s, y0 + h));
+            }
+            ring.push(vertex(x0 + s, y0 + s));
+            if above {
+                ring.push(vertex(x0 + h, y0 + s));
+            }
+            ring.push(vertex(x0, y0 + s));
+            if left {
+                ring.push(vertex(x0, y0 + h));
+            }
+
+            let center = vertex(x0 + h, y0 + h);
+            for i in 0..ring.len() {
+                let next = ring[(i + 1) % ring.len()];
+                indecies.extend_from_slice(&[center, ring[i], next]);
+            }
+        }
+
+        (points, indecies)
+    }
+}
+
+fn children((level, ix, iy): Cell) -> [Cell; 4] {
+    let (x, y) = (ix * 2, iy * 2);
+    [
+        (level + 1, x, y),
+        (level + 1, x + 1, y),
+        (level + 1, x, y + 1),
+        (level + 1, x + 1, y + 1),
+    ]
+}
+
+/// Indices of the cells sharing an edge with the cell, on the same level
+fn neighbours((level, ix, iy): Cell) -> Vec<(u32, u32)> {
+    let side = 1u32 << level;
+    let mut out = vec![];
+
+    if ix > 0 {
+        out.push((ix - 1, iy));
+    }
+    if iy > 0 {
+        out.push((ix, iy - 1));
+    }
+    if ix + 1 < side {
+        out.push((ix + 1, iy));
+    }
+    if iy + 1 < side {
+        out.push((ix, iy + 1));
+    }
+
+    out
+}
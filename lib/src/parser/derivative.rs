@@ -0,0 +1,200 @@
+/*
+ * Symbolic differentiation of the abstract syntax tree, used to derive
+ * analytic gradients of equations (eg. for surface normals)
+ */
+
+use super::{Operator, ParseNode, ParserError};
+
+impl ParseNode {
+    /// Differentiates the node with respect to the given variable, the
+    /// result is simplified
+    pub fn derivative(&self, var: &str) -> Result<ParseNode, ParserError> {
+        Ok(self.derive(var)?.simplify())
+    }
+
+    /// Checks if the variable appears anywhere inside of the node
+    pub fn depends_on(&self, var: &str) -> bool {
+        match self {
+            ParseNode::Factor(..) => false,
+            ParseNode::Identifier(name) => name == var,
+            ParseNode::Function(_, args) => args.iter().any(|arg| arg.depends_on(var)),
+            ParseNode::FunctionDefine(_, _, body) => body.depends_on(var),
+            ParseNode::UnaryOp(_, node) => node.depends_on(var),
+            ParseNode::BinOp(lhs, _, rhs) => lhs.depends_on(var) || rhs.depends_on(var),
+            ParseNode::Tuple(items) => items.iter().any(|item| item.depends_on(var)),
+        }
+    }
+
+    fn derive(&self, var: &str) -> Result<ParseNode, ParserError> {
+        // anything that does not contain the variable is a constant, vectors
+        // keep their shape
+        if !self.depends_on(var) && !matches!(self, ParseNode::Tuple(..)) {
+            return Ok(ParseNode::Factor(0.));
+        }
+
+        match self {
+            ParseNode::FunctionDefine(name, args, body) => Ok(ParseNode::FunctionDefine(
+                name.clone(),
+                args.clone(),
+                Box::new(body.derive(var)?),
+            )),
+            // d/dx x = 1 (constants are caught above)
+            ParseNode::Factor(..) | ParseNode::Identifier(..) => Ok(ParseNode::Factor(1.)),
+            ParseNode::UnaryOp(Operator::Sub, node) => Ok(neg(node.derive(var)?)),
+            ParseNode::UnaryOp(_, node) => node.derive(var),
+            // vectors are differentiated component by component
+            ParseNode::Tuple(items) => Ok(ParseNode::Tuple(
+                items
+                    .iter()
+                    .map(|item| item.derive(var))
+                    .collect::<Result<_, _>>()?,
+            )),
+            ParseNode::BinOp(lhs, op, rhs) => {
+                let (a, b) = (*lhs.clone(), *rhs.clone());
+
+                match op {
+                    Operator::Add => Ok(add(a.derive(var)?, b.derive(var)?)),
+                    Operator::Sub => Ok(sub(a.derive(var)?, b.derive(var)?)),
+                    // product rule
+                    Operator::Mul => {
+                        let (da, db) = (a.derive(var)?, b.derive(var)?);
+                        Ok(add(mul(da, b), mul(a, db)))
+                    }
+                    // quotient rule
+                    Operator::Div => {
+                        let (da, db) = (a.derive(var)?, b.derive(var)?);
+                        Ok(div(sub(mul(da, b.clone()), mul(a, db)), mul(b.clone(), b)))
+                    }
+                    Operator::Pow => derive_pow(a, b, var),
+                }
+            }
+            ParseNode::Function(name, args) => derive_native(name, args, var),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Helper functions
+// ----------------------------------------------------------------------------
+
+fn derive_pow(base: ParseNode, exponent: ParseNode, var: &str) -> Result<ParseNode, ParserError> {
+    // power rule, d/dx u^n = n * u^(n-1) * u'
+    if !exponent.depends_on(var) {
+        let du = base.derive(var)?;
+        let lowered = pow(base, sub(exponent.clone(), ParseNode::Factor(1.)));
+        return Ok(mul(mul(exponent, lowered), du));
+    }
+
+    // exponential rule, d/dx a^u = a^u * ln(a) * u'
+    if !base.depends_on(var) {
+        let du = exponent.derive(var)?;
+        let ln = func("log", vec![base.clone()]);
+        return Ok(mul(mul(pow(base, exponent), ln), du));
+    }
+
+    // general case, d/dx u^v = u^v * (v' * ln(u) + v * u' / u)
+    let (du, dv) = (base.derive(var)?, exponent.derive(var)?);
+    let ln = func("log", vec![base.clone()]);
+    let inner = add(mul(dv, ln), div(mul(exponent.clone(), du), base.clone()));
+
+    Ok(mul(pow(base, exponent), inner))
+}
+
+fn derive_native(name: &str, args: &[ParseNode], var: &str) -> Result<ParseNode, ParserError> {
+    let arg = |i: usize| args[i].clone();
+
+    // chain rule for single argument functions, outer derivative * u'
+    let chain = |outer: ParseNode| -> Result<ParseNode, ParserError> {
+        Ok(mul(outer, args[0].derive(var)?))
+    };
+
+    let one = || ParseNode::Factor(1.);
+
+    match (name, args.len()) {
+        ("sin", 1) => chain(func("cos", vec![arg(0)])),
+        ("cos", 1) => chain(neg(func("sin", vec![arg(0)]))),
+        ("tan", 1) => {
+            let cos = func("cos", vec![arg(0)]);
+            chain(div(one(), mul(cos.clone(), cos)))
+        }
+        ("asin", 1) => {
+            let root = func("sqrt", vec![sub(one(), mul(arg(0), arg(0)))]);
+            chain(div(one(), root))
+        }
+        ("acos", 1) => {
+            let root = func("sqrt", vec![sub(one(), mul(arg(0), arg(0)))]);
+            chain(neg(div(one(), root)))
+        }
+        ("atan", 1) => chain(div(one(), add(one(), mul(arg(0), arg(0))))),
+        ("abs", 1) => chain(func("sign", vec![arg(0)])),
+        ("exp", 1) => chain(func("exp", vec![arg(0)])),
+        ("log", 1) => chain(div(one(), arg(0))),
+        ("sqrt", 1) => {
+            let root = func("sqrt", vec![arg(0)]);
+            chain(div(one(), mul(ParseNode::Factor(2.), root)))
+        }
+        ("pow", 2) => derive_pow(arg(0), arg(1), var),
+        // mod(a, b) = a - b * floor(a / b), floor is treated as locally constant
+        ("mod", 2) => {
+            let quotient = func("floor", vec![div(arg(0), arg(1))]);
+            Ok(sub(arg(0).derive(var)?, mul(quotient, arg(1).derive(var)?)))
+        }
+        // d/dx hypot(a, b) = (a * a' + b * b') / hypot(a, b)
+        ("hypot", 2) => {
+            let (da, db) = (arg(0).derive(var)?, arg(1).derive(var)?);
+            let numerator = add(mul(arg(0), da), mul(arg(1), db));
+            Ok(div(numerator, func("hypot", vec![arg(0), arg(1)])))
+        }
+        // d/dx lerp(a, b, t) = a' + (b' - a') * t + (b - a) * t'
+        ("lerp", 3) => {
+            let (da, db, dt) = (
+                arg(0).derive(var)?,
+                arg(1).derive(var)?,
+                arg(2).derive(var)?,
+            );
+            Ok(add(
+                add(da.clone(), mul(sub(db, da), arg(2))),
+                mul(sub(arg(1), arg(0)), dt),
+            ))
+        }
+        // step functions have no derivative at their jumps
+        ("round", 1) | ("floor", 1) | ("ceil", 1) | ("fract", 1) | ("sign", 1) => Err(
+            ParserError::NotDifferentiable(format!("{}() cannot be differentiated", name)),
+        ),
+        _ => Err(ParserError::NotDifferentiable(format!(
+            "Unknown function {} with {} inputs",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+// Node builders --------------------------------------------------------------
+
+fn add(lhs: ParseNode, rhs: ParseNode) -> ParseNode {
+    ParseNode::BinOp(Box::new(lhs), Operator::Add, Box::new(rhs))
+}
+
+fn sub(lhs: ParseNode, rhs: ParseNode) -> ParseNode {
+    ParseNode::BinOp(Box::new(lhs), Operator::Sub, Box::new(rhs))
+}
+
+fn mul(lhs: ParseNode, rhs: ParseNode) -> ParseNode {
+    ParseNode::BinOp(Box::new(lhs), Operator::Mul, Box::new(rhs))
+}
+
+fn div(lhs: ParseNode, rhs: ParseNode) -> ParseNode {
+    ParseNode::BinOp(Box::new(lhs), Operator::Div, Box::new(rhs))
+}
+
+fn pow(lhs: ParseNode, rhs: ParseNode) -> ParseNode {
+    ParseNode::BinOp(Box::new(lhs), Operator::Pow, Box::new(rhs))
+}
+
+fn neg(node: ParseNode) -> ParseNode {
+    ParseNode::UnaryOp(Operator::Sub, Box::new(node))
+}
+
+fn func(name: &str, args: Vec<ParseNode>) -> ParseNode {
+    ParseNode::Function(name.into(), args)
+}
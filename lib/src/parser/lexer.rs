@@ -1,6 +1,6 @@
 use crate::{op_tok, parser::ParserError};
 
-use super::Token;
+use super::{Comparison, Token};
 
 // Macros ---------------------------------------------------------------------
 
@@ -20,6 +20,16 @@ macro_rules! is_digit {
     };
 }
 
+/// Greek letters allowed in identifiers & the names they are read as
+const GREEK_LETTERS: &[(char, &str)] = &[
+    ('θ', "theta"),
+    ('φ', "phi"),
+    ('ϕ', "phi"),
+    ('ρ', "rho"),
+    ('π', "pi"),
+    ('τ', "tau"),
+];
+
 /// Lexer used to tokenize a math equation string
 pub struct Lexer {
     tokens: Vec<Token>,
@@ -78,6 +88,13 @@ impl Lexer {
                 continue;
             }
 
+            // greek letters are spelled out, shaders only take ascii names
+            if let Some((_, name)) = GREEK_LETTERS.iter().find(|(letter, _)| *letter == curr) {
+                identifier.push_str(name);
+                self.advance();
+                continue;
+            }
+
             // else break the look
             break;
         }
@@ -85,6 +102,14 @@ impl Lexer {
         // if no identifier read than return false
         if identifier.len() == 0 {
             Ok(false)
+        // in is a keyword used by domains (x in [0, 1])
+        } else if identifier == "in" {
+            self.tokens.push(Token::In);
+            Ok(true)
+        // around is a keyword used by surfaces of revolution (around x)
+        } else if identifier == "around" {
+            self.tokens.push(Token::Around);
+            Ok(true)
         // else add identifier to tokens list
         } else {
             self.tokens.push(Token::Identifier(identifier));
@@ -101,10 +126,19 @@ impl Lexer {
                 // Non BinOp
                 '(' => self.tokens.push(Token::LeftParen),
                 ')' => self.tokens.push(Token::RightParen),
+                '[' => self.tokens.push(Token::LeftBracket),
+                ']' => self.tokens.push(Token::RightBracket),
+                '∈' => self.tokens.push(Token::In),
                 '=' => self.tokens.push(Token::Equals),
 
                 ',' => self.tokens.push(Token::ArgumentSeperator),
 
+                // Comparisons, used by inequalities
+                '<' | '>' | '≤' | '≥' => {
+                    let cmp = self.comparison(curr);
+                    self.tokens.push(Token::Compare(cmp));
+                }
+
                 // Bin Operators
                 '+' => self.tokens.push(op_tok!(Add)),
                 '-' => self.tokens.push(op_tok!(Sub)),
@@ -172,6 +206,22 @@ impl Lexer {
         }
     }
 
+    /// Reads the comparison starting with curr, the = of <= & >= is stepped
+    /// onto so the caller only has to advance past it
+    fn comparison(&mut self, curr: char) -> Comparison {
+        let inclusive = self.src.chars().nth(self.index + 1) == Some('=');
+        if inclusive && (curr == '<' || curr == '>') {
+            self.advance();
+        }
+
+        match (curr, inclusive) {
+            ('<', false) => Comparison::Less,
+            ('>', false) => Comparison::Greater,
+            ('<', true) | ('≤', _) => Comparison::LessEqual,
+            _ => Comparison::GreaterEqual,
+        }
+    }
+
     // moves index forward by 1
     fn advance(&mut self) {
         self.index += 1;
@@ -1,7 +1,9 @@
 // Module Definitions ---------------------------------------------------------
+pub mod derivative;
 pub mod lexer;
 pub mod parser;
 pub mod native;
+pub mod simplify;
 
 use std::fmt::{Display, Write};
 
@@ -10,11 +12,35 @@ use std::fmt::{Display, Write};
 pub enum ParserError {
     SyntaxError(String),
     RecursiveCall(String),
+    NotDifferentiable(String),
 }
 
+/// Input that surfaces of revolution sweep around their axis with, its range
+/// is the sweep angle (eg. g(x) = sqrt(x), around x, theta in [0, pi])
+pub const REVOLUTION_ANGLE: &str = "theta";
+
+/// Name given to equations without a function head (eg. x^2 + y^2 = 1), which
+/// are implicit surfaces F(x, y, z) = 0
+pub const IMPLICIT: &str = "implicit";
+
+/// Name given to inequalities (eg. x^2 + y^2 <= z <= 4), which are regions
+/// between a lower & upper bound on one axis. Their inputs are the 2 other
+/// axes followed by the bounded one & their body is (lower, upper)
+pub const REGION: &str = "region";
+
+/// Functions that make a vector from its tail & head, or a plane from its
+/// normal & a point on it (eg. v = vector(P, (1, 1, 0))). See math/primitives
+pub const VECTOR: &str = "vector";
+pub const PLANE: &str = "plane";
+
+/// Stands in for the missing side of one sided inequalities (eg. z <= 1 - x^2),
+/// regions are clipped to the visible part of the bounded axis so it is never
+/// reached
+pub const UNBOUNDED: f32 = 1e30;
+
 // Enumerations for use in Parsing -----------------------------------------
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[allow(unused)]
 pub enum ParseNode {
     Factor(f32),
@@ -23,6 +49,128 @@ pub enum ParseNode {
     FunctionDefine(String, Vec<String>, Box<ParseNode>),
     UnaryOp(Operator, Box<ParseNode>),
     BinOp(Box<ParseNode>, Operator, Box<ParseNode>),
+    /// Vector of expressions (eg. (cos(u), sin(u), u)), only valid as the
+    /// body of a parametric equation
+    Tuple(Vec<ParseNode>),
+}
+
+impl ParseNode {
+    /// Whether the node defines an implicit surface, F(x, y, z) = 0
+    pub fn is_implicit(&self) -> bool {
+        matches!(self, ParseNode::FunctionDefine(name, ..) if name == IMPLICIT)
+    }
+
+    /// Whether the node defines a region between 2 bounds, see REGION
+    pub fn is_region(&self) -> bool {
+        matches!(self, ParseNode::FunctionDefine(name, ..) if name == REGION)
+    }
+
+    /// Whether the node defines a vector field, which takes x, y & z & has a
+    /// vector as its body (eg. F(x, y, z) = (-y, x, z/5))
+    pub fn is_field(&self) -> bool {
+        match self {
+            ParseNode::FunctionDefine(_, args, body) => {
+                args.iter().map(String::as_str).eq(["x", "y", "z"])
+                    && matches!(**body, ParseNode::Tuple(..))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the node defines a parametric equation, which has a vector as
+    /// its body (eg. p(u, v) = (u, v, u*v) or c(t) = (cos(t), sin(t), t))
+    pub fn is_parametric(&self) -> bool {
+        match self {
+            _ if self.is_region() || self.is_field() => false,
+            ParseNode::FunctionDefine(_, _, body) => matches!(**body, ParseNode::Tuple(..)),
+            _ => false,
+        }
+    }
+
+    /// Whether the node changes over time, through t (unless it is an input),
+    /// TIME or time()
+    pub fn is_animated(&self) -> bool {
+        fn calls_time(node: &ParseNode) -> bool {
+            match node {
+                ParseNode::Function(name, args) => name == "time" || args.iter().any(calls_time),
+                ParseNode::FunctionDefine(_, _, body) | ParseNode::UnaryOp(_, body) => {
+                    calls_time(body)
+                }
+                ParseNode::BinOp(lhs, _, rhs) => calls_time(lhs) || calls_time(rhs),
+                ParseNode::Tuple(items) => items.iter().any(calls_time),
+                ParseNode::Identifier(..) | ParseNode::Factor(..) => false,
+            }
+        }
+
+        let t_is_input = matches!(self, ParseNode::FunctionDefine(_, args, _)
+            if args.iter().any(|arg| arg == "t"));
+
+        (!t_is_input && self.depends_on("t")) || self.depends_on("TIME") || calls_time(self)
+    }
+
+    /// Renames variables (& inputs of the head) all at once, so names can be
+    /// swapped with each other. Pairs are (from, to)
+    pub fn rename(&self, names: &[(&str, &str)]) -> ParseNode {
+        let rename = |name: &String| match names.iter().find(|(from, _)| from == name) {
+            Some((_, to)) => to.to_string(),
+            None => name.clone(),
+        };
+
+        match self {
+            ParseNode::Factor(..) => self.clone(),
+            ParseNode::Identifier(name) => ParseNode::Identifier(rename(name)),
+            ParseNode::Function(name, args) => ParseNode::Function(
+                name.clone(),
+                args.iter().map(|arg| arg.rename(names)).collect(),
+            ),
+            ParseNode::FunctionDefine(name, args, body) => ParseNode::FunctionDefine(
+                name.clone(),
+                args.iter().map(rename).collect(),
+                Box::new(body.rename(names)),
+            ),
+            ParseNode::UnaryOp(op, node) => ParseNode::UnaryOp(*op, Box::new(node.rename(names))),
+            ParseNode::BinOp(lhs, op, rhs) => ParseNode::BinOp(
+                Box::new(lhs.rename(names)),
+                *op,
+                Box::new(rhs.rename(names)),
+            ),
+            ParseNode::Tuple(items) => {
+                ParseNode::Tuple(items.iter().map(|item| item.rename(names)).collect())
+            }
+        }
+    }
+
+    /// Replaces variables with values (eg. fitted parameters), pairs are
+    /// (name, value)
+    pub fn substitute(&self, values: &[(String, f32)]) -> ParseNode {
+        match self {
+            ParseNode::Factor(..) => self.clone(),
+            ParseNode::Identifier(name) => match values.iter().find(|(var, _)| var == name) {
+                Some((_, val)) => ParseNode::Factor(*val),
+                None => self.clone(),
+            },
+            ParseNode::Function(name, args) => ParseNode::Function(
+                name.clone(),
+                args.iter().map(|arg| arg.substitute(values)).collect(),
+            ),
+            ParseNode::FunctionDefine(name, args, body) => ParseNode::FunctionDefine(
+                name.clone(),
+                args.clone(),
+                Box::new(body.substitute(values)),
+            ),
+            ParseNode::UnaryOp(op, node) => {
+                ParseNode::UnaryOp(*op, Box::new(node.substitute(values)))
+            }
+            ParseNode::BinOp(lhs, op, rhs) => ParseNode::BinOp(
+                Box::new(lhs.substitute(values)),
+                *op,
+                Box::new(rhs.substitute(values)),
+            ),
+            ParseNode::Tuple(items) => {
+                ParseNode::Tuple(items.iter().map(|item| item.substitute(values)).collect())
+            }
+        }
+    }
 }
 
 impl Display for ParseNode {
@@ -49,8 +197,19 @@ impl Display for ParseNode {
                 if !float_str.contains(".") {
                     float_str += ".0";
                 }
-                f.write_str(float_str.as_str())
+
+                // wraps negatives so they can't be read as a decrement (x--1.0)
+                if *val < 0. {
+                    f.write_fmt(format_args!("({})", float_str))
+                } else {
+                    f.write_str(float_str.as_str())
+                }
             },
+            // GLSL 3's round is free to go either way at .5, so it's spelled
+            // out to always round .5 up like NativeFunc::eval
+            ParseNode::Function(name, args) if name == "round" && args.len() == 1 => {
+                f.write_fmt(format_args!("floor({} + 0.5)", args[0]))
+            }
             ParseNode::Function(name, args) => {
                 let mut builder = String::new();
 
@@ -69,6 +228,10 @@ impl Display for ParseNode {
                 _ => f.write_fmt(format_args!("({}{}{})", lhs, op, rhs)),
             },
             ParseNode::UnaryOp(op, node) => f.write_fmt(format_args!("({}{})", op, node)),
+            ParseNode::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                f.write_fmt(format_args!("vec{}({})", items.len(), items.join(", ")))
+            }
         }
     }
 }
@@ -77,7 +240,12 @@ impl Display for ParseNode {
 pub enum Token {
     RightParen,
     LeftParen,
+    RightBracket,
+    LeftBracket,
     ArgumentSeperator,
+    In,
+    Around,
+    Compare(Comparison),
     BinOp(Operator),
     Identifier(String),
     Equals,
@@ -89,6 +257,11 @@ impl Display for Token {
         match self {
             Self::LeftParen => f.write_str("("),
             Self::RightParen => f.write_str(")"),
+            Self::LeftBracket => f.write_str("["),
+            Self::RightBracket => f.write_str("]"),
+            Self::In => f.write_str("in"),
+            Self::Around => f.write_str("around"),
+            Self::Compare(cmp) => cmp.fmt(f),
             Self::Equals => f.write_str("="),
             Self::ArgumentSeperator => f.write_str(","),
             Self::BinOp(op) => op.fmt(f),
@@ -98,7 +271,7 @@ impl Display for Token {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operator {
     Add,
     Sub,
@@ -119,6 +292,34 @@ impl Display for Operator {
     }
 }
 
+/// Comparison between the sides of an inequality, regions are drawn the same
+/// whether or not their bounds are included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    /// Whether the left side is the smaller one
+    pub fn ascending(&self) -> bool {
+        matches!(self, Self::Less | Self::LessEqual)
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+        })
+    }
+}
+
 // Macros ---------------------------------------------------------------------
 #[macro_export]
 macro_rules! op_tok {
@@ -26,9 +26,11 @@ pub const NATIVE_FUNCTIONS: &[NativeFunc] = &[
     NativeFunc("ceil", 1), 
     NativeFunc("mod", 2),
     NativeFunc("abs", 1),
+    NativeFunc("sign", 1),
     NativeFunc("fract", 1),
     NativeFunc("pow", 2),
     NativeFunc("exp", 1),
+    NativeFunc("log", 1),
     NativeFunc("sqrt", 1),
     NativeFunc("hypot", 2),
     NativeFunc("lerp", 3),
@@ -36,7 +38,15 @@ pub const NATIVE_FUNCTIONS: &[NativeFunc] = &[
 
 pub struct NativeConstant(pub &'static str, pub f32); 
 
-impl NativeConstant {}
+impl NativeConstant {
+    /// Looks up the value of a native constant by name
+    pub fn value(name: &str) -> Option<f32> {
+        NATIVE_CONSTANTS
+            .iter()
+            .find(|constant| constant.0 == name)
+            .map(|constant| constant.1)
+    }
+}
 
 pub struct NativeFunc(pub &'static str, pub usize);
 impl NativeFunc {
@@ -53,4 +63,49 @@ impl NativeFunc {
 
         false
     }
+
+    /// Evaluates a native function on the CPU, mirroring the GLSL definitions
+    /// used in the equation shader. Returns None for unknown functions or
+    /// functions that cannot be evaluated ahead of time (eg. time)
+    pub fn eval(name: &str, args: &[f32]) -> Option<f32> {
+        let val = match (name, args) {
+            ("sin", &[a]) => a.sin(),
+            ("cos", &[a]) => a.cos(),
+            ("tan", &[a]) => a.tan(),
+            ("asin", &[a]) => a.asin(),
+            ("acos", &[a]) => a.acos(),
+            ("atan", &[a]) => a.atan(),
+            // matches the floor(a + 0.5) round() is written as in shaders
+            ("round", &[a]) => {
+                if a - a.floor() >= 0.5 {
+                    a.ceil()
+                } else {
+                    a.floor()
+                }
+            }
+            ("floor", &[a]) => a.floor(),
+            ("ceil", &[a]) => a.ceil(),
+            // GLSL mod, which differs from rust's % for negative numbers
+            ("mod", &[a, b]) => a - b * (a / b).floor(),
+            ("abs", &[a]) => a.abs(),
+            // GLSL sign is 0 at 0, unlike signum
+            ("sign", &[a]) => {
+                if a == 0. {
+                    0.
+                } else {
+                    a.signum()
+                }
+            }
+            ("fract", &[a]) => a - a.floor(),
+            ("pow", &[a, b]) => a.powf(b),
+            ("exp", &[a]) => a.exp(),
+            ("log", &[a]) => a.ln(),
+            ("sqrt", &[a]) => a.sqrt(),
+            ("hypot", &[a, b]) => a.hypot(b),
+            ("lerp", &[from, to, travel]) => from + (to - from) * travel,
+            _ => return None,
+        };
+
+        Some(val)
+    }
 }
@@ -1,100 +1,323 @@
 use wasm_bindgen::JsValue;
 
-use crate::parser::Operator;
+use crate::{
+    math::{axes::AXIS_NAMES, domain::Domain},
+    parser::Operator,
+};
 
 use super::{
     native::{NativeFunc},
-    ParseNode, ParserError, Token,
+    ParseNode, ParserError, Token, IMPLICIT, PLANE, REGION, REVOLUTION_ANGLE, UNBOUNDED, VECTOR,
 };
 
+// higher order derivatives grow quickly & aren't useful to plot
+const MAX_DIFF_ORDER: usize = 16;
+
 pub struct Parser {
     toks: Vec<Token>,
     idx: usize,
+    domain: Domain,
+    revolution: Option<usize>,
 }
 
 // TODO ammend extra methods
 impl Parser {
     // constructor
     pub fn new(toks: Vec<Token>) -> Self {
-        Self { toks, idx: 0 }
+        Self {
+            toks,
+            idx: 0,
+            domain: Domain::new(),
+            revolution: None,
+        }
+    }
+
+    /// Input ranges read from the end of the equation, empty until parsed
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    /// Axis (0 for x, 1 for y & 2 for z) the equation is revolved around,
+    /// read from the end of the equation (eg. g(x) = sqrt(x), around x)
+    pub fn revolution(&self) -> Option<usize> {
+        self.revolution
     }
 
     pub fn parse(&mut self) -> Result<ParseNode, ParserError> {
-        // first token must be a function name
-        let func_name = if let Some(tok) = self.curr_tok() {
-            match tok {
-                Token::Identifier(name) => {
-                    self.advance();
-                    Ok(name)
-                }
-                _ => Err(ParserError::SyntaxError(String::new())),
-            }
-        } else {
-            // return parse node that doesnt graph anything if input is empty
+        // return parse node that doesnt graph anything if input is empty
+        if self.curr_tok().is_none() {
             return Ok(ParseNode::FunctionDefine(
                 "default".into(),
                 vec![],
                 Box::new(ParseNode::Factor(f32::MIN)),
             ));
-        }?;
+        }
 
-        // assure ( comes after
-        if let Some(tok) = self.curr_tok() {
-            match tok {
-                Token::LeftParen => {
-                    self.advance();
+        let (func_name, args, body) = match self.function_head() {
+            Some((name, args)) => {
+                let body = self.add_term()?;
+                (name, args, body)
+            }
+            // equations without a head (eg. x^2 + y^2 + z^2 = 1) are implicit
+            // surfaces, drawn where lhs - rhs = 0
+            None => {
+                let lhs = self.add_term()?;
+
+                // inequalities (eg. x^2 + y^2 <= z <= 4) are regions instead
+                if let Some(Token::Compare(..)) = self.curr_tok() {
+                    self.region(lhs)?
+                } else {
+                    self.expect(Token::Equals, "Missing =")?;
+                    let rhs = self.add_term()?;
+
+                    match lhs {
+                        // names given to points, vectors & planes (eg. P = (1, 2, 3)) have
+                        // no inputs, see math/primitives
+                        ParseNode::Identifier(name) if is_definition(&name, &rhs) => {
+                            (name, vec![], rhs)
+                        }
+                        lhs => {
+                            let args = vec!["x".into(), "y".into(), "z".into()];
+                            let body =
+                                ParseNode::BinOp(Box::new(lhs), Operator::Sub, Box::new(rhs));
+                            (IMPLICIT.into(), args, body)
+                        }
+                    }
                 }
-                _ => return Err(ParserError::SyntaxError("Missing (".into())),
             }
-        } else {
-            return Err(ParserError::SyntaxError("Missing (".into()));
+        };
+
+        // surfaces of revolution also take the angle they sweep through
+        let revolves = self.toks.iter().any(|tok| matches!(tok, Token::Around));
+        let mut inputs = args.clone();
+        if revolves {
+            inputs.push(REVOLUTION_ANGLE.into());
+        }
+
+        // optional domain & revolution clauses follow the body, eg.
+        // f(x, y) = x*y, x in [0, 1]
+        while let Some(Token::ArgumentSeperator) = self.curr_tok() {
+            self.advance();
+
+            match self.curr_tok() {
+                Some(Token::Around) => self.revolution_clause()?,
+                _ => self.domain_clause(&inputs)?,
+            }
+        }
+
+        let profile = args.len() == 1 && !matches!(body, ParseNode::Tuple(..));
+        if revolves && (func_name == IMPLICIT || !profile) {
+            return Err(ParserError::SyntaxError(
+                "Surfaces of revolution need a profile with 1 input (eg. g(x) = x^2, around x)"
+                    .into(),
+            ));
         }
 
+        if let Some(tok) = self.curr_tok() {
+            return Err(ParserError::SyntaxError(format!(
+                "Unexpected Token {}",
+                tok
+            )));
+        }
+
+        // returns node tree
+        Ok(ParseNode::FunctionDefine(func_name, args, Box::new(body)))
+    }
+
+    /// Reads a function head (eg. `f(x, y) =`), if the equation doesn't start
+    /// with one nothing is consumed & None is returned
+    fn function_head(&mut self) -> Option<(String, Vec<String>)> {
+        let start = self.idx;
+        let head = self.read_head();
+
+        if head.is_none() {
+            self.idx = start;
+        }
+
+        head
+    }
+
+    fn read_head(&mut self) -> Option<(String, Vec<String>)> {
+        let name = match self.curr_tok()? {
+            Token::Identifier(name) => name,
+            _ => return None,
+        };
+        self.advance();
+        self.expect(Token::LeftParen, "").ok()?;
+
         let mut args = vec![];
+        loop {
+            match self.curr_tok()? {
+                Token::Identifier(arg) => args.push(arg),
+                _ => return None,
+            }
+            self.advance();
 
-        while let Some(tok) = self.curr_tok() {
-            match tok {
-                Token::Identifier(name) => {
-                    args.push(name);
+            match self.curr_tok()? {
+                Token::ArgumentSeperator => self.advance(),
+                Token::RightParen => {
                     self.advance();
+                    break;
                 }
-                _ => return Err(ParserError::SyntaxError("Invalid arg".into())),
+                _ => return None,
             }
+        }
 
-            if let Some(tok) = self.curr_tok() {
-                match tok {
-                    Token::ArgumentSeperator => {
-                        self.advance();
-                    }
-                    Token::RightParen => {
-                        self.advance();
-                        break;
-                    }
-                    _ => return Err(ParserError::SyntaxError("Missing ,".into())),
-                }
-            } else {
-                return Err(ParserError::SyntaxError("Unexpected End".into()));
+        self.expect(Token::Equals, "").ok()?;
+
+        // native calls on the left hand side (eg. sin(x) = y) aren't heads
+        if NativeFunc::is_native(&(name.clone(), args.len())) {
+            return None;
+        }
+
+        Some((name, args))
+    }
+
+    /// Reads the rest of an inequality after its first side, one axis is
+    /// bounded from below and/or above by expressions of the other 2. A
+    /// missing bound is UNBOUNDED
+    fn region(
+        &mut self,
+        first: ParseNode,
+    ) -> Result<(String, Vec<String>, ParseNode), ParserError> {
+        let mut sides = vec![first];
+        let mut comparisons = vec![];
+
+        while let Some(Token::Compare(cmp)) = self.curr_tok() {
+            self.advance();
+            comparisons.push(cmp);
+            sides.push(self.add_term()?);
+        }
+
+        let ascending = comparisons[0].ascending();
+        if sides.len() > 3 || comparisons.iter().any(|cmp| cmp.ascending() != ascending) {
+            return Err(ParserError::SyntaxError(
+                "Inequalities can only bound an axis from either side (eg. 0 <= z <= 1)".into(),
+            ));
+        }
+
+        // puts the sides in ascending order
+        if !ascending {
+            sides.reverse();
+        }
+
+        let axis_of = |node: &ParseNode| match node {
+            ParseNode::Identifier(name) => AXIS_NAMES.iter().position(|&axis| axis == name),
+            _ => None,
+        };
+        let unbounded = |sign: f32| ParseNode::Factor(sign * UNBOUNDED);
+
+        // the axis is in the middle, or on either side of a single comparison
+        let (axis, lower, upper) = match sides.as_slice() {
+            [lower, middle, upper] => (axis_of(middle), lower.clone(), upper.clone()),
+            [lhs, rhs] => match (axis_of(lhs), axis_of(rhs)) {
+                (Some(axis), _) => (Some(axis), unbounded(-1.), rhs.clone()),
+                (None, axis) => (axis, lhs.clone(), unbounded(1.)),
+            },
+            _ => unreachable!(),
+        };
+
+        let name = match axis {
+            Some(axis) => AXIS_NAMES[axis],
+            None => {
+                return Err(ParserError::SyntaxError(
+                    "Inequalities need an axis to bound (eg. z <= 1 - x^2)".into(),
+                ))
             }
+        };
+
+        if lower.depends_on(name) || upper.depends_on(name) {
+            return Err(ParserError::SyntaxError(format!(
+                "Bounds of {} can't depend on {}",
+                name, name
+            )));
         }
 
-        // assure = comes after
-        if let Some(tok) = self.curr_tok() {
-            match tok {
-                Token::Equals => {
-                    self.advance();
-                }
-                _ => return Err(ParserError::SyntaxError("Missing (".into())),
+        // the other 2 axes are the inputs
+        let mut args: Vec<String> = AXIS_NAMES
+            .iter()
+            .filter(|&&axis| axis != name)
+            .map(|axis| axis.to_string())
+            .collect();
+        args.push(name.into());
+
+        Ok((REGION.into(), args, ParseNode::Tuple(vec![lower, upper])))
+    }
+
+    /// Reads a single `var in [min, max]` clause into the domain
+    fn domain_clause(&mut self, args: &[String]) -> Result<(), ParserError> {
+        let var = match self.curr_tok() {
+            Some(Token::Identifier(var)) if args.contains(&var) => var,
+            Some(Token::Identifier(var)) => {
+                return Err(ParserError::SyntaxError(format!(
+                    "Domain of {} given, but it is not an input",
+                    var
+                )))
             }
-        } else {
-            return Err(ParserError::SyntaxError("Missing (".into()));
+            _ => {
+                return Err(ParserError::SyntaxError(
+                    "Expected a domain (x in [a, b])".into(),
+                ))
+            }
+        };
+        self.advance();
+
+        if self.domain.get(&var).is_some() {
+            return Err(ParserError::SyntaxError(format!(
+                "Domain of {} given twice",
+                var
+            )));
         }
 
-        // returns node tree
-        Ok(ParseNode::FunctionDefine(
-            func_name,
-            args,
-            Box::new(self.add_term()?),
-        ))
+        self.expect(Token::In, "Missing in")?;
+        self.expect(Token::LeftBracket, "Missing [")?;
+        let min = self.bound()?;
+        self.expect(Token::ArgumentSeperator, "Missing ,")?;
+        let max = self.bound()?;
+        self.expect(Token::RightBracket, "Missing ]")?;
+
+        self.domain
+            .set(&var, (min, max))
+            .map_err(ParserError::SyntaxError)
+    }
+
+    /// Reads a single `around axis` clause
+    fn revolution_clause(&mut self) -> Result<(), ParserError> {
+        self.expect(Token::Around, "Missing around")?;
+
+        let axis = match self.curr_tok() {
+            Some(Token::Identifier(axis)) => AXIS_NAMES.iter().position(|&name| name == axis),
+            _ => None,
+        };
+        let axis = axis.ok_or_else(|| {
+            ParserError::SyntaxError("Expected an axis to revolve around (around x)".into())
+        })?;
+        self.advance();
+
+        if self.revolution.replace(axis).is_some() {
+            return Err(ParserError::SyntaxError("Axis of revolution given twice".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Reads an expression that must simplify to a constant
+    fn bound(&mut self) -> Result<f32, ParserError> {
+        self.add_term()?
+            .simplify()
+            .constant()
+            .ok_or_else(|| ParserError::SyntaxError("Domain bounds must be constant".into()))
+    }
+
+    /// Consumes the token if it is the expected kind, errors with msg if not
+    fn expect(&mut self, expected: Token, msg: &str) -> Result<(), ParserError> {
+        match self.curr_tok() {
+            Some(tok) if std::mem::discriminant(&tok) == std::mem::discriminant(&expected) => {
+                self.advance();
+                Ok(())
+            }
+            _ => Err(ParserError::SyntaxError(msg.into())),
+        }
     }
 
     fn add_term(&mut self) -> Result<ParseNode, ParserError> {
@@ -129,7 +352,7 @@ impl Parser {
     }
 
     fn mul_term(&mut self) -> Result<ParseNode, ParserError> {
-        let mut node = self.pow_term()?;
+        let mut node = self.unary_term()?;
 
         // loop until the current token is nonexistant OR is
         // not a * or /
@@ -141,7 +364,7 @@ impl Parser {
                         self.advance(); // move forward
 
                         // gets term for the right hand side
-                        let rhs = Box::new(self.pow_term()?);
+                        let rhs = Box::new(self.unary_term()?);
 
                         // moves previous node down the tree
                         node = ParseNode::BinOp(Box::new(node), op, rhs);
@@ -159,6 +382,20 @@ impl Parser {
         Ok(node)
     }
 
+    fn unary_term(&mut self) -> Result<ParseNode, ParserError> {
+        // leading - negates everything up to the next * or /, so -x^2 = -(x^2)
+        match self.curr_tok() {
+            Some(Token::BinOp(Operator::Sub)) => {
+                self.advance();
+                Ok(ParseNode::UnaryOp(
+                    Operator::Sub,
+                    Box::new(self.unary_term()?),
+                ))
+            }
+            _ => self.pow_term(),
+        }
+    }
+
     fn pow_term(&mut self) -> Result<ParseNode, ParserError> {
         let mut node = self.factor()?;
 
@@ -171,8 +408,12 @@ impl Parser {
                     Operator::Pow => {
                         self.advance(); // move forward
 
-                        // gets term for the right hand side
-                        let rhs = Box::new(self.factor()?);
+                        // gets term for the right hand side, which may be
+                        // negated (eg. 2^-x)
+                        let rhs = Box::new(match self.curr_tok() {
+                            Some(Token::BinOp(Operator::Sub)) => self.unary_term()?,
+                            _ => self.factor()?,
+                        });
 
                         // moves previous node down the tree
                         node = ParseNode::BinOp(Box::new(node), op, rhs);
@@ -210,9 +451,25 @@ impl Parser {
             Token::LeftParen => {
                 self.advance();
 
-                // parses interior of left paren
-                let node = self.add_term()?;
-                Ok(node)
+                // parses interior of left paren, commas make the group a
+                // vector (eg. (cos(u), sin(u), u))
+                let mut items = vec![self.add_term()?];
+                while let Some(Token::ArgumentSeperator) = self.curr_tok() {
+                    self.advance();
+                    items.push(self.add_term()?);
+                }
+
+                // assure ) closes the group
+                match self.curr_tok() {
+                    Some(Token::RightParen) => {
+                        self.advance();
+                        Ok(match items.len() {
+                            1 => items.remove(0),
+                            _ => ParseNode::Tuple(items),
+                        })
+                    }
+                    _ => Err(ParserError::SyntaxError("Missing )".into())),
+                }
             }
 
             Token::Identifier(name) => {
@@ -222,7 +479,18 @@ impl Parser {
                 if let Some(tok) = self.curr_tok() {
                     if matches!(tok, Token::LeftParen) {
                         self.advance();
-                        Ok(ParseNode::Function(name, self.read_args()?))
+                        let args = self.read_args()?;
+                        Self::special_form(name, args)
+                    } else if NativeFunc::is_native(&(name.clone(), 1))
+                        && matches!(
+                            tok,
+                            Token::Literal(_) | Token::Identifier(_) | Token::BinOp(Operator::Sub)
+                        )
+                    {
+                        // single argument functions can be applied without
+                        // parentheses (eg. cos t), binding tighter than * & /
+                        let arg = self.unary_term()?;
+                        Self::special_form(name, vec![arg])
                     } else {
                         Ok(ParseNode::Identifier(name))
                     }
@@ -239,6 +507,73 @@ impl Parser {
         }
     }
 
+    /// Resolves function calls that are evaluated at compile time (eg. diff),
+    /// any other function is returned as is
+    fn special_form(name: String, args: Vec<ParseNode>) -> Result<ParseNode, ParserError> {
+        match name.as_str() {
+            // diff(expr, var) or diff(expr, var, order)
+            "diff" => {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(ParserError::SyntaxError(
+                        "diff expects (expression, variable) or (expression, variable, order)"
+                            .into(),
+                    ));
+                }
+
+                let var = match &args[1] {
+                    ParseNode::Identifier(var) => var.clone(),
+                    _ => {
+                        return Err(ParserError::SyntaxError(
+                            "diff can only differentiate with respect to a variable".into(),
+                        ))
+                    }
+                };
+
+                // order must be a positive whole number
+                let order = match args.get(2).map(|arg| arg.simplify().constant()) {
+                    None => 1.,
+                    Some(Some(order)) if order >= 1. && order.fract() == 0. => order,
+                    _ => {
+                        return Err(ParserError::SyntaxError(
+                            "diff order must be a positive whole number".into(),
+                        ))
+                    }
+                };
+                if order > MAX_DIFF_ORDER as f32 {
+                    return Err(ParserError::SyntaxError(format!(
+                        "diff order can be at most {}",
+                        MAX_DIFF_ORDER
+                    )));
+                }
+
+                let mut node = args[0].clone();
+                for _ in 0..order as usize {
+                    // every further derivative of 0 is 0 too
+                    if node.constant() == Some(0.) {
+                        break;
+                    }
+                    node = node.derivative(&var)?;
+                }
+
+                Ok(node)
+            }
+            // grad_mag(expr), magnitude of the gradient in x & y
+            "grad_mag" => {
+                if args.len() != 1 {
+                    return Err(ParserError::SyntaxError(
+                        "grad_mag expects a single expression".into(),
+                    ));
+                }
+
+                let dx = args[0].derivative("x")?;
+                let dy = args[0].derivative("y")?;
+
+                Ok(ParseNode::Function("hypot".into(), vec![dx, dy]).simplify())
+            }
+            _ => Ok(ParseNode::Function(name, args)),
+        }
+    }
+
     fn read_args(&mut self) -> Result<Vec<ParseNode>, ParserError> {
         let mut args = vec![];
         while let Some(tok) = self.curr_tok() {
@@ -287,6 +622,32 @@ impl Parser {
     }
 
     pub fn validate(node: &ParseNode) -> Result<(), JsValue> {
+        // vectors can only be the whole body of a parametric equation
+        if let ParseNode::FunctionDefine(_, args, body) = node {
+            let nested = match &**body {
+                // regions keep their bounds in a vector
+                ParseNode::Tuple(items) if node.is_region() => items.iter().any(has_tuple),
+                ParseNode::Tuple(items) => {
+                    if items.len() != 3 {
+                        return Err("Parametric equations need 3 components (x, y, z)".into());
+                    }
+
+                    if args.len() != 1 && args.len() != 2 && !node.is_field() {
+                        let msg = "Parametric equations need 1 input (eg. c(t)) or 2 \
+                                   (eg. p(u, v)), vector fields need x, y & z (eg. F(x, y, z))";
+                        return Err(msg.into());
+                    }
+
+                    items.iter().any(has_tuple)
+                }
+                body => has_tuple(body),
+            };
+
+            if nested {
+                return Err("Vectors can only be used as the whole equation".into());
+            }
+        }
+
         let dependicies = Self::get_function_dependicies(node);
 
         for func in dependicies.iter() {
@@ -330,6 +691,11 @@ impl Parser {
                 // traverses down body
                 depends.append(&mut Self::get_function_dependicies(&**body));
             }
+            ParseNode::Tuple(items) => {
+                for item in items.iter() {
+                    depends.append(&mut Self::get_function_dependicies(item));
+                }
+            }
             // nothing to do for factors & identifiers
             ParseNode::Factor(..) | ParseNode::Identifier(..) => {}
         }
@@ -352,3 +718,29 @@ impl Parser {
         args
     }
 }
+
+/// Whether a vector appears anywhere inside of the node
+fn has_tuple(node: &ParseNode) -> bool {
+    match node {
+        ParseNode::Tuple(..) => true,
+        ParseNode::Function(_, args) => args.iter().any(has_tuple),
+        ParseNode::FunctionDefine(_, _, body) | ParseNode::UnaryOp(_, body) => has_tuple(body),
+        ParseNode::BinOp(lhs, _, rhs) => has_tuple(lhs) || has_tuple(rhs),
+        ParseNode::Factor(..) | ParseNode::Identifier(..) => false,
+    }
+}
+
+/// Whether name = rhs gives a name to a point, vector or plane rather than
+/// being an implicit surface. The rhs is a point (eg. P = (1, 2, 3)) or
+/// builds a vector or plane, without using the axes
+fn is_definition(name: &str, rhs: &ParseNode) -> bool {
+    let constructs = match rhs {
+        ParseNode::Tuple(..) => true,
+        ParseNode::Function(func, _) => func == VECTOR || func == PLANE,
+        _ => false,
+    };
+
+    constructs
+        && !AXIS_NAMES.contains(&name)
+        && AXIS_NAMES.iter().all(|axis| !rhs.depends_on(axis))
+}
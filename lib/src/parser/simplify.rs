@@ -0,0 +1,431 @@
+/*
+ * Simplification pass over the abstract syntax tree, folds constant
+ * expressions & applies basic algebraic identities so that the generated GLSL
+ * is smaller and the equation can be shown back to the user in a canonical form
+ */
+
+use std::cmp::Ordering;
+
+use super::{
+    native::{NativeConstant, NativeFunc},
+    Operator, ParseNode, UNBOUNDED,
+};
+
+impl ParseNode {
+    /// Returns a simplified copy of the node tree
+    pub fn simplify(&self) -> ParseNode {
+        self.simplify_with(&[])
+    }
+
+    /// Simplifies with a list of bound names (function arguments), which
+    /// shadow native constants of the same name
+    fn simplify_with(&self, bound: &[String]) -> ParseNode {
+        match self {
+            ParseNode::FunctionDefine(name, args, body) => ParseNode::FunctionDefine(
+                name.clone(),
+                args.clone(),
+                Box::new(body.simplify_with(args)),
+            ),
+            ParseNode::Factor(val) => ParseNode::Factor(*val),
+            ParseNode::Identifier(name) => {
+                // replaces constants with their literal values
+                match NativeConstant::value(name) {
+                    Some(val) if !bound.contains(name) => ParseNode::Factor(val),
+                    _ => ParseNode::Identifier(name.clone()),
+                }
+            }
+            ParseNode::Function(name, args) => {
+                let args: Vec<ParseNode> =
+                    args.iter().map(|arg| arg.simplify_with(bound)).collect();
+
+                // pow is treated the same as the ^ operator
+                if name == "pow" && args.len() == 2 {
+                    return simplify_pow(args[0].clone(), args[1].clone());
+                }
+
+                // folds function if every argument is constant
+                let consts: Vec<f32> = args.iter().filter_map(ParseNode::constant).collect();
+
+                if consts.len() == args.len() {
+                    if let Some(val) = NativeFunc::eval(name, &consts).filter(|v| v.is_finite()) {
+                        return ParseNode::Factor(val);
+                    }
+                }
+
+                ParseNode::Function(name.clone(), args)
+            }
+            ParseNode::UnaryOp(op, node) => {
+                let node = node.simplify_with(bound);
+
+                match op {
+                    Operator::Add => node,
+                    Operator::Sub => negate(node),
+                    _ => ParseNode::UnaryOp(*op, Box::new(node)),
+                }
+            }
+            ParseNode::BinOp(lhs, op, rhs) => {
+                let lhs = lhs.simplify_with(bound);
+                let rhs = rhs.simplify_with(bound);
+
+                match op {
+                    Operator::Add | Operator::Sub => {
+                        simplify_sum(ParseNode::BinOp(Box::new(lhs), *op, Box::new(rhs)))
+                    }
+                    Operator::Mul | Operator::Div => {
+                        simplify_product(ParseNode::BinOp(Box::new(lhs), *op, Box::new(rhs)))
+                    }
+                    Operator::Pow => simplify_pow(lhs, rhs),
+                }
+            }
+            ParseNode::Tuple(items) => {
+                ParseNode::Tuple(items.iter().map(|item| item.simplify_with(bound)).collect())
+            }
+        }
+    }
+
+    /// Returns the value of the node if it is a literal
+    pub fn constant(&self) -> Option<f32> {
+        match self {
+            ParseNode::Factor(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Formats the node as a human readable equation (eg. 2*x^2 + 1), unlike
+    /// the Display implementation which outputs GLSL
+    pub fn readable(&self) -> String {
+        match self {
+            ParseNode::FunctionDefine(_, _, body) if self.is_implicit() => {
+                format!("{} = 0", body.readable())
+            }
+            ParseNode::FunctionDefine(_, args, body) if self.is_region() => {
+                let bounds = match &**body {
+                    ParseNode::Tuple(bounds) => bounds,
+                    _ => return body.readable(),
+                };
+
+                // the missing side of one sided inequalities is left out
+                let bound = |node: &ParseNode| match node.constant() {
+                    Some(val) if val.abs() >= UNBOUNDED => None,
+                    _ => Some(node.readable()),
+                };
+
+                let mut sides = vec![];
+                sides.extend(bound(&bounds[0]));
+                sides.push(args[2].clone());
+                sides.extend(bound(&bounds[1]));
+                sides.join(" <= ")
+            }
+            // named points, vectors & planes
+            ParseNode::FunctionDefine(name, args, body) if args.is_empty() => {
+                format!("{} = {}", name, body.readable())
+            }
+            ParseNode::FunctionDefine(name, args, body) => {
+                format!("{}({}) = {}", name, args.join(", "), body.readable())
+            }
+            ParseNode::Factor(val) => val.to_string(),
+            ParseNode::Identifier(name) => name.clone(),
+            ParseNode::Function(name, args) => {
+                let args: Vec<String> = args.iter().map(ParseNode::readable).collect();
+                format!("{}({})", name, args.join(", "))
+            }
+            ParseNode::UnaryOp(op, node) => {
+                format!("{}{}", op, node.readable_grouped(precedence(self), false))
+            }
+            ParseNode::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(ParseNode::readable).collect();
+                format!("({})", items.join(", "))
+            }
+            ParseNode::BinOp(lhs, op, rhs) => {
+                let prec = precedence(self);
+
+                // powers are right associative, everything else is left associative
+                let (lhs, rhs) = match op {
+                    Operator::Pow => (
+                        lhs.readable_grouped(prec, true),
+                        rhs.readable_grouped(prec, false),
+                    ),
+                    _ => (
+                        lhs.readable_grouped(prec, false),
+                        rhs.readable_grouped(prec, true),
+                    ),
+                };
+
+                match op {
+                    Operator::Add | Operator::Sub => format!("{} {} {}", lhs, op, rhs),
+                    _ => format!("{}{}{}", lhs, op, rhs),
+                }
+            }
+        }
+    }
+
+    /// Formats the node, wrapping it in parenthesis if it binds looser than
+    /// its parent
+    fn readable_grouped(&self, parent: u8, strict: bool) -> String {
+        let prec = precedence(self);
+
+        // negative literals bind like a unary minus
+        let prec = match self.constant() {
+            Some(val) if val < 0. => precedence(&ParseNode::UnaryOp(
+                Operator::Sub,
+                Box::new(ParseNode::Factor(-val)),
+            )),
+            _ => prec,
+        };
+
+        if prec < parent || (strict && prec == parent) {
+            format!("({})", self.readable())
+        } else {
+            self.readable()
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Helper functions
+// ----------------------------------------------------------------------------
+
+/// Operator precedence used when printing readable equations
+fn precedence(node: &ParseNode) -> u8 {
+    match node {
+        ParseNode::BinOp(_, Operator::Add, _) | ParseNode::BinOp(_, Operator::Sub, _) => 1,
+        ParseNode::BinOp(_, Operator::Mul, _) | ParseNode::BinOp(_, Operator::Div, _) => 2,
+        ParseNode::UnaryOp(..) => 3,
+        ParseNode::BinOp(_, Operator::Pow, _) => 4,
+        _ => 5,
+    }
+}
+
+/// Ordering used to canonicalize commutative terms, identifiers come before
+/// function calls which come before compound expressions
+fn canonical_order(a: &ParseNode, b: &ParseNode) -> Ordering {
+    let rank = |node: &ParseNode| match node {
+        ParseNode::Factor(..) => 0,
+        ParseNode::Identifier(..) => 1,
+        ParseNode::BinOp(_, Operator::Pow, _) => 2,
+        ParseNode::Function(..) => 3,
+        _ => 4,
+    };
+
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| a.to_string().cmp(&b.to_string()))
+}
+
+/// Negates a node, folding into literals & double negatives
+fn negate(node: ParseNode) -> ParseNode {
+    match node {
+        ParseNode::Factor(val) => ParseNode::Factor(-val),
+        ParseNode::UnaryOp(Operator::Sub, inner) => *inner,
+        node => ParseNode::UnaryOp(Operator::Sub, Box::new(node)),
+    }
+}
+
+/// Flattens a chain of + and - into a list of (is negated, term)
+fn flatten_sum(node: ParseNode, negated: bool, terms: &mut Vec<(bool, ParseNode)>) {
+    match node {
+        ParseNode::BinOp(lhs, Operator::Add, rhs) => {
+            flatten_sum(*lhs, negated, terms);
+            flatten_sum(*rhs, negated, terms);
+        }
+        ParseNode::BinOp(lhs, Operator::Sub, rhs) => {
+            flatten_sum(*lhs, negated, terms);
+            flatten_sum(*rhs, !negated, terms);
+        }
+        ParseNode::UnaryOp(Operator::Sub, inner) => flatten_sum(*inner, !negated, terms),
+        node => terms.push((negated, node)),
+    }
+}
+
+fn simplify_sum(node: ParseNode) -> ParseNode {
+    let mut terms = vec![];
+    flatten_sum(node, false, &mut terms);
+
+    // folds every constant term together & collects like terms by adding
+    // their coefficients (2*x + x = 3*x, x - x = 0)
+    let mut constant = 0.;
+    let mut collected: Vec<(f32, ParseNode)> = vec![];
+
+    for (negated, term) in terms {
+        let (coefficient, term) = split_coefficient(term);
+        let coefficient = if negated { -coefficient } else { coefficient };
+
+        match term {
+            None => constant += coefficient,
+            Some(term) => match collected.iter_mut().find(|(_, other)| *other == term) {
+                Some((total, _)) => *total += coefficient,
+                None => collected.push((coefficient, term)),
+            },
+        }
+    }
+
+    collected.retain(|(coefficient, _)| *coefficient != 0.);
+    collected.sort_by(|(_, a), (_, b)| canonical_order(a, b));
+
+    // rebuilds the chain, with constants at the end
+    let mut out: Option<ParseNode> = None;
+
+    for (coefficient, term) in collected {
+        let negated = coefficient < 0.;
+        let term = simplify_product(ParseNode::BinOp(
+            Box::new(ParseNode::Factor(coefficient.abs())),
+            Operator::Mul,
+            Box::new(term),
+        ));
+
+        out = Some(match out {
+            None if negated => negate(term),
+            None => term,
+            Some(lhs) => {
+                let op = if negated {
+                    Operator::Sub
+                } else {
+                    Operator::Add
+                };
+                ParseNode::BinOp(Box::new(lhs), op, Box::new(term))
+            }
+        });
+    }
+
+    match out {
+        None => ParseNode::Factor(constant),
+        Some(lhs) if constant > 0. => ParseNode::BinOp(
+            Box::new(lhs),
+            Operator::Add,
+            Box::new(ParseNode::Factor(constant)),
+        ),
+        Some(lhs) if constant < 0. => ParseNode::BinOp(
+            Box::new(lhs),
+            Operator::Sub,
+            Box::new(ParseNode::Factor(-constant)),
+        ),
+        Some(lhs) => lhs,
+    }
+}
+
+/// Flattens a chain of * and / into numerator & denominator lists, pulling
+/// any constants & negations out into the coefficient
+fn flatten_product(
+    node: ParseNode,
+    inverted: bool,
+    coefficient: &mut f32,
+    numerator: &mut Vec<ParseNode>,
+    denominator: &mut Vec<ParseNode>,
+) {
+    match node {
+        ParseNode::BinOp(lhs, Operator::Mul, rhs) => {
+            flatten_product(*lhs, inverted, coefficient, numerator, denominator);
+            flatten_product(*rhs, inverted, coefficient, numerator, denominator);
+        }
+        ParseNode::BinOp(lhs, Operator::Div, rhs) => {
+            flatten_product(*lhs, inverted, coefficient, numerator, denominator);
+            flatten_product(*rhs, !inverted, coefficient, numerator, denominator);
+        }
+        ParseNode::UnaryOp(Operator::Sub, inner) => {
+            *coefficient = -*coefficient;
+            flatten_product(*inner, inverted, coefficient, numerator, denominator);
+        }
+        // division by a literal zero is left alone so it still shows up
+        ParseNode::Factor(val) if !(inverted && val == 0.) => {
+            *coefficient = if inverted {
+                *coefficient / val
+            } else {
+                *coefficient * val
+            };
+        }
+        node if inverted => denominator.push(node),
+        node => numerator.push(node),
+    }
+}
+
+fn simplify_product(node: ParseNode) -> ParseNode {
+    let mut coefficient = 1.;
+    let mut numerator = vec![];
+    let mut denominator = vec![];
+
+    flatten_product(
+        node,
+        false,
+        &mut coefficient,
+        &mut numerator,
+        &mut denominator,
+    );
+
+    build_product(coefficient, numerator, denominator)
+}
+
+/// Splits a term into its constant coefficient & the remaining product, which
+/// is None if the term is entirely constant
+fn split_coefficient(node: ParseNode) -> (f32, Option<ParseNode>) {
+    let mut coefficient = 1.;
+    let mut numerator = vec![];
+    let mut denominator = vec![];
+
+    flatten_product(
+        node,
+        false,
+        &mut coefficient,
+        &mut numerator,
+        &mut denominator,
+    );
+
+    if numerator.is_empty() && denominator.is_empty() {
+        (coefficient, None)
+    } else {
+        (coefficient, Some(build_product(1., numerator, denominator)))
+    }
+}
+
+/// Builds a canonical product out of flattened terms
+fn build_product(
+    coefficient: f32,
+    mut numerator: Vec<ParseNode>,
+    mut denominator: Vec<ParseNode>,
+) -> ParseNode {
+    numerator.sort_by(canonical_order);
+    denominator.sort_by(canonical_order);
+
+    let chain = |terms: Vec<ParseNode>| {
+        terms
+            .into_iter()
+            .fold(None, |acc: Option<ParseNode>, term| {
+                Some(match acc {
+                    None => term,
+                    Some(lhs) => ParseNode::BinOp(Box::new(lhs), Operator::Mul, Box::new(term)),
+                })
+            })
+    };
+
+    // coefficient is placed at the front (2*x), with -1 becoming a negation.
+    // A 0 coefficient keeps the other terms, they aren't known to be finite
+    // (0*sqrt(-1) & 0/0 are NaN)
+    let numerator = match coefficient {
+        1. if !numerator.is_empty() => chain(numerator).unwrap(),
+        -1. if !numerator.is_empty() => negate(chain(numerator).unwrap()),
+        c => {
+            numerator.insert(0, ParseNode::Factor(c));
+            chain(numerator).unwrap()
+        }
+    };
+
+    match chain(denominator) {
+        None => numerator,
+        Some(den) => ParseNode::BinOp(Box::new(numerator), Operator::Div, Box::new(den)),
+    }
+}
+
+fn simplify_pow(base: ParseNode, exponent: ParseNode) -> ParseNode {
+    match (base.constant(), exponent.constant()) {
+        // folds constant powers
+        (Some(b), Some(e)) if b.powf(e).is_finite() => ParseNode::Factor(b.powf(e)),
+        // x^0 = 1, 1^x = 1
+        (_, Some(0.)) => ParseNode::Factor(1.),
+        (Some(1.), _) => ParseNode::Factor(1.),
+        // x^1 = x
+        (_, Some(1.)) => base,
+        // x^2 = x*x, which also avoids pow() being undefined for negative
+        // bases in GLSL
+        (_, Some(2.)) => ParseNode::BinOp(Box::new(base.clone()), Operator::Mul, Box::new(base)),
+        _ => ParseNode::BinOp(Box::new(base), Operator::Pow, Box::new(exponent)),
+    }
+}
@@ -0,0 +1,130 @@
+use js_sys::{Float32Array, Uint32Array};
+use wasm_bindgen::JsValue;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlVertexArrayObject};
+
+/// Floats per instance, its base & vector (graph space) & strength (0 to 1)
+pub const INSTANCE_SIZE: usize = 7;
+
+/// Glyph drawn once per instance with a single draw call, vector fields draw
+/// their arrows & the pieces of their streamlines this way. Glyph vertices are
+/// packed as position & normal (see ShaderGenerator::generate_arrow) &
+/// instances as INSTANCE_SIZE floats
+pub struct Arrows {
+    pub vao: WebGlVertexArrayObject,
+    pub index_len: usize,
+    pub instances: usize,
+    buffers: [WebGlBuffer; 3],
+}
+
+impl Arrows {
+    /// Uploads the glyph & instances, attributes are bound by their names in
+    /// program (see field.vert)
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        program: &WebGlProgram,
+        (verticies, indecies): (&[f32], &[u32]),
+        instances: &[f32],
+    ) -> Result<Self, JsValue> {
+        let vao = gl.create_vertex_array().ok_or("Failed to create VAO")?;
+        gl.bind_vertex_array(Some(&vao));
+
+        let array_buffer = |data: &[f32]| -> Result<WebGlBuffer, JsValue> {
+            let buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
+            gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
+
+            unsafe {
+                let view = Float32Array::view(data);
+                gl.buffer_data_with_array_buffer_view(
+                    WebGl2RenderingContext::ARRAY_BUFFER,
+                    &view,
+                    WebGl2RenderingContext::STATIC_DRAW,
+                );
+            }
+
+            Ok(buffer)
+        };
+
+        // points each attribute at the bound buffer, attributes the shader
+        // doesn't use are skipped. Instanced ones advance once per instance
+        let attribute = |name: &str, size: i32, stride: usize, offset: usize, divisor: u32| {
+            let loc = gl.get_attrib_location(program, name);
+            if loc < 0 {
+                return;
+            }
+
+            let float = std::mem::size_of::<f32>();
+            gl.vertex_attrib_pointer_with_i32(
+                loc as u32,
+                size,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                (stride * float) as i32,
+                (offset * float) as i32,
+            );
+            gl.enable_vertex_attrib_array(loc as u32);
+            gl.vertex_attrib_divisor(loc as u32, divisor);
+        };
+
+        // Setup for glyph ----------------------------------------------------
+        let vertex_buff = array_buffer(verticies)?;
+        attribute("vertexPosition", 3, 6, 0, 0);
+        attribute("vertexNormal", 3, 6, 3, 0);
+
+        let index_buff = gl.create_buffer().ok_or("Failed to create buffer")?;
+        gl.bind_buffer(
+            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&index_buff),
+        );
+
+        unsafe {
+            let index_view = Uint32Array::view(indecies);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                &index_view,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+
+        // Setup for instances ------------------------------------------------
+        let instance_buff = array_buffer(instances)?;
+        attribute("instanceBase", 3, INSTANCE_SIZE, 0, 1);
+        attribute("instanceVector", 3, INSTANCE_SIZE, 3, 1);
+        attribute("instanceStrength", 1, INSTANCE_SIZE, 6, 1);
+
+        // unbind VAO
+        gl.bind_vertex_array(None);
+
+        Ok(Self {
+            vao,
+            index_len: indecies.len(),
+            instances: instances.len() / INSTANCE_SIZE,
+            buffers: [vertex_buff, index_buff, instance_buff],
+        })
+    }
+
+    /// Draws every instance with the currently bound program, mode is a gl
+    /// primitive (eg. TRIANGLES)
+    pub fn render(&self, gl: &WebGl2RenderingContext, mode: u32) {
+        if self.instances == 0 {
+            return;
+        }
+
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.draw_elements_instanced_with_i32(
+            mode,
+            self.index_len as i32,
+            WebGl2RenderingContext::UNSIGNED_INT,
+            0,
+            self.instances as i32,
+        );
+        gl.bind_vertex_array(None);
+    }
+
+    /// Frees the GPU buffers, the instances can't be drawn afterwards
+    pub fn delete(&self, gl: &WebGl2RenderingContext) {
+        gl.delete_vertex_array(Some(&self.vao));
+        for buffer in self.buffers.iter() {
+            gl.delete_buffer(Some(buffer));
+        }
+    }
+}
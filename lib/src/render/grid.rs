@@ -3,7 +3,10 @@ use wasm_bindgen::{JsValue, UnwrapThrowExt};
 use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlVertexArrayObject};
 
 use crate::{
-    math::mat4::{Mat4, Transform},
+    math::{
+        axes::{Axes, AXES_GLSL},
+        mat4::{Mat4, Transform},
+    },
     shaders::shader_generator::ShaderGenerator,
 };
 
@@ -15,6 +18,7 @@ const GRID_VERT_SHADER: &str = include_str!("../shaders/src/grid.vert");
 pub struct Grid {
     program: WebGlProgram,
     pub freq: f32,
+    pub axes: Axes,
     pub vao: WebGlVertexArrayObject,
     pub index_len: usize,
     pub transform: Transform,
@@ -23,15 +27,18 @@ pub struct Grid {
 
 impl Grid {
     pub fn new(gl: &WebGl2RenderingContext, freq: f32) -> Result<Self, JsValue> {
-        // compiles shaders
+        // compiles shaders, both place the grid on the axes
         let frag_shader = compile_shader(
             gl,
             WebGl2RenderingContext::FRAGMENT_SHADER,
-            GRID_FRAG_SHADER,
+            GRID_FRAG_SHADER.replace("$AXES$", AXES_GLSL).as_str(),
         )?;
 
-        let vert_shader =
-            compile_shader(gl, WebGl2RenderingContext::VERTEX_SHADER, GRID_VERT_SHADER)?;
+        let vert_shader = compile_shader(
+            gl,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            GRID_VERT_SHADER.replace("$AXES$", AXES_GLSL).as_str(),
+        )?;
 
         // links program
         let program = link_program(gl, &vert_shader, &frag_shader)?;
@@ -114,6 +121,7 @@ impl Grid {
             index_len: indecies.len(),
             //color: (0.5, 0.5, 0.5, 1.),
             freq,
+            axes: Axes::default(),
         };
 
         out.set_uniforms(gl);
@@ -160,5 +168,7 @@ impl Grid {
         if let Some(loc) = loc("frequency") {
             gl.uniform1f(Some(&loc), self.freq);
         }
+
+        self.axes.set_uniforms(gl, &self.program, self.freq);
     }
 }
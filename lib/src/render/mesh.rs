@@ -0,0 +1,150 @@
+use js_sys::{Float32Array, Uint32Array};
+use wasm_bindgen::JsValue;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlVertexArrayObject};
+
+/// Indexed triangle mesh uploaded to the GPU, vertices are tightly packed xyz
+/// positions bound to attribute 0
+pub struct Mesh {
+    pub vao: WebGlVertexArrayObject,
+    pub index_len: usize,
+    buffers: [WebGlBuffer; 2],
+}
+
+impl Mesh {
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        verticies: &[f32],
+        indecies: &[u32],
+    ) -> Result<Self, JsValue> {
+        // Create & bind VAO to record vert info
+        let vao = gl.create_vertex_array().ok_or("Failed to create VAO")?;
+        gl.bind_vertex_array(Some(&vao));
+
+        // Setup for vertex buffer --------------------------------------------
+        let vertex_buff = gl.create_buffer().ok_or("Failed to create buffer")?;
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vertex_buff));
+
+        unsafe {
+            // allocate view to put verticies in & fills in with vertex data
+            let vert_view = Float32Array::view(verticies);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &vert_view,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+
+        // Setup for vertex indices buffer ------------------------------------
+        let index_buff = gl.create_buffer().ok_or("Failed to create buffer")?;
+        gl.bind_buffer(
+            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&index_buff),
+        );
+
+        unsafe {
+            let index_view = Uint32Array::view(indecies);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                &index_view,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+
+        // Setup shader vertex attributes
+        gl.vertex_attrib_pointer_with_i32(
+            0,
+            3,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            3 * std::mem::size_of::<f32>() as i32,
+            0,
+        );
+        gl.enable_vertex_attrib_array(0);
+
+        // unbind VAO
+        gl.bind_vertex_array(None);
+
+        Ok(Self {
+            vao,
+            index_len: indecies.len(),
+            buffers: [vertex_buff, index_buff],
+        })
+    }
+
+    /// Draws the mesh with the currently bound program, mode is a gl
+    /// primitive (eg. TRIANGLES)
+    pub fn render(&self, gl: &WebGl2RenderingContext, mode: u32) {
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.draw_elements_with_i32(
+            mode,
+            self.index_len as i32,
+            WebGl2RenderingContext::UNSIGNED_INT,
+            0,
+        );
+        gl.bind_vertex_array(None);
+    }
+
+    /// Points attribute loc at the mesh's vertices & binds its indices in
+    /// the currently bound VAO, so other VAOs can draw the same mesh
+    pub fn bind_to(&self, gl: &WebGl2RenderingContext, loc: u32) {
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buffers[0]));
+        gl.vertex_attrib_pointer_with_i32(
+            loc,
+            3,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            3 * std::mem::size_of::<f32>() as i32,
+            0,
+        );
+        gl.enable_vertex_attrib_array(loc);
+
+        gl.bind_buffer(
+            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&self.buffers[1]),
+        );
+    }
+
+    /// Frees the GPU buffers, the mesh can't be drawn afterwards
+    pub fn delete(&self, gl: &WebGl2RenderingContext) {
+        gl.delete_vertex_array(Some(&self.vao));
+        for buffer in self.buffers.iter() {
+            gl.delete_buffer(Some(buffer));
+        }
+    }
+
+    /// Detail of a plane covering about the given number of pixels on
+    /// screen, with a cell every pixels_per_cell. Snapped to a power of 2
+    /// within (min, max) so only a few levels of detail are ever built.
+    /// Planes filling the screen (infinitely many pixels) get max
+    pub fn plane_detail(pixels: f32, pixels_per_cell: f32, (min, max): (usize, usize)) -> usize {
+        let cells = pixels / pixels_per_cell;
+
+        if cells.is_nan() || cells <= min as f32 {
+            return min;
+        }
+        if cells >= max as f32 {
+            return max;
+        }
+
+        let detail = 2f32.powf(cells.log2().round()) as usize;
+        detail.clamp(min, max)
+    }
+
+    /// Columns & rows of a plane that keeps its cells roughly square when
+    /// stretched to the aspect ratio (width / height), while keeping about
+    /// as many cells as a detail x detail plane. Aspects are snapped to
+    /// powers of 2 so planes can be shared between similar rectangles
+    pub fn plane_resolution(aspect: f32, detail: usize) -> (usize, usize) {
+        let power = if aspect.is_finite() && aspect > 0. {
+            aspect.log2().round().clamp(-4., 4.)
+        } else {
+            0.
+        };
+
+        let ratio = 2f32.powf(power / 2.);
+        let cols = (detail as f32 * ratio).round() as usize;
+        let rows = (detail as f32 / ratio).round() as usize;
+
+        (cols.max(1), rows.max(1))
+    }
+}
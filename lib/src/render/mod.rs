@@ -1,2 +1,6 @@
+pub mod arrows;
 pub mod glutils;
-pub mod grid;
\ No newline at end of file
+pub mod grid;
+pub mod mesh;
+pub mod planes;
+pub mod points;
\ No newline at end of file
@@ -0,0 +1,35 @@
+use wasm_bindgen::JsValue;
+use web_sys::WebGl2RenderingContext;
+
+use super::mesh::Mesh;
+
+/// Polygon where a plane cuts through the box it is drawn in, in graph space
+/// (see Primitive::clip_plane). Drawn as a fan of triangles around its first
+/// corner
+pub struct PlaneQuad {
+    mesh: Mesh,
+}
+
+impl PlaneQuad {
+    pub fn new(gl: &WebGl2RenderingContext, corners: &[[f32; 3]]) -> Result<Self, JsValue> {
+        let verticies: Vec<f32> = corners.iter().flatten().copied().collect();
+        let indecies: Vec<u32> = (2..corners.len() as u32)
+            .flat_map(|i| vec![0, i - 1, i])
+            .collect();
+
+        Ok(Self {
+            mesh: Mesh::new(gl, &verticies, &indecies)?,
+        })
+    }
+
+    /// Draws the polygon with the currently bound program, mode is a gl
+    /// primitive (eg. TRIANGLES)
+    pub fn render(&self, gl: &WebGl2RenderingContext, mode: u32) {
+        self.mesh.render(gl, mode);
+    }
+
+    /// Frees the GPU buffers, the polygon can't be drawn afterwards
+    pub fn delete(&self, gl: &WebGl2RenderingContext) {
+        self.mesh.delete(gl);
+    }
+}
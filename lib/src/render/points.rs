@@ -0,0 +1,123 @@
+use js_sys::Float32Array;
+use wasm_bindgen::JsValue;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlVertexArrayObject};
+
+use crate::shaders::shader_generator::ShaderGenerator;
+
+use super::mesh::Mesh;
+
+/// Floats per sprite, its center (graph space), size & index
+pub const SPRITE_SIZE: usize = 5;
+
+/// Square that point.vert turns to face the camera, every point is drawn with
+/// it by moving it to the point's center
+pub struct Billboard {
+    quad: Mesh,
+}
+
+impl Billboard {
+    pub fn new(gl: &WebGl2RenderingContext) -> Result<Self, JsValue> {
+        let (verticies, indecies) = ShaderGenerator::generate_plane(1);
+
+        Ok(Self {
+            quad: Mesh::new(gl, &verticies, &indecies)?,
+        })
+    }
+
+    /// Draws a point with the currently bound program, which sets its center
+    pub fn render(&self, gl: &WebGl2RenderingContext) {
+        self.quad.render(gl, WebGl2RenderingContext::TRIANGLES);
+    }
+}
+
+/// Points of a dataset, drawn as an instance of the billboard's square each
+/// (see sprite.vert). Sprites are packed as SPRITE_SIZE floats
+pub struct Sprites {
+    pub vao: WebGlVertexArrayObject,
+    pub count: usize,
+    buffer: WebGlBuffer,
+}
+
+impl Sprites {
+    /// Uploads the sprites, attributes are bound by their names in program
+    /// on top of the billboard's square
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        program: &WebGlProgram,
+        billboard: &Billboard,
+        sprites: &[f32],
+    ) -> Result<Self, JsValue> {
+        // the square's buffers are shared with the billboard
+        let vao = gl.create_vertex_array().ok_or("Failed to create VAO")?;
+        gl.bind_vertex_array(Some(&vao));
+
+        let loc = gl.get_attrib_location(program, "vertexPosition");
+        if loc >= 0 {
+            billboard.quad.bind_to(gl, loc as u32);
+        }
+
+        let buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
+
+        unsafe {
+            let view = Float32Array::view(sprites);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &view,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+
+        // attributes the shader doesn't use are skipped
+        let attribute = |name: &str, size: i32, offset: usize| {
+            let loc = gl.get_attrib_location(program, name);
+            if loc < 0 {
+                return;
+            }
+
+            let float = std::mem::size_of::<f32>();
+            gl.vertex_attrib_pointer_with_i32(
+                loc as u32,
+                size,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                (SPRITE_SIZE * float) as i32,
+                (offset * float) as i32,
+            );
+            gl.enable_vertex_attrib_array(loc as u32);
+            gl.vertex_attrib_divisor(loc as u32, 1);
+        };
+
+        attribute("instanceCenter", 3, 0);
+        attribute("instanceSize", 1, 3);
+        attribute("instanceIndex", 1, 4);
+
+        // unbind VAO
+        gl.bind_vertex_array(None);
+
+        Ok(Self {
+            vao,
+            count: sprites.len() / SPRITE_SIZE,
+            buffer,
+        })
+    }
+
+    /// Draws every sprite with the currently bound program
+    pub fn render(&self, gl: &WebGl2RenderingContext, billboard: &Billboard) {
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.draw_elements_instanced_with_i32(
+            WebGl2RenderingContext::TRIANGLES,
+            billboard.quad.index_len as i32,
+            WebGl2RenderingContext::UNSIGNED_INT,
+            0,
+            self.count as i32,
+        );
+        gl.bind_vertex_array(None);
+    }
+
+    /// Frees the GPU buffers, the billboard's square is kept
+    pub fn delete(&self, gl: &WebGl2RenderingContext) {
+        gl.delete_vertex_array(Some(&self.vao));
+        gl.delete_buffer(Some(&self.buffer));
+    }
+}
@@ -2,15 +2,20 @@ pub struct ShaderGenerator {}
 
 impl ShaderGenerator {
     pub fn generate_plane(detail: usize) -> (Vec<f32>, Vec<u32>) {
+        Self::generate_grid(detail, detail)
+    }
+
+    /// Plane with a different number of cells along x & y, used when the
+    /// plane is stretched into a long rectangle
+    pub fn generate_grid(cols: usize, rows: usize) -> (Vec<f32>, Vec<u32>) {
         let mut points = vec![];
         let mut indecies = vec![];
-        let detailf32 = detail as f32;
 
-        for y in 0..(detail + 1) {
-            for x in 0..(detail + 1) {
-                // normalizes X and Y between -1. and 1.
-                let normal_x = (2. * (x as f32 / detailf32) - 1.) / 2.;
-                let normal_y = (2. * (y as f32 / detailf32) - 1.) / 2.;
+        for y in 0..(rows + 1) {
+            for x in 0..(cols + 1) {
+                // normalizes X and Y between -0.5 and 0.5
+                let normal_x = x as f32 / cols as f32 - 0.5;
+                let normal_y = y as f32 / rows as f32 - 0.5;
 
                 // pushes to points
                 points.push(normal_x);
@@ -19,9 +24,9 @@ impl ShaderGenerator {
             }
         }
 
-        let to_index = |x: usize, y: usize| (y * (detail + 1) + x) as u32;
-        for x in 0..detail {
-            for y in 0..detail {
+        let to_index = |x: usize, y: usize| (y * (cols + 1) + x) as u32;
+        for x in 0..cols {
+            for y in 0..rows {
                 // top triangle
                 indecies.push(to_index(x, y));
                 indecies.push(to_index(x + 1, y));
@@ -37,4 +42,147 @@ impl ShaderGenerator {
         (points, indecies)
     }
 
+    /// Grid laid out like generate_grid, where the first and/or last column
+    /// collapses into a single vertex (a pole). Wrapped around the pole a
+    /// grid becomes a disk, or a sphere with both poles, without the sliver
+    /// triangles that meet at a pole of a plain grid
+    pub fn generate_polar(cols: usize, rows: usize, poles: (bool, bool)) -> (Vec<f32>, Vec<u32>) {
+        let mut points = vec![];
+        let mut indecies = vec![];
+
+        // index of the first vertex of each column
+        let mut columns = vec![];
+        for x in 0..(cols + 1) {
+            let collapsed = (x == 0 && poles.0) || (x == cols && poles.1);
+            let normal_x = x as f32 / cols as f32 - 0.5;
+            columns.push((points.len() / 3) as u32);
+
+            let count = if collapsed { 1 } else { rows + 1 };
+            for y in 0..count {
+                points.push(normal_x);
+                points.push(y as f32 / rows as f32 - 0.5);
+                points.push(0.);
+            }
+        }
+
+        let to_index = |x: usize, y: usize| {
+            let collapsed = (x == 0 && poles.0) || (x == cols && poles.1);
+            columns[x] + if collapsed { 0 } else { y as u32 }
+        };
+
+        for x in 0..cols {
+            for y in 0..rows {
+                let triangles = [
+                    [to_index(x, y), to_index(x + 1, y), to_index(x, y + 1)],
+                    [
+                        to_index(x, y + 1),
+                        to_index(x + 1, y),
+                        to_index(x + 1, y + 1),
+                    ],
+                ];
+
+                // triangles with 2 corners on a pole have no area
+                for [a, b, c] in triangles.iter() {
+                    if a != b && b != c && a != c {
+                        indecies.extend_from_slice(&[*a, *b, *c]);
+                    }
+                }
+            }
+        }
+
+        (points, indecies)
+    }
+
+    /// Unit cube (0 to 1) that bounds implicit surfaces, faces wind counter
+    /// clockwise seen from outside
+    pub fn generate_cube() -> (Vec<f32>, Vec<u32>) {
+        let mut points = vec![];
+
+        // corner i has x, y & z from its 1st, 2nd & 3rd bits
+        for i in 0..8 {
+            points.push((i & 1) as f32);
+            points.push((i >> 1 & 1) as f32);
+            points.push((i >> 2 & 1) as f32);
+        }
+
+        let indecies = vec![
+            0, 2, 1, 1, 2, 3, // z = 0
+            4, 5, 6, 6, 5, 7, // z = 1
+            0, 1, 4, 4, 1, 5, // y = 0
+            2, 6, 3, 3, 6, 7, // y = 1
+            0, 4, 2, 2, 4, 6, // x = 0
+            1, 3, 5, 5, 3, 7, // x = 1
+        ];
+
+        (points, indecies)
+    }
+
+    /// Arrow of length 1 pointing along z from the origin, a shaft capped at
+    /// its base with a cone for its head. Vertices are packed as position &
+    /// normal, with radii relative to the length of the arrow
+    pub fn generate_arrow(sides: usize) -> (Vec<f32>, Vec<u32>) {
+        let (shaft, head, head_length): (f32, f32, f32) = (0.04, 0.12, 0.3);
+        let neck = 1. - head_length;
+
+        // the cone's normal leans towards the tip
+        let slant = (head_length * head_length + head * head).sqrt();
+        let cone = (head_length / slant, head / slant);
+
+        Self::generate_revolved(
+            &[
+                // base cap
+                [(0., 0., 0., -1.), (shaft, 0., 0., -1.)],
+                // shaft
+                [(shaft, 0., 1., 0.), (shaft, neck, 1., 0.)],
+                // underside of the head
+                [(shaft, neck, 0., -1.), (head, neck, 0., -1.)],
+                // head
+                [(head, neck, cone.0, cone.1), (0., 1., cone.0, cone.1)],
+            ],
+            sides,
+        )
+    }
+
+    /// Open tube of radius 1 from z = 0 to 1, packed like generate_arrow
+    pub fn generate_tube(sides: usize) -> (Vec<f32>, Vec<u32>) {
+        Self::generate_revolved(&[[(1., 0., 1., 0.), (1., 1., 1., 0.)]], sides)
+    }
+
+    /// Bands swept around z, each between 2 rings given as (radius, z, radial
+    /// normal, normal along z)
+    fn generate_revolved(
+        bands: &[[(f32, f32, f32, f32); 2]],
+        sides: usize,
+    ) -> (Vec<f32>, Vec<u32>) {
+        let mut points = vec![];
+        let mut indecies = vec![];
+
+        for band in bands.iter() {
+            let start = (points.len() / 6) as u32;
+
+            for &(radius, z, radial, along) in band.iter() {
+                for side in 0..sides {
+                    let angle = side as f32 / sides as f32 * std::f32::consts::TAU;
+                    let (sin, cos) = angle.sin_cos();
+
+                    points.extend_from_slice(&[radius * cos, radius * sin, z]);
+                    points.extend_from_slice(&[radial * cos, radial * sin, along]);
+                }
+            }
+
+            let to_index = |ring: usize, side: usize| start + (ring * sides + side % sides) as u32;
+            for side in 0..sides {
+                indecies.extend_from_slice(&[
+                    to_index(0, side),
+                    to_index(0, side + 1),
+                    to_index(1, side),
+                    to_index(1, side),
+                    to_index(0, side + 1),
+                    to_index(1, side + 1),
+                ]);
+            }
+        }
+
+        (points, indecies)
+    }
 }
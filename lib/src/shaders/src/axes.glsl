@@ -0,0 +1,54 @@
+// ----------------------------------------------------------------------------
+// Axis Mapping, shared by every shader that places things on the graph
+// ----------------------------------------------------------------------------
+
+#define LN10 2.302585092994046
+
+// Each axis maps its axis coordinate (log10 of the graph value on log axes)
+// linearly onto the world, the center of the axis lands on the origin
+uniform vec3 axisOffset;
+uniform vec3 axisScale;
+uniform vec3 axisStretch;
+// 1 for logarithmic axes, 0 for linear
+uniform vec3 axisLog;
+
+float toAxis(float value, float isLog) {
+    return isLog > .5 ? log(value) / LN10 : value;
+}
+
+float fromAxis(float coord, float isLog) {
+    return isLog > .5 ? pow(10., coord) : coord;
+}
+
+// graph space -> world space
+vec3 graphToWorld(vec3 graph) {
+    vec3 coord = vec3(
+        toAxis(graph.x, axisLog.x),
+        toAxis(graph.y, axisLog.y),
+        toAxis(graph.z, axisLog.z)
+    );
+
+    return (coord - axisOffset) / axisScale * axisStretch;
+}
+
+// rate of change of the world position with respect to the graph position
+vec3 worldSlope(vec3 graph) {
+    vec3 slope = vec3(
+        axisLog.x > .5 ? 1. / (graph.x * LN10) : 1.,
+        axisLog.y > .5 ? 1. / (graph.y * LN10) : 1.,
+        axisLog.z > .5 ? 1. / (graph.z * LN10) : 1.
+    );
+
+    return slope / axisScale * axisStretch;
+}
+
+// world space -> graph space
+vec3 worldToGraph(vec3 world) {
+    vec3 coord = world / axisStretch * axisScale + axisOffset;
+
+    return vec3(
+        fromAxis(coord.x, axisLog.x),
+        fromAxis(coord.y, axisLog.y),
+        fromAxis(coord.z, axisLog.z)
+    );
+}
@@ -0,0 +1,263 @@
+#version 300 es
+
+precision highp float;
+
+/*
+ * Vertex shader for space curves (x, y, z) = c(t), the plane is rolled into a
+ * tube around the curve, x runs along t & y around the tube. The same plane
+ * is also wrapped into a ball to draw the point moving along the curve.
+ * Shares equation.frag with surfaces
+ */
+
+// ----------------------------------------------------------------------------
+// Math Constants
+// ----------------------------------------------------------------------------
+
+#define PI 3.1415926538
+#define TAU 6.283185307179586
+
+// ----------------------------------------------------------------------------
+// Graph Constants
+// ----------------------------------------------------------------------------
+
+// Determines tangent sampling detail, as a portion of the range of t
+#define EPSILON 0.0001
+
+// Set to 1 when the derivative of the curve was derived symbolically,
+// tangents are then calculated from it instead of sampling with EPSILON
+#define ANALYTIC_TANGENTS $ANALYTIC_TANGENTS$
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+// Color for entire graph (constant)
+uniform vec4 graphColor;
+
+// Fun variables for user to play around with
+uniform float TIME;
+
+uniform float oldToNew;
+
+$AXES$
+
+// Range of t the tube is swept over (t min, t max, unused, unused)
+uniform vec4 domain;
+
+// Radius of the tube in world space
+uniform float tubeRadius;
+
+// Number of rings along the tube, each ring's frame is carried over from the
+// one before it
+uniform float tubeRings;
+
+// Set to 1 when drawing the moving point instead of the tube, which is a ball
+// of radius pointRadius around the curve at pointParam
+uniform float isPoint;
+uniform float pointParam;
+uniform float pointRadius;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+layout(location = 0) in vec3 vertexPosition;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Vertex position in transformed space (without perspective applied)
+out vec3 v_Vertex;
+// Symbolic Vertex position on graph
+out vec3 v_GraphVertex;
+// Vertex Normal
+out vec3 v_Normal;
+// Curves have no breaks, always 0 (see equation.frag)
+flat out vec3 v_Break;
+out float v_PlaneX;
+// Below 1 when the vertex is undefined (NaN or infinite), triangles touching
+// it are discarded
+out float v_Valid;
+
+// ----------------------------------------------------------------------------
+// Code
+// ----------------------------------------------------------------------------
+
+vec3 func(float param);
+vec3 curve(float param);
+vec3 tangent(float param);
+bool isFinite(vec3 v);
+vec3 ringSide(float param);
+
+void main() {
+    vec2 plane = vertexPosition.xy + .5;
+    float around = plane.y * TAU;
+
+    float param = isPoint > .5 ? pointParam : mix(domain.x, domain.y, plane.x);
+    vec3 graphPos = func(param);
+    vec3 center = curve(param);
+
+    // frame around the tangent, carried along the curve so the tube doesn't
+    // twist
+    vec3 forward = tangent(param);
+    vec3 side = ringSide(param);
+    vec3 ring = cos(around) * side + sin(around) * cross(forward, side);
+
+    // the ball goes from the back of the point to the front of it along x
+    vec3 offset = ring * tubeRadius;
+    if(isPoint > .5) {
+        float latitude = plane.x * PI;
+        ring = sin(latitude) * ring - cos(latitude) * forward;
+        offset = ring * pointRadius;
+    }
+
+    vec3 world = center + offset;
+
+    v_Break = vec3(0.);
+    v_PlaneX = 0.;
+    v_Valid = 1.;
+
+    // keeps NaN out of the driver, the vertex is discarded anyways
+    if(!isFinite(world)) {
+        v_Valid = 0.;
+        graphPos = vec3(0.);
+        world = vec3(0.);
+    }
+
+    vec4 pos4 = vec4(world, 1.);
+
+    // calculate fragment color
+    v_Vertex = vec3(mView * mWorld * pos4); // transformed matrix
+    v_Normal = ring;
+    v_GraphVertex = vec3(graphPos.xy, pos4.z);
+
+    gl_Position = mProj * mView * mWorld * pos4; // applies projection
+}
+
+// Predefined functions, round is written as floor(a + 0.5) by the parser
+float hypot(float x, float y) {
+    return length(vec2(x, y));
+}
+
+float lerp(float from, float to, float travel) {
+    return from + (to - from) * travel;
+}
+
+float time() {
+    return TIME;
+}
+
+// used to trick glsl optimizer
+$EXTERN_FUNCTIONS$
+
+// point on the curve in graph space
+vec3 func(float param) {
+    $PARAMS$
+
+    // template $$ replaced in rust
+    if(oldToNew < 1.) {
+        return mix($OLD_FUNCTION$, $CURRENT_FUNCTION$, oldToNew);
+    }
+
+    return $CURRENT_FUNCTION$;
+}
+
+// position of the curve in world space
+vec3 curve(float param) {
+    vec3 world = graphToWorld(func(param));
+    world.z *= - amplitude;
+    return world;
+}
+
+// derivative of func with respect to t, in graph space
+vec3 derivative(float param) {
+    $PARAMS$
+
+    if(oldToNew < 1.) {
+        return mix($OLD_DT$, $CURRENT_DT$, oldToNew);
+    }
+
+    return $CURRENT_DT$;
+}
+
+bool isFinite(vec3 v) {
+    return !any(notEqual(v, v)) && all(lessThan(abs(v), vec3(1e30)));
+}
+
+// direction of the curve in world space
+vec3 tangent(float param) {
+#if ANALYTIC_TANGENTS
+    // the derivative is carried into world space through each axis' mapping
+    vec3 scale = worldSlope(func(param)) * vec3(1., 1., -amplitude);
+    vec3 d = derivative(param) * scale;
+#else
+    float delta = (domain.y - domain.x) * EPSILON;
+    vec3 d = curve(param + delta) - curve(param - delta);
+#endif
+
+    return isFinite(d) && length(d) > 0. ? normalize(d) : vec3(1., 0., 0.);
+}
+
+// side of the first ring, perpendicular to its direction
+vec3 firstSide(vec3 forward) {
+    vec3 up = abs(forward.z) < .9 ? vec3(0., 0., 1.) : vec3(1., 0., 0.);
+    return normalize(cross(forward, up));
+}
+
+vec3 reflectAcross(vec3 v, vec3 across) {
+    float len = dot(across, across);
+    return len > 0. ? v - across * (2. * dot(across, v) / len) : v;
+}
+
+// side of the ring at param, carried ring by ring from the start of the curve
+// by reflecting it twice (double reflection, Wang et al. 2008) so it rotates
+// as little as possible, matching TriMesh::curve_tube
+vec3 ringSide(float param) {
+    float spacing = (domain.y - domain.x) / tubeRings;
+    float at = domain.x;
+    vec3 center = curve(at);
+    vec3 forward = tangent(at);
+    vec3 side = firstSide(forward);
+
+    if(!(spacing > 0.)) {
+        return side;
+    }
+
+    // whole rings up to param, then the rest of the way to it
+    int rings = int(floor((param - domain.x) / spacing + .001));
+    for(int i = 1; i <= rings + 1; i++) {
+        float next = min(domain.x + float(i) * spacing, param);
+        if(next - at < spacing * .001) {
+            break;
+        }
+
+        vec3 nextCenter = curve(next);
+        vec3 ahead = tangent(next);
+
+        // reflects across the plane between the centers, then across the one
+        // taking the reflected direction onto the next
+        vec3 chord = nextCenter - center;
+        vec3 between = reflectAcross(side, chord);
+        vec3 nextSide = reflectAcross(between, ahead - reflectAcross(forward, chord));
+
+        // kept perpendicular to the direction, starting over where the curve
+        // is undefined
+        nextSide = normalize(nextSide - ahead * dot(ahead, nextSide));
+        side = isFinite(nextSide) ? nextSide : firstSide(ahead);
+
+        center = nextCenter;
+        forward = ahead;
+        at = next;
+    }
+
+    return side;
+}
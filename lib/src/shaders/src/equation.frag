@@ -1,10 +1,10 @@
+#version 300 es
+
 /*
  * Fragment shader for every equation rendered
  */
 
-#if GL_ES
 precision highp float;
-#endif
 
 // Uncomment to render surface normals instead of graph color
 
@@ -20,11 +20,23 @@ uniform vec3 globalLightPosition;
 #define MAX_LIGHT 1.05
 
 // Vertex position in transformed space (without perspective applied)
-varying vec3 v_Vertex;
+in vec3 v_Vertex;
 // Symbolic Vertex position on graph
-varying vec3 v_GraphVertex;
+in vec3 v_GraphVertex;
 // Vertex Normal
-varying vec3 v_Normal;
+in vec3 v_Normal;
+// Flat varyings come from the last vertex of each triangle, which is shared
+// by 2 triangles of the plane. x is above 0 when the triangle on its +x side
+// spans a break in the surface, y when the one on its -x side does & z is
+// its position along the plane's x
+flat in vec3 v_Break;
+// Position along the plane's x, compared with v_Break.z to tell which of the
+// 2 triangles the fragment is in
+in float v_PlaneX;
+// Below 1 on triangles touching a vertex outside of the graph
+in float v_Valid;
+
+out vec4 fragColor;
 
 // mathematical sigmoid function
 float sigmoid(float x) {
@@ -38,6 +50,18 @@ float sigmoidClamp(float x, float minimum, float maximum) {
 }
 
 void main() {
+  // triangles spanning a break aren't drawn, so poles aren't stitched together
+  float broken = v_PlaneX > v_Break.z ? v_Break.x : v_Break.y;
+  if(broken > 0.) {
+    discard;
+  }
+
+  // holes in the graph (NaN or infinite) aren't drawn, vertices next to them
+  // sit on the edge of the graph so the hole is clipped cleanly
+  if(v_Valid < .999) {
+    discard;
+  }
+
   // get normal direction to light
   vec3 toLight = normalize(globalLightPosition - v_Vertex);
 
@@ -59,5 +83,5 @@ void main() {
   alpha = clamp(alpha, 0., 1.);
 
   // multiplies color by cosAngle 
-  gl_FragColor = vec4(vec3(graphColor) * cosAngle, graphColor.a * alpha);
+  fragColor = vec4(vec3(graphColor) * cosAngle, graphColor.a * alpha);
 }
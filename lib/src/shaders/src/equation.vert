@@ -1,6 +1,6 @@
-#if GL_ES
+#version 300 es
+
 precision highp float;
-#endif
 
 // ----------------------------------------------------------------------------
 // Math Constants
@@ -13,14 +13,24 @@ precision highp float;
 // Graph Constants 
 // ----------------------------------------------------------------------------
 
-// Vertical Stretch
-#define AMPLITUDE 1.
-
 // Determines normal sampling detail 
 // Lower means more accuracy but you need to be careful because it can cause funky
 // floating point rounding errors
 #define EPSILON 0.001
 
+// Set to 1 when the partial derivatives of the equation were derived
+// symbolically, normals are then calculated from the gradient instead of
+// sampling with EPSILON
+#define ANALYTIC_NORMALS $ANALYTIC_NORMALS$
+
+// How many times an edge is halved while looking for a break, matches
+// BREAK_STEPS in mesh/breaks.rs
+#define BREAK_STEPS 8
+
+// How many times an edge is halved while looking for the edge of the graph,
+// matches EDGE_STEPS in mesh/breaks.rs
+#define EDGE_STEPS 8
+
 // ----------------------------------------------------------------------------
 // Uniforms
 // ----------------------------------------------------------------------------
@@ -30,7 +40,8 @@ uniform mat4 mWorld;
 uniform mat4 mView;
 uniform mat4 mProj;
 
-uniform float graphFrequency;
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
 
 // Color for entire graph (constant)
 uniform vec4 graphColor;
@@ -39,64 +50,165 @@ uniform vec4 graphColor;
 uniform float TIME;
 
 uniform float oldToNew;
+
+$AXES$
+
+// Rectangle the graph is drawn over in axis coordinates
+// (x min, x max, y min, y max)
+uniform vec4 domain;
+
+// Distance to the neighbouring vertices on the plane, along x & y
+uniform vec2 breakSpacing;
+
+// Set when the graph may break over its domain (see may_break in
+// mesh/breaks.rs), breaks aren't looked for otherwise
+uniform bool checkBreaks;
+
 // ----------------------------------------------------------------------------
 // Vertex Attributes
 // ----------------------------------------------------------------------------
 
-attribute vec3 vertexPosition;
+layout(location = 0) in vec3 vertexPosition;
 
 // ----------------------------------------------------------------------------
 // Varying vars for fragment shader
 // ----------------------------------------------------------------------------
 
 // Vertex position in transformed space (without perspective applied)
-varying vec3 v_Vertex;
+out vec3 v_Vertex;
 // Symbolic Vertex position on graph
-varying vec3 v_GraphVertex;
+out vec3 v_GraphVertex;
 // Vertex Normal
-varying vec3 v_Normal;
+out vec3 v_Normal;
+// Whether the 2 triangles this vertex is the last vertex of span a break in
+// the surface (a jump or a pole), along with its position along the plane's
+// x to tell them apart. See equation.frag
+flat out vec3 v_Break;
+out float v_PlaneX;
+// Below 1 when the vertex is outside of the graph (NaN or infinite) & couldn't
+// be moved onto its edge, triangles touching it are discarded
+out float v_Valid;
 
 // ----------------------------------------------------------------------------
 // Code
 // ----------------------------------------------------------------------------
 
 vec3 func(vec2 pos);
-vec3 normal(vec2 pos);
+vec3 surface(vec2 pos);
+vec3 orient(vec3 local);
+vec3 toLocal(vec3 v);
+vec3 normal(vec3 graph);
+vec2 gradient(vec2 pos);
+vec2 planeToGraph(vec2 plane);
+bool spansBreak(vec2 a, vec2 b);
+bool isFinite(vec3 v);
+bool isUndefined(vec3 v);
+bool snapToEdge(vec2 plane, inout vec2 graph);
 
 void main() {
-    vec2 graphVert = vertexPosition.xy * graphFrequency;
-    vec4 pos4 = vec4(func(graphVert) / graphFrequency, 1.);
+    vec2 graphVert = planeToGraph(vertexPosition.xy);
+
+    // vertices outside of the graph are moved onto its edge so holes are
+    // clipped cleanly, those with nothing to snap to & poles are dropped
+    v_Valid = 1.;
+    vec3 world = surface(graphVert);
+    if(!isFinite(world)
+        && (!isUndefined(world) || !snapToEdge(vertexPosition.xy, graphVert))) {
+        v_Valid = 0.;
+    }
+
+    vec3 graphPos = func(graphVert);
+    vec3 local = surface(graphVert);
+
+    // keeps NaN out of the driver, the vertex is discarded anyways
+    if(v_Valid < 1.) {
+        graphPos = vec3(graphVert, 0.);
+        local = vec3(graphToWorld(vec3(graphVert, 1.)).xy, 0.);
+    }
+
+    vec4 pos4 = vec4(orient(local), 1.);
 
     // calculate fragment color
     v_Vertex = vec3(mView * mWorld * pos4); // transformed matrix
-    v_Normal = normal(graphVert);
+    v_Normal = orient(normal(graphPos));
     v_GraphVertex = vec3(graphVert, pos4.z);
 
+    // planes from generate_grid end each triangle on a vertex with its
+    // largest y, so a vertex is the last vertex of the triangle below it on
+    // its +x side & the one below it on its -x side. Only the edges of those
+    // 2 are checked
+    v_Break = vec3(0., 0., vertexPosition.x);
+    v_PlaneX = vertexPosition.x;
+
+    if(checkBreaks) {
+        vec2 dx = vec2(breakSpacing.x, 0.);
+        vec2 dy = vec2(0., breakSpacing.y);
+        vec2 plane = vertexPosition.xy;
+
+        vec2 below = planeToGraph(plane - dy);
+        vec2 belowRight = planeToGraph(plane + dx - dy);
+        vec2 left = planeToGraph(plane - dx);
+        bool downwards = spansBreak(graphVert, below);
+
+        if(downwards || spansBreak(below, belowRight) || spansBreak(belowRight, graphVert)) {
+            v_Break.x = 1.;
+        }
+        if(downwards || spansBreak(below, left) || spansBreak(left, graphVert)) {
+            v_Break.y = 1.;
+        }
+    }
+
     gl_Position = mProj * mView * mWorld * pos4; // applies projection
 }
 
-vec3 normalTriPos(in vec2 origin, float ang) {
-    float x = origin.x + cos(ang) * EPSILON;
-    float y = origin.y + sin(ang) * EPSILON;
-    return func(vec2(x, y));
+// difference to the point step away in graph space, taken on whichever side
+// of the point is inside the graph so NaN never leaks into the normal
+vec3 sideStep(vec2 pos, vec3 center, vec2 step) {
+    vec3 forward = surface(pos + step);
+    if(isFinite(forward)) {
+        return forward - center;
+    }
+
+    vec3 backward = surface(pos - step);
+    if(isFinite(backward)) {
+        return center - backward;
+    }
+
+    // flat along the step when the graph is too thin to sample
+    return vec3(step, 0.);
 }
 
-vec3 normal(in vec2 pos) {
-    // gets 3 points circularly around point to form a triangle with the
-    // pos at the center
-    vec3 triPoint1 = normalTriPos(pos, 0.);
-    vec3 triPoint2 = normalTriPos(pos, TAU / 3.);
-    vec3 triPoint3 = normalTriPos(pos, 2. * TAU / 3.);
+// normal in world space, graph is a point on the surface in graph space
+vec3 normal(in vec3 graph) {
+    vec2 pos = graph.xy;
+
+#if ANALYTIC_NORMALS
+    // normal of the surface (X, Y, Z(X, Y)) is (dZ/dX, dZ/dY, -1), which
+    // matches the winding of the sampled normal below. The graph gradient is
+    // carried into world space through each axis' mapping. Gradients blow up
+    // on the edge of the graph (eg. sqrt(1-x^2)), those fall back to sampling
+    vec3 slope = worldSlope(graph) * toLocal(vec3(1., 1., -amplitude));
+    vec2 grad = gradient(pos) * slope.z / slope.xy;
+    if(isFinite(vec3(grad, 0.))) {
+        return vec3(grad, -1.);
+    }
+#endif
+
+    vec3 center = surface(pos);
+    if(!isFinite(center)) {
+        return vec3(0., 0., -1.);
+    }
 
-    // gets 2 lines of the triangle
-    vec3 a = triPoint2 - triPoint1;
-    vec3 b = triPoint3 - triPoint1;
+    // gets 2 lines along x & y from the center
+    vec3 a = sideStep(pos, center, vec2(EPSILON, 0.));
+    vec3 b = sideStep(pos, center, vec2(0., EPSILON));
 
-    // gets cross product of the 2 lines to get the normal of the triangle, 
-    return -cross(a, b);
+    // gets cross product of the 2 lines to get the normal of the surface
+    vec3 n = -cross(a, b);
+    return isFinite(n) && length(n) > 0. ? n : vec3(0., 0., -1.);
 }
 
-// Predefined functions
+// Predefined functions, round is written as floor(a + 0.5) by the parser
 float hypot(float x, float y) {
     return length(vec2(x, y));
 }
@@ -109,14 +221,6 @@ float time() {
     return TIME;
 }
 
-float round(float a) {
-    if (fract(a) >= 0.5) {
-        return ceil(a);
-    } else {
-        return floor(a);
-    }
-}
-
 // used to trick glsl optimizer
 $EXTERN_FUNCTIONS$
 
@@ -134,6 +238,153 @@ vec3 func(vec2 pos) {
         z = $CURRENT_FUNCTION$;
     }
 
-    z *= - AMPLITUDE;
     return vec3(x, y, z);
 }
+
+// position of the surface in world space, in the frame of the surface
+vec3 surface(vec2 pos) {
+    vec3 world = orient(graphToWorld(func(pos)));
+    world.z *= - amplitude;
+    return toLocal(world);
+}
+
+// the surface is built with its inputs along x & y & its value along z, the
+// axis uniforms are given in the same order. These swizzle from that frame to
+// the graph's & back, so surfaces like x = f(y, z) are drawn sideways
+vec3 orient(vec3 local) {
+    return local.$ORIENT$;
+}
+
+vec3 toLocal(vec3 v) {
+    return v.$TO_LOCAL$;
+}
+
+// partial derivatives of func's z with respect to x & y
+vec2 gradient(vec2 pos) {
+    float x = pos.x;
+    float y = pos.y;
+    vec2 grad;
+
+    // lerping between graphs lerps between their derivatives
+    if(oldToNew < 1.) {
+        grad = vec2(
+            lerp($OLD_DX$, $CURRENT_DX$, oldToNew),
+            lerp($OLD_DY$, $CURRENT_DY$, oldToNew)
+        );
+    } else {
+        grad = vec2($CURRENT_DX$, $CURRENT_DY$);
+    }
+
+    return grad;
+}
+
+// stretches the plane over the domain, evenly spaced along the axes
+vec2 planeToGraph(vec2 plane) {
+    vec2 coord = mix(domain.xz, domain.yw, plane + .5);
+    return vec2(fromAxis(coord.x, axisLog.x), fromAxis(coord.y, axisLog.y));
+}
+
+// whether the surface jumps or passes through a pole that changes sign
+// between a & b, see mesh/breaks.rs for how it works
+bool spansBreak(vec2 a, vec2 b) {
+    float za = func(a).z;
+    float zb = func(b).z;
+    float first = abs(zb - za);
+
+    // points on a pole can't be connected to anything
+    if(abs(za) > 1e30 || abs(zb) > 1e30) {
+        return true;
+    }
+
+    // undefined (NaN) points are left to be handled separately
+    if(first != first || first <= 1e-4 * axisScale.z) {
+        return false;
+    }
+
+    for(int i = 0; i < BREAK_STEPS; i++) {
+        vec2 m = (a + b) / 2.;
+        float zm = func(m).z;
+
+        if(zm != zm) {
+            return false;
+        }
+
+        if(abs(zm - za) > abs(zb - zm)) {
+            b = m;
+            zb = zm;
+        } else {
+            a = m;
+            za = zm;
+        }
+    }
+
+    float ratio = abs(zb - za) / first;
+    bool flips = sign(za) != sign(zb);
+
+    return ratio > .5 && (flips || ratio < 2.);
+}
+
+bool isUndefined(vec3 v) {
+    return any(notEqual(v, v));
+}
+
+bool isFinite(vec3 v) {
+    return !isUndefined(v) && all(lessThan(abs(v), vec3(1e30)));
+}
+
+// moves graph (outside of the graph) onto the edge of the graph towards its
+// neighbours inside of it. A single edge is halved, towards the middle of
+// those neighbours or the first of them when the middle is outside too, so
+// at most 15 points are sampled. Halving mirrors find_edge in mesh/breaks.rs
+bool snapToEdge(vec2 plane, inout vec2 graph) {
+    vec2 dx = vec2(breakSpacing.x, 0.);
+    vec2 dy = vec2(0., breakSpacing.y);
+    vec2 dirs[6];
+    dirs[0] = dx;
+    dirs[1] = -dx;
+    dirs[2] = dy;
+    dirs[3] = -dy;
+    dirs[4] = dx - dy;
+    dirs[5] = dy - dx;
+
+    int count = 0;
+    vec2 first = graph;
+    vec2 sum = vec2(0.);
+
+    for(int i = 0; i < 6; i++) {
+        vec2 neighbour = planeToGraph(plane + dirs[i]);
+        if(!isFinite(surface(neighbour))) {
+            continue;
+        }
+
+        if(count == 0) {
+            first = neighbour;
+        }
+        sum += neighbour;
+        count++;
+    }
+
+    if(count == 0) {
+        return false;
+    }
+
+    vec2 inside = sum / float(count);
+    if(!isFinite(surface(inside))) {
+        inside = first;
+    }
+
+    // halves the edge keeping one end inside & one outside the graph
+    vec2 outside = graph;
+    for(int j = 0; j < EDGE_STEPS; j++) {
+        vec2 m = (outside + inside) / 2.;
+
+        if(isFinite(surface(m))) {
+            inside = m;
+        } else {
+            outside = m;
+        }
+    }
+
+    graph = inside;
+    return true;
+}
@@ -0,0 +1,41 @@
+/*
+ * Fragment shader for vector fields, lit like equation.frag but colored by
+ * the strength of the field
+ */
+
+#if GL_ES
+precision highp float;
+#endif
+
+uniform vec4 graphColor;
+uniform vec3 globalLightPosition;
+
+// Lighting Constants
+#define MIN_LIGHT 0.1
+#define MAX_LIGHT 1.05
+
+// Vertex position in transformed space (without perspective applied)
+varying vec3 v_Vertex;
+// Vertex Normal
+varying vec3 v_Normal;
+// Color from the strength of the field
+varying vec3 v_Color;
+
+// mathematical sigmoid function
+float sigmoid(float x) {
+  float denom = 1. + exp(-x);
+  return 1. / denom;
+}
+
+// clamps sigmoid between min & max
+float sigmoidClamp(float x, float minimum, float maximum) {
+  return minimum + sigmoid(x) * (maximum - minimum);
+}
+
+void main() {
+  vec3 toLight = normalize(globalLightPosition - v_Vertex);
+  float cosAngle = dot(normalize(v_Normal), toLight);
+  cosAngle = sigmoidClamp(cosAngle, MIN_LIGHT, MAX_LIGHT);
+
+  gl_FragColor = vec4(v_Color * cosAngle, graphColor.a);
+}
@@ -0,0 +1,128 @@
+#if GL_ES
+precision highp float;
+#endif
+
+/*
+ * Vertex shader for vector fields F(x, y, z), drawn with instancing. Each
+ * instance places a glyph (an arrow or a piece of tube) along a vector, the
+ * field is evaluated on the CPU for the lattice of arrows & the streamlines
+ * (see math/field.rs). Instances are colored by the strength of the field
+ */
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+// Color for entire graph (constant)
+uniform vec4 graphColor;
+
+$AXES$
+
+// Length in world space of the arrows where the field is strongest
+uniform float arrowLength;
+
+// Radius of the streamlines in world space
+uniform float tubeRadius;
+
+// Set to 1 when drawing the pieces of the streamlines instead of arrows, which
+// stretch a tube from their base to the end of their vector
+uniform float isStreamline;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+// glyph pointing along z from 0 to 1, with its normal
+attribute vec3 vertexPosition;
+attribute vec3 vertexNormal;
+
+// per instance, in graph space & the strength of the field (0 to 1)
+attribute vec3 instanceBase;
+attribute vec3 instanceVector;
+attribute float instanceStrength;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Vertex position in transformed space (without perspective applied)
+varying vec3 v_Vertex;
+// Vertex Normal
+varying vec3 v_Normal;
+// Color from the strength of the field
+varying vec3 v_Color;
+
+// ----------------------------------------------------------------------------
+// Code
+// ----------------------------------------------------------------------------
+
+vec3 toWorld(vec3 graph);
+vec3 colormap(float strength);
+
+void main() {
+    vec3 base = toWorld(instanceBase);
+    vec3 along;
+    float width;
+
+    if(isStreamline > .5) {
+        along = toWorld(instanceBase + instanceVector) - base;
+        width = tubeRadius;
+    } else {
+        // the direction is carried into world space through each axis'
+        // mapping, arrows keep their proportions as they scale
+        vec3 slope = worldSlope(instanceBase) * vec3(1., 1., -amplitude);
+        vec3 direction = instanceVector * slope;
+        along = length(direction) > 0. ? normalize(direction) : vec3(0.);
+        along *= arrowLength * instanceStrength;
+        width = length(along);
+    }
+
+    // frame around the glyph, with z along the vector
+    vec3 forward = length(along) > 0. ? normalize(along) : vec3(0., 0., 1.);
+    vec3 up = abs(forward.z) < .9 ? vec3(0., 0., 1.) : vec3(1., 0., 0.);
+    vec3 side = normalize(cross(forward, up));
+    vec3 other = cross(forward, side);
+
+    vec3 world = base
+        + (side * vertexPosition.x + other * vertexPosition.y) * width
+        + along * vertexPosition.z;
+    vec3 normal = side * vertexNormal.x + other * vertexNormal.y + forward * vertexNormal.z;
+
+    vec4 pos4 = vec4(world, 1.);
+
+    v_Vertex = vec3(mView * mWorld * pos4);
+    v_Normal = normal;
+    v_Color = colormap(instanceStrength);
+
+    gl_Position = mProj * mView * mWorld * pos4;
+}
+
+// graph space -> world space, where z is flipped
+vec3 toWorld(vec3 graph) {
+    vec3 world = graphToWorld(graph);
+    world.z *= - amplitude;
+    return world;
+}
+
+// weak parts of the field are blue, going through green to red where it is
+// strongest
+vec3 colormap(float strength) {
+    vec3 weak = vec3(.25, .45, .95);
+    vec3 middle = vec3(.3, .85, .45);
+    vec3 strong = vec3(.95, .3, .25);
+    float s = clamp(strength, 0., 1.);
+
+    if(s < .5) {
+        return mix(weak, middle, s * 2.);
+    }
+
+    return mix(middle, strong, s * 2. - 1.);
+}
@@ -7,16 +7,22 @@ precision mediump float;
 varying vec3 color;
 varying vec2 UV;
 
-uniform float frequency;
+$AXES$
+
+// spacing between lines in axis units, grows by powers of 10 so very large
+// ranges don't turn into a solid color
+float lineSpacing(float scale) {
+    return max(1., pow(10., floor(log(scale / 10.) / LN10)));
+}
 
 void main() {
     float fuzz = .5;
-    float minorLineFreq = 1. / (frequency + 1.);
+    vec2 spacing = vec2(lineSpacing(axisScale.x), lineSpacing(axisScale.y));
 
-    float xd = mod(UV.x, minorLineFreq) * 88.1;
-    float yd = mod(UV.y, minorLineFreq) * 88.1;
+    // distance to the nearest line as a portion of the grid
+    vec2 d = mod(UV, spacing) / axisScale.xy * 88.1;
 
-    if(xd < fuzz || yd < fuzz) {
+    if(d.x < fuzz || d.y < fuzz) {
         gl_FragColor = COLOR;
     } else {
         gl_FragColor = vec4(0.0);
@@ -10,14 +10,19 @@ uniform mat4 mModel;
 uniform mat4 mView;
 uniform mat4 mProj;
 
+$AXES$
+
 varying vec3 color;
 varying vec2 UV;
 
 void main() {
     color = vertexColor;
 
-    vec4 pos4 = vec4(vertexPosition, 1.);
+    // the grid covers the x & y axes, stretched the same as the graphs
+    vec4 pos4 = vec4(vertexPosition * vec3(axisStretch.xy, 1.), 1.);
     gl_Position = mProj * mView * mModel * pos4; // applies projection
-    UV = vertexPosition.xy;
+
+    // axis coordinates, lines are drawn at whole numbers (decades on log axes)
+    UV = axisOffset.xy + vertexPosition.xy * axisScale.xy;
     //gl_Position = vec4(vertexPosition.x, vertexPosition.y, 0., 0.);
 }
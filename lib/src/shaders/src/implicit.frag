@@ -0,0 +1,201 @@
+#version 300 es
+
+/*
+ * Fragment shader for implicit surfaces F(x, y, z) = 0. Rays from the camera
+ * are marched through the bounding box until F changes sign, the crossing is
+ * then refined & shaded like any other graph. GLSL 3 is needed to write the
+ * depth of the crossing, so implicit surfaces overlap other graphs correctly
+ */
+
+precision highp float;
+
+// ----------------------------------------------------------------------------
+// Graph Constants
+// ----------------------------------------------------------------------------
+
+// Samples taken along each ray, thin features between samples are missed
+#define MARCH_STEPS 160
+
+// How many times the step containing a crossing is halved
+#define REFINE_STEPS 10
+
+// Size of the step used to sample normals, relative to the ray's length
+// through the box
+#define EPSILON 0.0005
+
+// Lighting Constants
+#define MIN_LIGHT 0.1
+#define MAX_LIGHT 1.05
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+// Color for entire graph (constant)
+uniform vec4 graphColor;
+uniform vec3 globalLightPosition;
+
+// Fun variables for user to play around with
+uniform float TIME;
+
+uniform float oldToNew;
+
+// Corners of the box the surface is drawn in, in world space
+uniform vec3 boxMin;
+uniform vec3 boxMax;
+
+$AXES$
+
+// ----------------------------------------------------------------------------
+// Inputs & Outputs
+// ----------------------------------------------------------------------------
+
+// Position on the box in world space (before mWorld is applied)
+in vec3 v_Model;
+// Camera position in the same space as v_Model
+in vec3 v_Camera;
+
+out vec4 fragColor;
+
+// ----------------------------------------------------------------------------
+// Code
+// ----------------------------------------------------------------------------
+
+float field(vec3 world);
+
+// mathematical sigmoid function
+float sigmoid(float x) {
+    float denom = 1. + exp(-x);
+    return 1. / denom;
+}
+
+// clamps sigmoid between min & max
+float sigmoidClamp(float x, float minimum, float maximum) {
+    return minimum + sigmoid(x) * (maximum - minimum);
+}
+
+void main() {
+    vec3 dir = normalize(v_Model - v_Camera);
+
+    // distances along the ray where it enters & leaves the box
+    vec3 t0 = (boxMin - v_Camera) / dir;
+    vec3 t1 = (boxMax - v_Camera) / dir;
+    vec3 tMin = min(t0, t1);
+    vec3 tMax = max(t0, t1);
+    float near = max(max(tMin.x, tMin.y), tMin.z);
+    float far = min(min(tMax.x, tMax.y), tMax.z);
+
+    // every pixel is covered by a front & back face of the box, only the face
+    // the ray enters through marches unless the camera is inside of the box
+    float t = dot(v_Model - v_Camera, dir);
+    if(near > 0. && abs(t - near) > abs(t - far)) {
+        discard;
+    }
+    near = max(near, 0.);
+
+    float stride = (far - near) / float(MARCH_STEPS);
+    float a = near;
+    float fa = field(v_Camera + dir * a);
+    float b = a;
+    bool hit = false;
+
+    for(int i = 1; i <= MARCH_STEPS; i++) {
+        b = near + stride * float(i);
+        float fb = field(v_Camera + dir * b);
+
+        // undefined samples (NaN) never count as a crossing
+        if(fa == fa && fb == fb && (fa < 0.) != (fb < 0.)) {
+            hit = true;
+            break;
+        }
+
+        a = b;
+        fa = fb;
+    }
+
+    if(!hit) {
+        discard;
+    }
+
+    for(int i = 0; i < REFINE_STEPS; i++) {
+        float m = (a + b) / 2.;
+        float fm = field(v_Camera + dir * m);
+
+        if((fm < 0.) == (fa < 0.)) {
+            a = m;
+            fa = fm;
+        } else {
+            b = m;
+        }
+    }
+
+    vec3 pos = v_Camera + dir * (a + b) / 2.;
+
+    // normal from the gradient of the field, facing the camera
+    float h = (far - near) * EPSILON;
+    vec3 grad = vec3(
+        field(pos + vec3(h, 0., 0.)) - field(pos - vec3(h, 0., 0.)),
+        field(pos + vec3(0., h, 0.)) - field(pos - vec3(0., h, 0.)),
+        field(pos + vec3(0., 0., h)) - field(pos - vec3(0., 0., h))
+    );
+    vec3 normal = dot(grad, dir) > 0. ? -grad : grad;
+    if(!(length(normal) > 0.)) {
+        normal = -dir;
+    }
+
+    vec4 view = mView * mWorld * vec4(pos, 1.);
+    vec4 clip = mProj * view;
+    gl_FragDepth = clip.z / clip.w * .5 + .5;
+
+    // same lighting as equation.frag
+    vec3 toLight = normalize(globalLightPosition - view.xyz);
+    float cosAngle = dot(normalize(normal), toLight);
+    cosAngle = sigmoidClamp(cosAngle, MIN_LIGHT, MAX_LIGHT);
+
+    fragColor = vec4(vec3(graphColor) * cosAngle, graphColor.a);
+}
+
+// Predefined functions, round is written as floor(a + 0.5) by the parser
+float hypot(float x, float y) {
+    return length(vec2(x, y));
+}
+
+float lerp(float from, float to, float travel) {
+    return from + (to - from) * travel;
+}
+
+float time() {
+    return TIME;
+}
+
+// used to trick glsl optimizer
+$EXTERN_FUNCTIONS$
+
+float func(vec3 pos) {
+    float x = pos.x;
+    float y = pos.y;
+    float z = pos.z;
+
+#define t TIME
+
+    // template $$ replaced in rust
+    if(oldToNew < 1.) {
+        return lerp($OLD_FUNCTION$, $CURRENT_FUNCTION$, oldToNew);
+    }
+
+    return $CURRENT_FUNCTION$;
+}
+
+// F at a point in world space, z is stretched by -amplitude like explicit
+// surfaces are
+float field(vec3 world) {
+    return func(worldToGraph(vec3(world.xy, world.z / -amplitude)));
+}
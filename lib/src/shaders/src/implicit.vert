@@ -0,0 +1,44 @@
+#version 300 es
+
+/*
+ * Vertex shader for implicit surfaces F(x, y, z) = 0, draws the box the
+ * surface is bounded by. The surface itself is found by implicit.frag
+ */
+
+precision highp float;
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Corners of the box the surface is drawn in, in world space
+uniform vec3 boxMin;
+uniform vec3 boxMax;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+// Corner of the unit cube
+layout(location = 0) in vec3 vertexPosition;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Position on the box in world space (before mWorld is applied)
+out vec3 v_Model;
+// Camera position in the same space as v_Model
+out vec3 v_Camera;
+
+void main() {
+    v_Model = mix(boxMin, boxMax, vertexPosition);
+    v_Camera = vec3(inverse(mView * mWorld) * vec4(0., 0., 0., 1.));
+
+    gl_Position = mProj * mView * mWorld * vec4(v_Model, 1.);
+}
@@ -0,0 +1,205 @@
+#version 300 es
+
+precision highp float;
+
+/*
+ * Vertex shader for parametric surfaces (x, y, z) = p(u, v), the plane is
+ * stretched over the ranges of u & v then bent into the surface. Shares
+ * equation.frag with explicit surfaces
+ */
+
+// ----------------------------------------------------------------------------
+// Math Constants
+// ----------------------------------------------------------------------------
+
+#define PI 3.1415926538
+#define TAU 6.283185307179586
+
+// ----------------------------------------------------------------------------
+// Graph Constants
+// ----------------------------------------------------------------------------
+
+// Determines normal sampling detail, as a portion of the ranges of u & v
+#define EPSILON 0.001
+
+// Set to 1 when the partial derivatives of the equation were derived
+// symbolically, normals are then calculated from them instead of sampling
+// with EPSILON
+#define ANALYTIC_NORMALS $ANALYTIC_NORMALS$
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+// Color for entire graph (constant)
+uniform vec4 graphColor;
+
+// Fun variables for user to play around with
+uniform float TIME;
+
+uniform float oldToNew;
+
+$AXES$
+
+// Ranges of the 2 inputs the plane is stretched over
+// (u min, u max, v min, v max)
+uniform vec4 domain;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+layout(location = 0) in vec3 vertexPosition;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Vertex position in transformed space (without perspective applied)
+out vec3 v_Vertex;
+// Symbolic Vertex position on graph
+out vec3 v_GraphVertex;
+// Vertex Normal
+out vec3 v_Normal;
+// Parametric surfaces have no breaks, always 0 (see equation.frag)
+flat out vec3 v_Break;
+out float v_PlaneX;
+// Below 1 when the vertex is undefined (NaN or infinite), triangles touching
+// it are discarded
+out float v_Valid;
+
+// ----------------------------------------------------------------------------
+// Code
+// ----------------------------------------------------------------------------
+
+vec3 func(vec2 param);
+vec3 surface(vec2 param);
+vec3 normal(vec2 param);
+bool isFinite(vec3 v);
+
+void main() {
+    vec2 param = mix(domain.xz, domain.yw, vertexPosition.xy + .5);
+    vec3 graphPos = func(param);
+    vec3 world = surface(param);
+
+    v_Break = vec3(0.);
+    v_PlaneX = 0.;
+    v_Valid = 1.;
+
+    // keeps NaN out of the driver, the vertex is discarded anyways
+    if(!isFinite(world)) {
+        v_Valid = 0.;
+        graphPos = vec3(0.);
+        world = vec3(0.);
+    }
+
+    vec4 pos4 = vec4(world, 1.);
+
+    // calculate fragment color
+    v_Vertex = vec3(mView * mWorld * pos4); // transformed matrix
+    v_Normal = normal(param);
+    v_GraphVertex = vec3(graphPos.xy, pos4.z);
+
+    gl_Position = mProj * mView * mWorld * pos4; // applies projection
+}
+
+// Predefined functions, round is written as floor(a + 0.5) by the parser
+float hypot(float x, float y) {
+    return length(vec2(x, y));
+}
+
+float lerp(float from, float to, float travel) {
+    return from + (to - from) * travel;
+}
+
+float time() {
+    return TIME;
+}
+
+// used to trick glsl optimizer
+$EXTERN_FUNCTIONS$
+
+// point on the surface in graph space
+vec3 func(vec2 param) {
+    $PARAMS$
+
+    // template $$ replaced in rust
+    if(oldToNew < 1.) {
+        return mix($OLD_FUNCTION$, $CURRENT_FUNCTION$, oldToNew);
+    }
+
+    return $CURRENT_FUNCTION$;
+}
+
+// position of the surface in world space
+vec3 surface(vec2 param) {
+    vec3 world = graphToWorld(func(param));
+    world.z *= - amplitude;
+    return world;
+}
+
+// partial derivatives of func with respect to u & v, in graph space
+vec3 derivativeU(vec2 param) {
+    $PARAMS$
+
+    if(oldToNew < 1.) {
+        return mix($OLD_DU$, $CURRENT_DU$, oldToNew);
+    }
+
+    return $CURRENT_DU$;
+}
+
+vec3 derivativeV(vec2 param) {
+    $PARAMS$
+
+    if(oldToNew < 1.) {
+        return mix($OLD_DV$, $CURRENT_DV$, oldToNew);
+    }
+
+    return $CURRENT_DV$;
+}
+
+bool isFinite(vec3 v) {
+    return !any(notEqual(v, v)) && all(lessThan(abs(v), vec3(1e30)));
+}
+
+// normal in world space from the partial derivatives, 0 where they are
+// parallel
+vec3 tangentNormal(vec2 param) {
+#if ANALYTIC_NORMALS
+    // derivatives are carried into world space through each axis' mapping
+    vec3 scale = worldSlope(func(param)) * vec3(1., 1., -amplitude);
+    vec3 du = derivativeU(param) * scale;
+    vec3 dv = derivativeV(param) * scale;
+#else
+    vec2 delta = (domain.yw - domain.xz) * EPSILON;
+    vec3 du = surface(param + vec2(delta.x, 0.)) - surface(param - vec2(delta.x, 0.));
+    vec3 dv = surface(param + vec2(0., delta.y)) - surface(param - vec2(0., delta.y));
+#endif
+
+    vec3 n = -cross(du, dv);
+    return length(n) > EPSILON * EPSILON * (dot(du, du) + dot(dv, dv)) ? n : vec3(0.);
+}
+
+// normal in world space, wound the same way as explicit surfaces
+vec3 normal(vec2 param) {
+    vec3 n = tangentNormal(param);
+
+    // the surface doesn't move along one of the inputs at a pole, so the
+    // normal is taken from just inside of the ranges instead
+    if(!(isFinite(n) && length(n) > 0.)) {
+        vec2 inset = (domain.yw - domain.xz) * EPSILON;
+        vec2 center = (domain.xz + domain.yw) * .5;
+        n = tangentNormal(param + sign(center - param) * inset);
+    }
+
+    return isFinite(n) && length(n) > 0. ? n : vec3(0., 0., -1.);
+}
@@ -0,0 +1,56 @@
+#if GL_ES
+precision highp float;
+#endif
+
+/*
+ * Vertex shader for planes, the polygon where the plane cuts through the box
+ * it is drawn in is built on the CPU in graph space (see render/planes.rs)
+ */
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+$AXES$
+
+// Normal of the plane in graph space
+uniform vec3 planeNormal;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+// corner of the polygon in graph space
+attribute vec3 vertexPosition;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Vertex position in transformed space (without perspective applied)
+varying vec3 v_Vertex;
+// Vertex Normal
+varying vec3 v_Normal;
+
+void main() {
+    vec3 world = graphToWorld(vertexPosition);
+    world.z *= - amplitude;
+    vec4 pos4 = vec4(world, 1.);
+
+    // normals are carried into world space by the inverse of each axis'
+    // scaling
+    vec3 slope = worldSlope(vertexPosition) * vec3(1., 1., -amplitude);
+
+    v_Vertex = vec3(mView * mWorld * pos4);
+    v_Normal = planeNormal / slope;
+
+    gl_Position = mProj * mView * mWorld * pos4;
+}
@@ -0,0 +1,28 @@
+/*
+ * Fragment shader for points, shaded like a ball facing the camera
+ */
+
+#if GL_ES
+precision highp float;
+#endif
+
+uniform vec4 graphColor;
+
+// Lighting Constants
+#define MIN_LIGHT 0.35
+
+// Position on the disc, its edge is 1 away from the center
+varying vec2 v_Corner;
+
+void main() {
+  float r2 = dot(v_Corner, v_Corner);
+  if(r2 > 1.) {
+    discard;
+  }
+
+  // normal of the ball in view space, lit from the top left
+  vec3 normal = vec3(v_Corner, sqrt(1. - r2));
+  float light = max(dot(normal, normalize(vec3(-.4, .5, 1.))), MIN_LIGHT);
+
+  gl_FragColor = vec4(vec3(graphColor) * light, graphColor.a);
+}
@@ -0,0 +1,54 @@
+#if GL_ES
+precision highp float;
+#endif
+
+/*
+ * Vertex shader for points, a square is turned to face the camera around the
+ * point & point.frag cuts a shaded disc out of it
+ */
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+$AXES$
+
+// Position of the point in graph space
+uniform vec3 center;
+
+// Radius of the disc in world space
+uniform float pointRadius;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+// corner of the square, from -0.5 to 0.5 along x & y
+attribute vec3 vertexPosition;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Position on the disc, its edge is 1 away from the center
+varying vec2 v_Corner;
+
+void main() {
+    vec3 world = graphToWorld(center);
+    world.z *= - amplitude;
+
+    // the square is spread out in view space, so it always faces the camera
+    vec4 view = mView * mWorld * vec4(world, 1.);
+    view.xy += vertexPosition.xy * 2. * pointRadius;
+
+    v_Corner = vertexPosition.xy * 2.;
+    gl_Position = mProj * view;
+}
@@ -0,0 +1,40 @@
+/*
+ * Fragment shader for vectors, lines & planes, lit like equation.frag
+ */
+
+#if GL_ES
+precision highp float;
+#endif
+
+uniform vec4 graphColor;
+uniform vec3 globalLightPosition;
+
+// Lighting Constants
+#define MIN_LIGHT 0.1
+#define MAX_LIGHT 1.05
+
+// Vertex position in transformed space (without perspective applied)
+varying vec3 v_Vertex;
+// Vertex Normal
+varying vec3 v_Normal;
+
+// mathematical sigmoid function
+float sigmoid(float x) {
+  float denom = 1. + exp(-x);
+  return 1. / denom;
+}
+
+// clamps sigmoid between min & max
+float sigmoidClamp(float x, float minimum, float maximum) {
+  return minimum + sigmoid(x) * (maximum - minimum);
+}
+
+void main() {
+  vec3 toLight = normalize(globalLightPosition - v_Vertex);
+
+  // planes are lit the same from either side
+  float cosAngle = abs(dot(normalize(v_Normal), toLight));
+  cosAngle = sigmoidClamp(cosAngle, MIN_LIGHT, MAX_LIGHT);
+
+  gl_FragColor = vec4(vec3(graphColor) * cosAngle, graphColor.a);
+}
@@ -0,0 +1,332 @@
+#version 300 es
+
+precision highp float;
+
+/*
+ * Vertex shader for regions lower <= z <= upper, the solid is drawn in 3
+ * passes over planes. The lower & upper faces are planes displaced by their
+ * bound & clipped to where the region isn't empty, the walls are a plane
+ * wrapped around the edge of the domain with x going around & y from the
+ * lower to the upper bound. Shares equation.frag with surfaces
+ */
+
+// ----------------------------------------------------------------------------
+// Graph Constants
+// ----------------------------------------------------------------------------
+
+// Determines normal sampling detail
+#define EPSILON 0.001
+
+// How many times an edge is halved while looking for the edge of the region,
+// matches EDGE_STEPS in mesh/breaks.rs
+#define EDGE_STEPS 8
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+// Color for entire graph (constant)
+uniform vec4 graphColor;
+
+// Fun variables for user to play around with
+uniform float TIME;
+
+uniform float oldToNew;
+
+$AXES$
+
+// Rectangle the region is drawn over in axis coordinates
+// (x min, x max, y min, y max)
+uniform vec4 domain;
+
+// Graph values the bounds are clipped to (min, max)
+uniform vec2 valueRange;
+
+// Distance to the neighbouring vertices on the plane, along x & y
+uniform vec2 breakSpacing;
+
+// Part of the solid being drawn, 0 for the lower face, 1 for the upper face &
+// 2 for the walls
+uniform float face;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+layout(location = 0) in vec3 vertexPosition;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Vertex position in transformed space (without perspective applied)
+out vec3 v_Vertex;
+// Symbolic Vertex position on graph
+out vec3 v_GraphVertex;
+// Vertex Normal
+out vec3 v_Normal;
+// Regions have no breaks, always 0 (see equation.frag)
+flat out vec3 v_Break;
+out float v_PlaneX;
+// Below 1 when the vertex is outside of the region & couldn't be moved onto
+// its edge, triangles touching it are discarded
+out float v_Valid;
+
+// ----------------------------------------------------------------------------
+// Code
+// ----------------------------------------------------------------------------
+
+vec2 func(vec2 pos);
+vec2 bounds(vec2 pos);
+vec3 solid(vec2 pos, float height);
+vec3 orient(vec3 local);
+vec3 direction(vec3 local);
+vec3 faceNormal(vec2 pos);
+vec2 planeToGraph(vec2 plane);
+vec2 perimeter(float around, out vec2 outward);
+bool inside(vec2 pos);
+bool isFinite(vec3 v);
+bool snapToEdge(vec2 plane, inout vec2 graph);
+
+void main() {
+    vec2 plane = vertexPosition.xy;
+    float height = face;
+    vec2 outward = vec2(0.);
+
+    // walls stand on the edge of the domain
+    bool wall = face > 1.5;
+    if(wall) {
+        plane = perimeter(vertexPosition.x, outward);
+        height = vertexPosition.y + .5;
+    }
+
+    vec2 graphVert = planeToGraph(plane);
+
+    // faces are clipped to where the region isn't empty, vertices outside of
+    // it are moved onto its edge where the faces meet
+    v_Valid = 1.;
+    if(!wall && !inside(graphVert) && !snapToEdge(plane, graphVert)) {
+        v_Valid = 0.;
+    }
+
+    vec2 range = bounds(graphVert);
+    vec3 world = solid(graphVert, height);
+    vec3 normal = wall ? direction(vec3(outward, 0.)) : faceNormal(graphVert);
+
+    // keeps NaN out of the driver, the vertex is discarded anyways
+    if(any(notEqual(range, range)) || !isFinite(world)) {
+        v_Valid = 0.;
+        world = vec3(plane, 0.);
+        normal = vec3(0., 0., -1.);
+    }
+
+    vec4 pos4 = vec4(world, 1.);
+
+    // calculate fragment color
+    v_Vertex = vec3(mView * mWorld * pos4); // transformed matrix
+    v_Normal = normal;
+    v_GraphVertex = vec3(graphVert, pos4.z);
+    v_Break = vec3(0.);
+    v_PlaneX = 0.;
+
+    gl_Position = mProj * mView * mWorld * pos4; // applies projection
+}
+
+// Predefined functions, round is written as floor(a + 0.5) by the parser
+float hypot(float x, float y) {
+    return length(vec2(x, y));
+}
+
+float lerp(float from, float to, float travel) {
+    return from + (to - from) * travel;
+}
+
+float time() {
+    return TIME;
+}
+
+// used to trick glsl optimizer
+$EXTERN_FUNCTIONS$
+
+// lower & upper bound at pos
+vec2 func(vec2 pos) {
+    float x = pos.x;
+    float y = pos.y;
+
+#define t TIME
+
+    // template $$ replaced in rust
+    if(oldToNew < 1.) {
+        return mix($OLD_FUNCTION$, $CURRENT_FUNCTION$, oldToNew);
+    }
+
+    return $CURRENT_FUNCTION$;
+}
+
+// bounds clipped to the value range, clamp is undefined for NaN so undefined
+// bounds are returned as they are
+vec2 bounds(vec2 pos) {
+    vec2 range = func(pos);
+    if(any(notEqual(range, range))) {
+        return range;
+    }
+
+    return clamp(range, valueRange.x, valueRange.y);
+}
+
+// whether the region isn't empty at pos
+bool inside(vec2 pos) {
+    vec2 range = bounds(pos);
+    return range.x <= range.y;
+}
+
+// position in world space of the point height (0 to 1) of the way from the
+// lower to the upper bound at pos, the walls shrink away where the region is
+// empty
+vec3 solid(vec2 pos, float height) {
+    vec2 range = bounds(pos);
+    float value = range.x + height * max(range.y - range.x, 0.);
+
+    vec3 world = orient(graphToWorld(vec3(pos, value)));
+    world.z *= - amplitude;
+    return world;
+}
+
+// the region is built with its inputs along x & y & the bounded axis along z,
+// the axis uniforms are given in the same order. This swizzles from that frame
+// to the graph's
+vec3 orient(vec3 local) {
+    return local.$ORIENT$;
+}
+
+// direction in the frame of the region -> world space, where z is flipped
+vec3 direction(vec3 local) {
+    vec3 world = orient(local);
+    world.z = -world.z;
+    return world;
+}
+
+// difference to the point step away on the face, taken on whichever side of
+// the point is inside the region
+vec3 sideStep(vec2 pos, vec3 center, vec2 step) {
+    if(inside(pos + step)) {
+        return solid(pos + step, face) - center;
+    }
+
+    if(inside(pos - step)) {
+        return center - solid(pos - step, face);
+    }
+
+    // flat along the step when the region is too thin to sample
+    return direction(vec3(step, 0.));
+}
+
+// normal of the face at pos, the upper face points along the bounded axis &
+// the lower face against it so both point out of the solid
+vec3 faceNormal(vec2 pos) {
+    vec3 center = solid(pos, face);
+    vec3 a = sideStep(pos, center, vec2(EPSILON, 0.));
+    vec3 b = sideStep(pos, center, vec2(0., EPSILON));
+
+    vec3 up = direction(vec3(0., 0., 1.)) * (face > .5 ? 1. : -1.);
+    vec3 n = cross(a, b);
+    if(!isFinite(n) || length(n) == 0.) {
+        return up;
+    }
+
+    return dot(n, up) < 0. ? -n : n;
+}
+
+// stretches the plane over the domain, evenly spaced along the axes
+vec2 planeToGraph(vec2 plane) {
+    vec2 coord = mix(domain.xz, domain.yw, plane + .5);
+    return vec2(fromAxis(coord.x, axisLog.x), fromAxis(coord.y, axisLog.y));
+}
+
+// point on the edge of the plane, around goes from -0.5 to 0.5 counter
+// clockwise starting at the corner (-0.5, -0.5). outward is the direction the
+// edge faces
+vec2 perimeter(float around, out vec2 outward) {
+    float s = (around + .5) * 4.;
+    float edge = min(floor(s), 3.);
+    float along = s - edge - .5;
+
+    if(edge < .5) {
+        outward = vec2(0., -1.);
+        return vec2(along, -.5);
+    } else if(edge < 1.5) {
+        outward = vec2(1., 0.);
+        return vec2(.5, along);
+    } else if(edge < 2.5) {
+        outward = vec2(0., 1.);
+        return vec2(-along, .5);
+    }
+
+    outward = vec2(-1., 0.);
+    return vec2(-.5, -along);
+}
+
+bool isFinite(vec3 v) {
+    return !any(notEqual(v, v)) && all(lessThan(abs(v), vec3(1e30)));
+}
+
+// moves graph (outside of the region) to the closest point on the edge of the
+// region towards any neighbour inside of it. Mirrors find_edge in
+// mesh/breaks.rs
+bool snapToEdge(vec2 plane, inout vec2 graph) {
+    vec2 dx = vec2(breakSpacing.x, 0.);
+    vec2 dy = vec2(0., breakSpacing.y);
+    vec2 dirs[6];
+    dirs[0] = dx;
+    dirs[1] = -dx;
+    dirs[2] = dy;
+    dirs[3] = -dy;
+    dirs[4] = dx - dy;
+    dirs[5] = dy - dx;
+
+    bool found = false;
+    float closest = 2.;
+    vec2 snapped = graph;
+
+    for(int i = 0; i < 6; i++) {
+        vec2 inner = planeToGraph(plane + dirs[i]);
+        if(!inside(inner)) {
+            continue;
+        }
+
+        // halves the edge keeping one end inside & one outside the region,
+        // travel is how far along the edge the inside end is
+        vec2 outer = graph;
+        float lo = 0.;
+        float hi = 1.;
+        for(int j = 0; j < EDGE_STEPS; j++) {
+            vec2 m = (outer + inner) / 2.;
+            float mid = (lo + hi) / 2.;
+
+            if(inside(m)) {
+                inner = m;
+                hi = mid;
+            } else {
+                outer = m;
+                lo = mid;
+            }
+        }
+
+        if(hi < closest) {
+            closest = hi;
+            snapped = inner;
+            found = true;
+        }
+    }
+
+    graph = snapped;
+    return found;
+}
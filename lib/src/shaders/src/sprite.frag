@@ -0,0 +1,39 @@
+/*
+ * Fragment shader for measured data, shaded like point.frag with the picked
+ * point lightened & outlined
+ */
+
+#if GL_ES
+precision highp float;
+#endif
+
+uniform vec4 graphColor;
+
+// Lighting Constants
+#define MIN_LIGHT 0.35
+
+// Portion of the disc's radius the outline of the picked point covers
+#define OUTLINE 0.2
+
+// Position on the disc, its edge is 1 away from the center
+varying vec2 v_Corner;
+// 1 for the picked point, 0 otherwise
+varying float v_Picked;
+
+void main() {
+  float r2 = dot(v_Corner, v_Corner);
+  if(r2 > 1.) {
+    discard;
+  }
+
+  // normal of the ball in view space, lit from the top left
+  vec3 normal = vec3(v_Corner, sqrt(1. - r2));
+  float light = max(dot(normal, normalize(vec3(-.4, .5, 1.))), MIN_LIGHT);
+  vec3 color = vec3(graphColor) * light;
+
+  if(v_Picked > .5) {
+    color = sqrt(r2) > 1. - OUTLINE ? vec3(1.) : mix(color, vec3(1.), .35);
+  }
+
+  gl_FragColor = vec4(color, graphColor.a);
+}
@@ -0,0 +1,68 @@
+#if GL_ES
+precision highp float;
+#endif
+
+/*
+ * Vertex shader for measured data, drawn with instancing. Each instance turns
+ * a square to face the camera around a point like point.vert, the picked point
+ * is drawn larger
+ */
+
+// Scale of the picked point's sprite
+#define PICKED_SCALE 1.6
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+$AXES$
+
+// Radius in world space of points with a size of 1
+uniform float pointRadius;
+
+// Index of the picked point, -1 when none of the dataset's points are picked
+uniform float picked;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+// corner of the square, from -0.5 to 0.5 along x & y
+attribute vec3 vertexPosition;
+
+// per instance, the point in graph space, its size & index in the dataset
+attribute vec3 instanceCenter;
+attribute float instanceSize;
+attribute float instanceIndex;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Position on the disc, its edge is 1 away from the center
+varying vec2 v_Corner;
+// 1 for the picked point, 0 otherwise
+varying float v_Picked;
+
+void main() {
+    vec3 world = graphToWorld(instanceCenter);
+    world.z *= - amplitude;
+
+    v_Picked = abs(instanceIndex - picked) < .5 ? 1. : 0.;
+    float radius = pointRadius * instanceSize * mix(1., PICKED_SCALE, v_Picked);
+
+    // the square is spread out in view space, so it always faces the camera
+    vec4 view = mView * mWorld * vec4(world, 1.);
+    view.xy += vertexPosition.xy * 2. * radius;
+
+    v_Corner = vertexPosition.xy * 2.;
+    gl_Position = mProj * view;
+}
@@ -0,0 +1,100 @@
+#if GL_ES
+precision highp float;
+#endif
+
+/*
+ * Vertex shader for vectors & lines, drawn with a single instance of an arrow
+ * or tube (see render/arrows.rs). Unlike the arrows of vector fields the glyph
+ * spans the whole vector & keeps the width of curves, with a head of a fixed
+ * size. Lines are clipped to the box they are drawn in on the CPU
+ */
+
+// Radius & length of the glyph's shaft & the start of its head, matches
+// ShaderGenerator::generate_arrow
+#define SHAFT 0.04
+#define NECK 0.7
+
+// ----------------------------------------------------------------------------
+// Uniforms
+// ----------------------------------------------------------------------------
+
+// Perspective & Trasformation Matrix Uniforms
+uniform mat4 mWorld;
+uniform mat4 mView;
+uniform mat4 mProj;
+
+// Vertical Stretch, lowered when fitting the view around tall graphs
+uniform float amplitude;
+
+$AXES$
+
+// Radius in world space, the same as curves
+uniform float tubeRadius;
+
+// Set to 1 for lines, which are drawn as a tube without a head
+uniform float isLine;
+
+// ----------------------------------------------------------------------------
+// Vertex Attributes
+// ----------------------------------------------------------------------------
+
+// glyph pointing along z from 0 to 1, with its normal
+attribute vec3 vertexPosition;
+attribute vec3 vertexNormal;
+
+// tail & vector in graph space
+attribute vec3 instanceBase;
+attribute vec3 instanceVector;
+
+// ----------------------------------------------------------------------------
+// Varying vars for fragment shader
+// ----------------------------------------------------------------------------
+
+// Vertex position in transformed space (without perspective applied)
+varying vec3 v_Vertex;
+// Vertex Normal
+varying vec3 v_Normal;
+
+vec3 toWorld(vec3 graph);
+
+void main() {
+    vec3 tail = toWorld(instanceBase);
+    vec3 along = toWorld(instanceBase + instanceVector) - tail;
+    float len = length(along);
+
+    // frame around the glyph, with z along the vector
+    vec3 forward = len > 0. ? along / len : vec3(0., 0., 1.);
+    vec3 up = abs(forward.z) < .9 ? vec3(0., 0., 1.) : vec3(1., 0., 0.);
+    vec3 side = normalize(cross(forward, up));
+    vec3 other = cross(forward, side);
+
+    // the head keeps its size while the shaft stretches, short vectors are
+    // half head
+    float width = tubeRadius;
+    float height = len * vertexPosition.z;
+    if(isLine < .5) {
+        width = tubeRadius / SHAFT;
+        float head = min(width * (1. - NECK), len / 2.);
+
+        height = vertexPosition.z < NECK
+            ? vertexPosition.z / NECK * (len - head)
+            : len - (1. - vertexPosition.z) / (1. - NECK) * head;
+    }
+
+    vec3 world = tail
+        + (side * vertexPosition.x + other * vertexPosition.y) * width
+        + forward * height;
+    vec4 pos4 = vec4(world, 1.);
+
+    v_Vertex = vec3(mView * mWorld * pos4);
+    v_Normal = side * vertexNormal.x + other * vertexNormal.y + forward * vertexNormal.z;
+
+    gl_Position = mProj * mView * mWorld * pos4;
+}
+
+// graph space -> world space, where z is flipped
+vec3 toWorld(vec3 graph) {
+    vec3 world = graphToWorld(graph);
+    world.z *= - amplitude;
+    return world;
+}
@@ -2,10 +2,27 @@
  * Testing Module, not for production
  */
 use crate::math::mat4::{Mat4};
+use crate::math::region::Region;
+use crate::eval::{interval::Interval, Scope};
+use crate::math::axes::{Axes, Axis, UPRIGHT};
+use crate::math::data::{DataPoint, Dataset};
+use crate::math::domain::Domain;
+use crate::math::field::{magnitude, Field};
+use crate::math::fit::{drawn_values, fill_params, free_params, Fit};
+use crate::math::framing::{z_range, ViewFrame};
+use crate::math::geometry::{orientation_of, GraphEquation, GraphKind};
+use crate::math::primitives::Primitive;
 use crate::math::vec3::Vec3;
 /// Module to contain unit tests for projectss
 use crate::parser::lexer::Lexer;
 use crate::parser::parser::Parser;
+use crate::parser::{ParseNode, ParserError};
+use crate::mesh::{
+    breaks::{find_edge, may_break, spans_break},
+    quadtree::{RefineOptions, Surface},
+    TriMesh,
+};
+use crate::render::mesh::Mesh;
 use crate::shaders::shader_generator::ShaderGenerator;
 
 #[test]
@@ -79,3 +96,1271 @@ fn planegen() {
     println!("{:?}", indecies);
     println!("Length {:?}", indecies.len());
 }
+
+// parses an equation string into an AST, panicking on failure
+fn parse(src: &str) -> ParseNode {
+    let toks = Lexer::new(src.into()).tokenize().unwrap();
+    Parser::new(toks).parse().unwrap()
+}
+
+#[test]
+fn simplify() {
+    let cases = [
+        ("f(x, y) = (2 * 3) + x^2", "f(x, y) = x*x + 6"),
+        ("f(x, y) = x*1 + 0 + y^1", "f(x, y) = x + y"),
+        ("f(x, y) = y*x*2 - x*y*2", "f(x, y) = 0"),
+        ("f(x, y) = sin(pi / 2) * cos(x)", "f(x, y) = cos(x)"),
+        ("f(x, y) = 0 * sin(x) + 3 - 5", "f(x, y) = -2"),
+        ("f(x, y) = (x + 1)*2", "f(x, y) = 2*(x + 1)"),
+        ("f(x, e) = e * pow(x, 1)", "f(x, e) = e*x"),
+        // 0 only absorbs factors known to be finite
+        ("f(x, y) = 0 * 2", "f(x, y) = 0"),
+        ("f(x, y) = 0 * sqrt(-1 - x^2)", "f(x, y) = 0*sqrt(-(x*x) - 1)"),
+        ("f(x, y) = 0 / 0", "f(x, y) = 0/0"),
+    ];
+
+    for (input, expected) in cases.iter() {
+        let simplified = parse(input).simplify();
+        println!("{} => {}", input, simplified.readable());
+        assert_eq!(simplified.readable(), *expected);
+    }
+
+    // negative literals must not be emitted as a GLSL decrement
+    let glsl = parse("f(x, y) = x - 2 * 3").simplify().to_string();
+    assert!(!glsl.contains("--"));
+
+    // round is written out so .5 rounds up on the GPU too
+    let glsl = parse("f(x, y) = round(x)").to_string();
+    assert!(glsl.contains("floor(x + 0.5)"));
+    assert_eq!(parse("f(x, y) = round(x)").readable(), "f(x, y) = round(x)");
+}
+
+#[test]
+fn unary_minus() {
+    let scope = Scope::new().with("x", 3.).with("y", 2.);
+    let cases = [
+        // binds looser than ^ & tighter than * & /
+        ("f(x, y) = -x^2", -9.),
+        ("f(x, y) = 2^-x", 0.125),
+        ("f(x, y) = -x * y", -6.),
+        ("f(x, y) = x * -y", -6.),
+        ("f(x, y) = x / -y", -1.5),
+        ("f(x, y) = --x", 3.),
+        ("f(x, y) = -(x + y)", -5.),
+    ];
+
+    for (input, expected) in cases.iter() {
+        assert_eq!(parse(input).eval(&scope), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn trailing_tokens() {
+    let parse_err = |src: &str| {
+        let toks = Lexer::new(src.into()).tokenize().unwrap();
+        Parser::new(toks).parse().is_err()
+    };
+
+    // tokens left after the body are errors rather than silently dropped
+    assert!(parse_err("f(x, y) = x y"));
+    assert!(parse_err("f(x, y) = x + y)"));
+    assert!(parse_err("f(x, y) = sin(x) 2"));
+    assert!(!parse_err("f(x, y) = x + y"));
+}
+
+#[test]
+fn derivative() {
+    let cases = [
+        ("f(x, y) = x^2 * y", "x", "f(x, y) = 2*x*y"),
+        ("f(x, y) = sin(x * y)", "y", "f(x, y) = x*cos(x*y)"),
+        ("f(x, y) = exp(2 * x) + y", "x", "f(x, y) = 2*exp(2*x)"),
+        ("f(x, y) = floor(y) + x", "x", "f(x, y) = 1"),
+        ("f(x, y) = x / y", "y", "f(x, y) = -x/(y*y)"),
+    ];
+
+    for (input, var, expected) in cases.iter() {
+        let derived = parse(input).derivative(var).unwrap();
+        println!("d/d{} {} => {}", var, input, derived.readable());
+        assert_eq!(derived.readable(), *expected);
+    }
+
+    // step functions can't be differentiated through
+    assert!(parse("f(x, y) = round(x)").derivative("x").is_err());
+}
+
+#[test]
+fn diff_operator() {
+    let cases = [
+        ("f(x, y) = diff(x^2 * y, x)", "f(x, y) = 2*x*y"),
+        ("f(x, y) = diff(x^3, x, 2)", "f(x, y) = 6*x"),
+        ("f(x, y) = 1 + diff(sin(y), y)", "f(x, y) = cos(y) + 1"),
+        ("f(x, y) = grad_mag(x + y)", "f(x, y) = 1.4142135"),
+    ];
+
+    for (input, expected) in cases.iter() {
+        let ast = parse(input).simplify();
+        println!("{} => {}", input, ast.readable());
+        assert_eq!(ast.readable(), *expected);
+    }
+
+    let not_differentiable = Lexer::new("f(x, y) = diff(floor(x), x)".into())
+        .tokenize()
+        .map(|toks| Parser::new(toks).parse());
+
+    assert!(matches!(
+        not_differentiable,
+        Ok(Err(ParserError::NotDifferentiable(_)))
+    ));
+
+    // orders past the cap are refused before anything is differentiated
+    let too_high = Lexer::new("f(x, y) = diff(x^2, x, 100000000)".into())
+        .tokenize()
+        .map(|toks| Parser::new(toks).parse());
+
+    assert!(matches!(too_high, Ok(Err(ParserError::SyntaxError(_)))));
+    assert_eq!(
+        parse("f(x, y) = diff(x^2, x, 16)").simplify().readable(),
+        "f(x, y) = 0"
+    );
+}
+
+#[test]
+fn dual_eval() {
+    let ast = parse("f(x, y) = sin(x * y) + x^3 / y + exp(x) * hypot(x, y)");
+    let scope = Scope::new().with("x", 0.7).with("y", -1.3);
+    let vars = ["x", "y"];
+
+    let dual = ast.eval_dual(&scope, &vars, true);
+    let hessian = dual.hessian.unwrap();
+    println!("{:?} {:?} {:?}", dual.value, dual.gradient, hessian);
+
+    let close = |a: f32, b: f32| (a - b).abs() <= 1e-3 * (1. + b.abs());
+    assert!(close(dual.value, ast.eval(&scope)));
+
+    // must agree with the symbolic derivatives
+    for (i, var) in vars.iter().enumerate() {
+        let first = ast.derivative(var).unwrap();
+        assert!(close(dual.gradient[i], first.eval(&scope)));
+
+        for (j, other) in vars.iter().enumerate() {
+            let second = first.derivative(other).unwrap();
+            assert!(close(hessian[i][j], second.eval(&scope)));
+        }
+    }
+
+    // step functions degrade to a 0 derivative instead of failing
+    let stepped = parse("f(x, y) = floor(x) + x").eval_dual(&scope, &vars, false);
+    assert_eq!(stepped.gradient, vec![1., 0.]);
+    assert!(stepped.hessian.is_none());
+}
+
+#[test]
+fn interval_eval() {
+    let scope = Scope::new();
+    let bounds = |src: &str, x: (f32, f32), y: (f32, f32)| {
+        let ranges = [
+            ("x", Interval::new(x.0, x.1)),
+            ("y", Interval::new(y.0, y.1)),
+        ];
+        parse(src).eval_interval(&scope, &ranges)
+    };
+
+    // every sample inside of the rectangle must lie inside of the bounds
+    let src = "f(x, y) = sin(3*x) * y^2 - floor(x) + sqrt(abs(y)) / (2 + cos(x*y))";
+    let range = bounds(src, (-2., 1.5), (-1., 3.));
+    println!("{:?}", range);
+    assert!(!range.undefined);
+
+    let ast = parse(src);
+    for i in 0..=40 {
+        for j in 0..=40 {
+            let x = -2. + 3.5 * i as f32 / 40.;
+            let y = -1. + 4. * j as f32 / 40.;
+            assert!(range.contains(ast.eval(&Scope::new().with("x", x).with("y", y))));
+        }
+    }
+
+    // periodic & even functions keep tight bounds
+    let squared = bounds("f(x, y) = x^2", (-2., 1.), (0., 0.));
+    assert!(squared.lo == 0. && squared.hi >= 4. && squared.hi < 4.001);
+    let wave = bounds("f(x, y) = sin(x)", (0., 4.), (0., 0.));
+    assert!(wave.hi == 1. && wave.lo < 4f32.sin() && wave.lo > -0.76);
+
+    // poles & divisions by 0 are flagged
+    assert!(bounds("f(x, y) = tan(x)", (1., 2.), (0., 0.)).undefined);
+    assert!(bounds("f(x, y) = 1 / (x*y)", (-1., 1.), (1., 2.)).undefined);
+    assert!(bounds("f(x, y) = sqrt(x)", (-1., 1.), (0., 0.)).undefined);
+    assert!(!bounds("f(x, y) = tan(x)", (-1., 1.), (0., 0.)).undefined);
+}
+
+// how far the default axes reach from the origin in world space
+const HALF: (f32, f32) = (0.5, 0.5);
+
+#[test]
+fn fit_view() {
+    let fov = 45f32.to_radians();
+    let axes = Axes::default();
+
+    // tall graphs are squashed into the same box as x & y
+    let tall = parse("f(x, y) = 1000*x*y");
+    let range = z_range(&[(&tall, &Domain::new())], &axes, 8., &Scope::new()).unwrap();
+    assert!((range.0 + 16000.).abs() < 1. && (range.1 - 16000.).abs() < 1.);
+
+    let frame = ViewFrame::fit(range, HALF, &axes, 8., fov, 1.);
+    assert!((frame.amplitude * 16000. / 8. - 0.5).abs() < 1e-4);
+
+    // the bounding sphere of the box fits inside of the fov
+    let radius = (0.75f32).sqrt();
+    assert!((frame.cam_distance * (fov / 2.).sin() - radius).abs() < 1e-4);
+
+    // flat graphs aren't stretched & wide canvases are limited by height
+    let flat = ViewFrame::fit((-0.1, 0.1), HALF, &axes, 8., fov, 2.);
+    assert_eq!(flat.amplitude, 1.);
+    assert!(flat.cam_distance < frame.cam_distance);
+
+    // samples next to poles are ignored
+    let pole = parse("f(x, y) = 1 / x");
+    let range = z_range(&[(&pole, &Domain::new())], &axes, 8., &Scope::new()).unwrap();
+    assert!(range.1 < 100.);
+    let undefined = parse("f(x, y) = sqrt(0 - 1)");
+    assert!(z_range(&[(&undefined, &Domain::new())], &axes, 8., &Scope::new()).is_none());
+}
+
+#[test]
+fn axes() {
+    // default axes follow the zoom, centered on the origin
+    let linear = Axis::default();
+    assert_eq!(linear.plane_to_graph(0.5, 8.), 4.);
+    assert_eq!(linear.graph_to_world(2., 8.), 0.25);
+
+    // ranges & stretches map the ends of the range to the ends of the axis
+    let ranged = Axis::new(Some((10., 20.)), 2., false).unwrap();
+    assert_eq!(ranged.plane_to_graph(-0.5, 8.), 10.);
+    assert_eq!(ranged.graph_to_world(20., 8.), 1.);
+
+    // log axes space out decades evenly
+    let log = Axis::new(Some((1., 1000.)), 1., true).unwrap();
+    assert!((log.plane_to_graph(0., 8.) - 31.622776).abs() < 1e-3);
+    assert!((log.graph_to_world(100., 8.) - 1. / 6.).abs() < 1e-5);
+    assert!(log.graph_to_world(-1., 8.).is_nan());
+
+    assert!(Axis::new(Some((0., 10.)), 1., true).is_err());
+    assert!(Axis::new(Some((5., 1.)), 1., false).is_err());
+    assert!(Axis::new(None, 0., false).is_err());
+
+    // a range with only one end is rejected rather than dropped
+    assert_eq!(Axis::range(Some(1.), Some(2.)), Ok(Some((1., 2.))));
+    assert_eq!(Axis::range(None, None), Ok(None));
+    assert!(Axis::range(Some(1.), None).is_err());
+    assert!(Axis::range(None, Some(2.)).is_err());
+
+    // CPU sampling happens on the axes, exp(x) only spans a few decades
+    // over a log z axis
+    let axes = Axes {
+        x: Axis::new(Some((0., 10.)), 1., false).unwrap(),
+        y: Axis::default(),
+        z: Axis::new(Some((1., 1e5)), 1., true).unwrap(),
+    };
+    let exp = parse("f(x, y) = exp(x)");
+    let range = z_range(&[(&exp, &Domain::new())], &axes, 8., &Scope::new()).unwrap();
+    assert!(range.0 == 1. && (range.1 - 10f32.exp()).abs() < 1.);
+    let fov = 45f32.to_radians();
+    assert_eq!(
+        ViewFrame::fit(range, HALF, &axes, 8., fov, 1.).amplitude,
+        1.
+    );
+
+    // while on a linear z axis it has to be squashed to fit
+    let linear_z = Axes {
+        z: Axis::default(),
+        ..axes
+    };
+    assert!(ViewFrame::fit(range, HALF, &linear_z, 8., fov, 1.).amplitude < 0.001);
+}
+
+#[test]
+fn domain() {
+    let parse_domain = |src: &str| {
+        let toks = Lexer::new(src.into()).tokenize()?;
+        let mut parser = Parser::new(toks);
+        parser.parse()?;
+        Ok::<_, ParserError>(parser.domain().clone())
+    };
+
+    // ranges are read from the end of the equation, bounds can be negative
+    // & constant expressions
+    let domain = parse_domain("f(x, y) = x*y, x in [0, 1], y ∈ [-pi, 2*pi]").unwrap();
+    assert_eq!(domain.get("x"), Some((0., 1.)));
+    assert!((domain.get("y").unwrap().0 + std::f32::consts::PI).abs() < 1e-6);
+
+    assert!(parse_domain("f(x, y) = x, z in [0, 1]").is_err());
+    assert!(parse_domain("f(x, y) = x, x in [1, 0]").is_err());
+    assert!(parse_domain("f(x, y) = x, x in [0, y]").is_err());
+    assert!(parse_domain("f(x, y) = x, x in [0, 1], x in [0, 2]").is_err());
+    assert!(parse_domain("f(x, y) = x, x in [0, 1").is_err());
+
+    // inputs without a range follow the axes, overrides take priority
+    let axes = Axes::default();
+    let mut overrides = Domain::new();
+    overrides.set("y", (5., 6.)).unwrap();
+    let merged = domain.merged(&overrides);
+    assert_eq!(merged.rect(&axes, 8.), ((0., 1.), (5., 6.)));
+    assert_eq!(Domain::new().rect(&axes, 8.), ((-4., 4.), (-4., 4.)));
+
+    // ranges of inputs on log axes have to be positive, others are free
+    let log = Axes {
+        x: Axis::new(Some((1., 100.)), 1., true).unwrap(),
+        ..Axes::default()
+    };
+    assert!(domain.check_log(&log).is_err());
+    assert!(merged.check_log(&axes).is_ok());
+    overrides.set("x", (0.5, 10.)).unwrap();
+    assert!(domain.merged(&overrides).check_log(&log).is_ok());
+
+    // fitting only samples the domain
+    let graph = parse("f(x, y) = x");
+    let range = z_range(&[(&graph, &domain)], &axes, 8., &Scope::new()).unwrap();
+    assert_eq!(range, (0., 1.));
+
+    // planes follow the shape of the domain, snapped to powers of 2
+    assert_eq!(Mesh::plane_resolution(1., 300), (300, 300));
+    assert_eq!(Mesh::plane_resolution(4., 300), (600, 150));
+    assert_eq!(Mesh::plane_resolution(0.3, 300), (150, 600));
+    let (points, indecies) = ShaderGenerator::generate_grid(4, 2);
+    assert_eq!((points.len(), indecies.len()), (15 * 3, 4 * 2 * 6));
+}
+
+#[test]
+fn adaptive_mesh() {
+    let axes = Axes::default();
+    let scope = Scope::new();
+    let options = RefineOptions {
+        min_depth: 2,
+        max_depth: 7,
+        tolerance: 0.001,
+    };
+
+    let mesh = |src: &str| {
+        let equation = parse(src);
+        let surface = Surface {
+            equation: &equation,
+            scope: &scope,
+            axes: &axes,
+            rect: ((-1., 1.), (-1., 1.)),
+        };
+        TriMesh::adaptive_surface(&surface, &options)
+    };
+
+    // planes stay at the minimum depth, sharp peaks are refined
+    let flat = mesh("f(x, y) = 2*x + y/10");
+    let peak = mesh("f(x, y) = exp(0 - 50*(x^2 + y^2))");
+    assert_eq!(flat.indices.len() / 3, 4 * 4 * 2);
+    assert!(peak.indices.len() > 10 * flat.indices.len());
+    assert!(peak.indices.len() / 3 < 128 * 128 * 2);
+
+    // no cracks, edges inside of the square are shared by exactly 2
+    // triangles & every triangle faces up
+    let mut edges = std::collections::HashMap::new();
+    for tri in peak.indices.chunks(3) {
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+
+        let p = |i: u32| {
+            (
+                peak.positions[i as usize * 3],
+                peak.positions[i as usize * 3 + 1],
+            )
+        };
+        let (a, b, c) = (p(tri[0]), p(tri[1]), p(tri[2]));
+        assert!((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0) > 0.);
+    }
+
+    let on_border = |i: u32| {
+        let (x, y) = (
+            peak.positions[i as usize * 3],
+            peak.positions[i as usize * 3 + 1],
+        );
+        x.abs() == 1. || y.abs() == 1.
+    };
+    for ((a, b), count) in edges {
+        let border = on_border(a) && on_border(b);
+        assert!(count == 2 || (border && count == 1));
+    }
+
+    // normals point up & the obj lists every vertex & face
+    assert!(peak.normals.chunks(3).all(|n| n[2] > 0.));
+    let obj = peak.to_obj();
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("v ")).count(),
+        peak.positions.len() / 3
+    );
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("f ")).count(),
+        peak.indices.len() / 3
+    );
+
+    // animated graphs aren't refined, they change every frame
+    for src in ["f(x, y) = sin(x + t)", "f(x, y) = TIME*x", "f(x, y) = sin(x + time())"].iter() {
+        assert!(parse(src).is_animated(), "{}", src);
+    }
+    assert!(!parse("c(t) = (cos t, sin t, t)").is_animated());
+    assert!(!parse("f(x, y) = x*y").is_animated());
+}
+
+#[test]
+fn breaks() {
+    let scope = Scope::new();
+    let jumps = |src: &str, a: (f32, f32), b: (f32, f32)| {
+        let equation = parse(src);
+        spans_break(
+            |x, y| equation.eval(&scope.clone().with("x", x).with("y", y)),
+            a,
+            b,
+            1e-3,
+        )
+    };
+
+    // sign changing poles & jumps are breaks
+    assert!(jumps("f(x, y) = tan(x)", (1.5, 0.), (1.7, 0.)));
+    assert!(jumps("f(x, y) = 1 / (x*y)", (-0.1, 1.), (0.3, 1.)));
+    assert!(jumps("f(x, y) = floor(x)", (0.9, 0.), (1.2, 0.)));
+    assert!(jumps("f(x, y) = 5*sign(x) + x", (-0.5, 0.), (0.2, 0.)));
+
+    // steep surfaces, spikes that keep their sign & undefined points aren't
+    assert!(!jumps("f(x, y) = 1000*x", (0., 0.), (1., 0.)));
+    assert!(!jumps("f(x, y) = tan(x)", (1.4, 0.), (1.5, 0.)));
+    assert!(!jumps("f(x, y) = 1 / x^2", (-0.1, 0.), (0.3, 0.)));
+    assert!(!jumps("f(x, y) = sqrt(x)", (-1., 0.), (1., 0.)));
+
+    // exported meshes don't connect either side of a pole
+    let axes = Axes::default();
+    let equation = parse("f(x, y) = tan(x)");
+    let surface = Surface {
+        equation: &equation,
+        scope: &scope,
+        axes: &axes,
+        rect: ((0., 3.), (0., 1.)),
+    };
+    let options = RefineOptions {
+        min_depth: 3,
+        max_depth: 7,
+        tolerance: 0.01,
+    };
+    let mesh = TriMesh::adaptive_surface(&surface, &options);
+
+    let half_pi = std::f32::consts::FRAC_PI_2;
+    for tri in mesh.indices.chunks(3) {
+        let xs: Vec<f32> = tri
+            .iter()
+            .map(|i| mesh.positions[*i as usize * 3])
+            .collect();
+        let below = xs.iter().any(|x| *x < half_pi);
+        let above = xs.iter().any(|x| *x > half_pi);
+        assert!(!(below && above));
+    }
+    assert!(!mesh.indices.is_empty());
+
+    // neither do the planes the shader displaces
+    let plane = TriMesh::adaptive_plane(&surface, &options);
+    let u_pole = half_pi / 3.;
+    for tri in plane.indices.chunks(3) {
+        let us: Vec<f32> = tri
+            .iter()
+            .map(|i| plane.positions[*i as usize * 3] + 0.5)
+            .collect();
+        assert!(!(us.iter().any(|u| *u < u_pole) && us.iter().any(|u| *u > u_pole)));
+    }
+
+    // the shader only looks for breaks where the CPU finds there may be some
+    let may = |src: &str, rect| may_break(&parse(src), &scope, rect);
+    assert!(may("f(x, y) = tan(x)", ((0., 3.), (0., 1.))));
+    assert!(may("f(x, y) = 1 / x", ((-1., 1.), (-1., 1.))));
+    assert!(may("f(x, y) = floor(x) + y", ((0.2, 0.8), (0., 1.))));
+    assert!(may("f(x, y) = sin(x + t)", ((0., 1.), (0., 1.))));
+    assert!(may("f(x, y) = sin(x + time())", ((0., 1.), (0., 1.))));
+    assert!(!may("f(x, y) = tan(x)", ((0., 1.), (0., 1.))));
+    assert!(!may("f(x, y) = 1 / x", ((1., 2.), (-1., 1.))));
+    assert!(!may("f(x, y) = x^2 + sin(y)", ((-4., 4.), (-4., 4.))));
+}
+
+#[test]
+fn nan_holes() {
+    let axes = Axes::default();
+    let scope = Scope::new();
+    let options = RefineOptions {
+        min_depth: 3,
+        max_depth: 7,
+        tolerance: 0.001,
+    };
+    let equation = parse("f(x, y) = sqrt(1 - x^2 - y^2)");
+    let surface = Surface {
+        equation: &equation,
+        scope: &scope,
+        axes: &axes,
+        rect: ((-2., 2.), (-2., 2.)),
+    };
+    let mesh = TriMesh::adaptive_surface(&surface, &options);
+
+    // nothing is poisoned by NaN, including normals on the rim where the
+    // gradient is infinite
+    assert!(mesh.positions.iter().all(|v| v.is_finite()));
+    assert!(mesh.normals.iter().all(|v| v.is_finite()));
+    assert!(mesh.normals.chunks(3).all(|n| {
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        (len - 1.).abs() < 1e-3
+    }));
+
+    // every drawn vertex is on the hemisphere & the edge reaches the circle
+    let mut rim = 0f32;
+    for tri in mesh.indices.chunks(3) {
+        for &i in tri {
+            let p = &mesh.positions[i as usize * 3..i as usize * 3 + 3];
+            let r = (p[0] * p[0] + p[1] * p[1]).sqrt();
+            assert!(r <= 1.);
+            assert!((r * r + p[2] * p[2] - 1.).abs() < 1e-3);
+            rim = rim.max(r);
+        }
+    }
+    assert!(rim > 0.999);
+    assert!(!mesh.indices.is_empty());
+
+    // vertices outside of the graph move onto its edge
+    let (edge, travel) = find_edge(|x, _| (1. - x * x).sqrt(), (2., 0.), (0., 0.));
+    assert!((edge.0 - 1.).abs() < 0.01 && travel < 0.51);
+}
+
+#[test]
+fn plane_lod() {
+    // a cell every 3 pixels, snapped to powers of 2
+    assert_eq!(Mesh::plane_detail(800., 3., (16, 512)), 256);
+    assert_eq!(Mesh::plane_detail(1600., 3., (16, 512)), 512);
+    assert_eq!(Mesh::plane_detail(200., 3., (16, 512)), 64);
+
+    // zooming far in or out stays within the limits
+    assert_eq!(Mesh::plane_detail(1e6, 3., (16, 512)), 512);
+    assert_eq!(Mesh::plane_detail(1., 3., (16, 512)), 16);
+    assert_eq!(Mesh::plane_detail(f32::NAN, 3., (16, 512)), 16);
+
+    // planes reaching behind the camera fill the screen
+    assert_eq!(Mesh::plane_detail(f32::INFINITY, 3., (16, 512)), 512);
+}
+
+#[test]
+fn implicit() {
+    // equations without a head are F(x, y, z) = lhs - rhs
+    let sphere = parse("x^2 + y^2 + z^2 = 1");
+    assert!(sphere.is_implicit());
+    assert_eq!(sphere.simplify().readable(), "x*x + y*y + z*z - 1 = 0");
+
+    let on = Scope::new().with("x", 0.6).with("y", 0.).with("z", 0.8);
+    let inside = Scope::new().with("x", 0.).with("y", 0.).with("z", 0.);
+    assert!(sphere.eval(&on).abs() < 1e-6);
+    assert!(sphere.eval(&inside) < 0.);
+
+    // heads are still explicit, unless they are calls to native functions
+    assert!(!parse("f(x, y) = x*y").is_implicit());
+    assert!(parse("sin(x) = y").is_implicit());
+    assert!(parse("z = x^2").is_implicit());
+
+    // the box can be given like any other domain
+    let toks = Lexer::new("x^2 + z^2 = 1, z in [0, 2]".into())
+        .tokenize()
+        .unwrap();
+    let mut parser = Parser::new(toks);
+    parser.parse().unwrap();
+    let bounds = parser.domain().bounds(&Axes::default(), 8.);
+    assert_eq!(bounds, [(-4., 4.), (-4., 4.), (0., 2.)]);
+
+    let toks = Lexer::new("x^2 + y^2 1".into()).tokenize().unwrap();
+    assert!(Parser::new(toks).parse().is_err());
+}
+
+#[test]
+fn polygonize() {
+    let sphere = parse("x^2 + y^2 + z^2 = 1");
+    let bounds = [(-1.5, 1.5), (-1.2, 1.3), (-1.5, 1.4)];
+    let mesh = TriMesh::implicit_surface(&sphere, &Scope::new(), bounds, 24);
+    assert!(!mesh.indices.is_empty());
+    assert_eq!(mesh.positions.len(), mesh.normals.len());
+
+    // vertices lie on the sphere with normals pointing out of it
+    for (p, n) in mesh.positions.chunks(3).zip(mesh.normals.chunks(3)) {
+        let r = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        assert!((r - 1.).abs() < 0.02);
+        assert!((p[0] * n[0] + p[1] * n[1] + p[2] * n[2]) / r > 0.99);
+    }
+
+    // closed & consistently wound, every edge is walked once in each
+    // direction, and every triangle faces out
+    let mut edges = std::collections::HashMap::new();
+    for tri in mesh.indices.chunks(3) {
+        for k in 0..3 {
+            *edges.entry((tri[k], tri[(k + 1) % 3])).or_insert(0) += 1;
+        }
+
+        let p = |i: u32| &mesh.positions[i as usize * 3..i as usize * 3 + 3];
+        let (a, b, c) = (p(tri[0]), p(tri[1]), p(tri[2]));
+        let (u, v) = (
+            [b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+            [c[0] - a[0], c[1] - a[1], c[2] - a[2]],
+        );
+        let normal = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        assert!(normal[0] * a[0] + normal[1] * a[1] + normal[2] * a[2] >= 0.);
+    }
+    assert!(edges
+        .iter()
+        .all(|(&(a, b), &count)| count == 1 && edges.get(&(b, a)) == Some(&1)));
+
+    // cells with undefined corners are left out
+    let (points, indecies) = crate::mesh::polygonize::polygonize(
+        |x, y, z| {
+            if x < 0.2 {
+                f32::NAN
+            } else {
+                x * x + y * y + z * z - 0.25
+            }
+        },
+        [(-1., 1.); 3],
+        8,
+    );
+    let p = |i: u32| points[i as usize * 3];
+    assert!(!indecies.is_empty() && indecies.iter().all(|&i| p(i) >= 0.));
+}
+
+#[test]
+fn parametric() {
+    let sphere = parse("p(u, v) = (cos(u)*sin(v), sin(u)*sin(v), cos(v)), v in [0, pi]");
+    assert!(sphere.is_parametric() && !parse("f(x, y) = (x)").is_parametric());
+    assert_eq!(
+        sphere.simplify().readable(),
+        "p(u, v) = (cos(u)*sin(v), sin(u)*sin(v), cos(v))"
+    );
+    assert!(sphere.to_string().contains("vec3("));
+
+    // vectors are evaluated & differentiated component by component
+    let scope = Scope::new()
+        .with("u", std::f32::consts::FRAC_PI_2)
+        .with("v", std::f32::consts::FRAC_PI_2);
+    let components = |node: &ParseNode| match node {
+        ParseNode::FunctionDefine(_, _, body) => match &**body {
+            ParseNode::Tuple(items) => items.iter().map(|item| item.eval(&scope)).collect(),
+            _ => vec![],
+        },
+        _ => vec![],
+    };
+    assert!(sphere.eval(&scope).is_nan());
+
+    let point: Vec<f32> = components(&sphere);
+    assert!(point[0].abs() < 1e-6 && (point[1] - 1.).abs() < 1e-6 && point[2].abs() < 1e-6);
+
+    let du: Vec<f32> = components(&sphere.derivative("u").unwrap());
+    assert!((du[0] + 1.).abs() < 1e-6 && du[1].abs() < 1e-6 && du[2].abs() < 1e-6);
+
+    // every point of the mesh is on the sphere with a normal through its center
+    let params = ["u".to_string(), "v".to_string()];
+    let ranges = [(0., std::f32::consts::TAU), (0., std::f32::consts::PI)];
+    let mesh = TriMesh::parametric_surface(&sphere, &Scope::new(), &params, ranges, 16);
+    assert_eq!(mesh.indices.len(), 16 * 16 * 6);
+
+    for (p, n) in mesh.positions.chunks(3).zip(mesh.normals.chunks(3)) {
+        let r = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        assert!((r - 1.).abs() < 1e-4);
+        assert!((p[0] * n[0] + p[1] * n[1] + p[2] * n[2]).abs() > 0.99);
+    }
+}
+
+#[test]
+fn curve() {
+    let helix = parse("c(t) = (cos t, sin t, t/4)");
+    assert!(helix.is_parametric());
+    assert_eq!(
+        helix.simplify().readable(),
+        "c(t) = (cos(t), sin(t), 0.25*t)"
+    );
+
+    // single argument functions don't need parentheses, their argument ends
+    // at the next * or /
+    let scope = Scope::new().with("x", 2.);
+    assert_eq!(parse("f(x) = sin x * 2").eval(&scope), 2. * 2f32.sin());
+    assert_eq!(parse("f(x) = cos -x^2").eval(&scope), (-4f32).cos());
+    assert_eq!(parse("f(x) = sqrt abs x").eval(&scope), 2f32.sqrt());
+    assert_eq!(parse("f(x) = x * sin x").readable(), parse("f(x) = x*sin(x)").readable());
+
+    // vectors with a single input are curves
+    assert_eq!(GraphKind::of(&helix), GraphKind::Curve);
+    assert_eq!(
+        GraphKind::of(&parse("p(u, v) = (u, v, 0)")),
+        GraphKind::Parametric
+    );
+
+    // every ring is a circle of the radius around the helix, with normals
+    // pointing out of the tube across the direction of the helix
+    let (samples, sides) = (32, 8);
+    let range = (0., std::f32::consts::TAU);
+    let mesh = TriMesh::curve_tube(&helix, &Scope::new(), "t", range, (samples, sides), 0.1);
+    assert_eq!(mesh.indices.len(), samples * sides * 6);
+
+    let points = mesh.positions.chunks(3).zip(mesh.normals.chunks(3));
+    for (i, (p, n)) in points.enumerate() {
+        let t = range.1 * (i % (samples + 1)) as f32 / samples as f32;
+        let center = [t.cos(), t.sin(), t / 4.];
+        let forward = [-t.sin(), t.cos(), 0.25];
+
+        for axis in 0..3 {
+            assert!((p[axis] - center[axis] - n[axis] * 0.1).abs() < 1e-5);
+        }
+
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        let across = n[0] * forward[0] + n[1] * forward[1] + n[2] * forward[2];
+        assert!((length - 1.).abs() < 1e-5 && across.abs() < 1e-5);
+    }
+
+    // rings don't twist where the curve turns upright
+    let steep = parse("s(t) = (t, 0, t^3)");
+    let mesh = TriMesh::curve_tube(&steep, &Scope::new(), "t", (-1., 1.), (samples, sides), 0.1);
+    let sides: Vec<&[f32]> = mesh.normals.chunks(3).take(samples + 1).collect();
+    for pair in sides.windows(2) {
+        let turn = pair[0][0] * pair[1][0] + pair[0][1] * pair[1][1] + pair[0][2] * pair[1][2];
+        assert!(turn > 0.95, "{:?} turns to {:?}", pair[0], pair[1]);
+    }
+}
+
+#[test]
+fn coordinate_modes() {
+    // modes come from the names of the inputs, or a rho head
+    let cone = parse("f(r, θ) = r");
+    assert_eq!(cone, parse("f(r, theta) = r"));
+    assert_eq!(GraphKind::of(&cone), GraphKind::Cylindrical);
+    assert_eq!(GraphKind::of(&parse("g(θ, φ) = 1")), GraphKind::Spherical);
+    assert_eq!(GraphKind::of(&parse("rho(a, b) = 1")), GraphKind::Spherical);
+    assert_eq!(GraphKind::of(&parse("f(x, y) = x")), GraphKind::Explicit);
+
+    // poles are a single vertex, without triangles that have no area
+    let (rings, segments) = (8, 16);
+    let (disk, disk_indices) = ShaderGenerator::generate_polar(rings, segments, (true, false));
+    let (sphere, sphere_indices) = ShaderGenerator::generate_polar(rings, segments, (true, true));
+    assert_eq!(disk.len() / 3, 1 + rings * (segments + 1));
+    assert_eq!(sphere.len() / 3, 2 + (rings - 1) * (segments + 1));
+    assert_eq!(disk_indices.len(), (2 * rings - 1) * segments * 3);
+    assert_eq!(sphere_indices.len(), (2 * rings - 2) * segments * 3);
+
+    // rewritten as parametric surfaces, the radius (or angle from the pole)
+    // comes first
+    let cone = GraphKind::Cylindrical.to_parametric(&cone);
+    assert_eq!(Parser::get_function_args(&cone), vec!["r", "theta"]);
+
+    let ranges = [(0., 1.), (0., std::f32::consts::TAU)];
+    let params = ["r".to_string(), "theta".to_string()];
+    let mesh = TriMesh::parametric_surface(&cone, &Scope::new(), &params, ranges, 16);
+    for p in mesh.positions.chunks(3) {
+        assert!((p[2] - (p[0] * p[0] + p[1] * p[1]).sqrt()).abs() < 1e-5);
+    }
+
+    // normals of spheres point outwards, even at the poles
+    let sphere = GraphKind::Spherical.to_parametric(&parse("rho(theta, phi) = 2"));
+    assert_eq!(Parser::get_function_args(&sphere), vec!["phi", "theta"]);
+
+    let ranges = [(0., std::f32::consts::PI), (0., std::f32::consts::TAU)];
+    let params = ["phi".to_string(), "theta".to_string()];
+    let mesh = TriMesh::parametric_surface(&sphere, &Scope::new(), &params, ranges, 16);
+    for (p, n) in mesh.positions.chunks(3).zip(mesh.normals.chunks(3)) {
+        let r = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        assert!((r - 2.).abs() < 1e-4);
+        assert!((p[0] * n[0] + p[1] * n[1] + p[2] * n[2]) / r > 0.99);
+    }
+}
+
+#[test]
+fn oriented_surfaces() {
+    // the head names the axis of the value, the other 2 axes are inputs
+    let sideways = parse("x(y, z) = y^2 + z, y in [0, 1]");
+    assert_eq!(orientation_of(&sideways), [1, 2, 0]);
+    assert_eq!(orientation_of(&parse("y(x, z) = x")), [0, 2, 1]);
+    assert_eq!(orientation_of(&parse("f(x, y) = x")), UPRIGHT);
+    assert_eq!(orientation_of(&parse("x(y, y) = y")), UPRIGHT);
+
+    // drawn in its own frame with the inputs along x & y, so the domain &
+    // axes are renamed the same way
+    let orientation = orientation_of(&sideways);
+    let local = GraphEquation::drawn(&sideways, GraphKind::Explicit, orientation);
+    let scope = Scope::new().with("x", 2.).with("y", 3.);
+    assert_eq!(local.eval(&scope), 7.);
+
+    let mut domain = Domain::new();
+    domain.set("y", (0., 1.)).unwrap();
+    assert_eq!(domain.oriented(orientation).get("x"), Some((0., 1.)));
+
+    let mut axes = Axes::default();
+    axes.y.stretch = 2.;
+    assert_eq!(axes.oriented(orientation).x.stretch, 2.);
+
+    // mirrored frames are rewound, so triangles face the same way relative
+    // to their normals as upright surfaces
+    let options = RefineOptions {
+        min_depth: 2,
+        max_depth: 5,
+        tolerance: 0.001,
+    };
+
+    let sources = [
+        "z(x, y) = x^2 + y",
+        "x(y, z) = y^2 + z",
+        "y(x, z) = x^2 + z",
+    ];
+    for src in sources.iter() {
+        let equation = parse(src);
+        let orientation = orientation_of(&equation);
+        let local = GraphEquation::drawn(&equation, GraphKind::Explicit, orientation);
+        let surface = Surface {
+            equation: &local,
+            scope: &Scope::new(),
+            axes: &Axes::default(),
+            rect: ((-1., 1.), (-1., 1.)),
+        };
+        let mesh = TriMesh::adaptive_surface(&surface, &options).oriented(orientation);
+        let point = |i: u32| &mesh.positions[i as usize * 3..i as usize * 3 + 3];
+
+        for tri in mesh.indices.chunks(3) {
+            let (a, b, c) = (point(tri[0]), point(tri[1]), point(tri[2]));
+            let [u, v, value] = orientation.map(|axis| a[axis]);
+            assert!((value - u * u - v).abs() < 1e-4);
+
+            // the normal points along the value's axis
+            let n = &mesh.normals[tri[0] as usize * 3..tri[0] as usize * 3 + 3];
+            assert!(n[orientation[2]] > 0.);
+
+            let (e1, e2) = (
+                [b[0] - a[0], b[1] - a[1], b[2] - a[2]],
+                [c[0] - a[0], c[1] - a[1], c[2] - a[2]],
+            );
+            let facing = [
+                e1[1] * e2[2] - e1[2] * e2[1],
+                e1[2] * e2[0] - e1[0] * e2[2],
+                e1[0] * e2[1] - e1[1] * e2[0],
+            ];
+            assert!(facing[0] * n[0] + facing[1] * n[1] + facing[2] * n[2] > 0.);
+        }
+    }
+}
+
+#[test]
+fn revolution() {
+    let revolve = |src: &str| {
+        let toks = Lexer::new(src.into()).tokenize().unwrap();
+        let mut parser = Parser::new(toks);
+        parser.parse().map(|ast| (ast, parser.revolution()))
+    };
+
+    // the sweep angle is an input of the domain, but only when revolving
+    let (bowl, axis) = revolve("g(x) = sqrt(x), around y, theta in [0, pi]").unwrap();
+    assert_eq!(axis, Some(1));
+    assert_eq!(GraphKind::of(&bowl), GraphKind::Explicit);
+    assert!(revolve("g(x) = x, around x, around y").is_err());
+    assert!(revolve("f(x, y) = x, around x").is_err());
+    assert!(revolve("g(x) = x, around w").is_err());
+    assert!(revolve("g(x) = x, theta in [0, pi]").is_err());
+
+    // a cone revolved around each axis, x is the distance from the axis
+    // unless revolving around x, where the profile is
+    let profile = parse("g(x) = x");
+    for axis in 0..3 {
+        let surface = GraphKind::Revolution(axis).to_parametric(&profile);
+        let params = Parser::get_function_args(&surface);
+        let ranges = params
+            .iter()
+            .map(|param| match param.as_str() {
+                "theta" => (0., std::f32::consts::TAU),
+                _ => (0.5, 2.),
+            })
+            .collect::<Vec<_>>();
+        let ranges = [ranges[0], ranges[1]];
+        let mesh = TriMesh::parametric_surface(&surface, &Scope::new(), &params, ranges, 16);
+
+        for (p, n) in mesh.positions.chunks(3).zip(mesh.normals.chunks(3)) {
+            let (along, radial) = match axis {
+                0 => (p[0], [0., p[1], p[2]]),
+                1 => (p[1], [p[0], 0., p[2]]),
+                _ => (p[2], [p[0], p[1], 0.]),
+            };
+            let radius =
+                (radial[0] * radial[0] + radial[1] * radial[1] + radial[2] * radial[2]).sqrt();
+            assert!((radius - along).abs() < 1e-4);
+
+            // normals point away from the axis
+            let outwards = radial[0] * n[0] + radial[1] * n[1] + radial[2] * n[2];
+            assert!(outwards / radius > 0.5);
+        }
+    }
+}
+
+#[test]
+fn regions() {
+    // the bounded axis is in the middle, or on either side of one comparison
+    let bowl = parse("x^2 + y^2 <= z <= 4");
+    assert_eq!(GraphKind::of(&bowl), GraphKind::Region);
+    assert_eq!(parse("4 >= z > x^2 + y^2"), bowl);
+    assert_eq!(bowl.simplify().readable(), "x*x + y*y <= z <= 4");
+    assert_eq!(parse("z ≤ 1 - x").readable(), "z <= 1 - x");
+    assert_eq!(parse("x - 1 < z").readable(), "x - 1 <= z");
+    assert_eq!(orientation_of(&parse("y^2 + z^2 <= x <= 4")), [1, 2, 0]);
+
+    let invalid = ["x < y > z", "1 <= 2", "z <= z^2", "0 <= z <= 1 <= 2"];
+    for src in invalid.iter() {
+        let toks = Lexer::new(src.to_string()).tokenize().unwrap();
+        assert!(Parser::new(toks).parse().is_err(), "{}", src);
+    }
+
+    // a paraboloid capped at 4 holds 8π, whichever axis it is along
+    let scope = Scope::new();
+    for src in ["x^2 + y^2 <= z <= 4", "y^2 + z^2 <= x <= 4"].iter() {
+        let ast = parse(src);
+        let drawn = GraphEquation::drawn(&ast, GraphKind::Region, orientation_of(&ast));
+        let region = Region::new(&drawn, &scope, (-10., 10.)).unwrap();
+        let volume = region.volume(((-2., 2.), (-2., 2.)), 256);
+        assert!((volume - 8. * std::f32::consts::PI).abs() < 0.01);
+    }
+
+    // one sided regions stop at the end of the range
+    let slab = parse("z >= 1 - x");
+    let region = Region::new(&slab, &scope, (0., 2.)).unwrap();
+    assert!((region.volume(((0., 1.), (0., 1.)), 64) - 1.5).abs() < 1e-4);
+
+    // the mesh is closed & faces outwards, so the volume it encloses (by the
+    // divergence theorem) matches
+    let cup = parse("x^2 + y^2 <= z <= 6");
+    let region = Region::new(&cup, &scope, (-10., 10.)).unwrap();
+    let rect = ((-2., 2.), (-2., 2.));
+    let mesh = TriMesh::region_solid(&region, rect, 64);
+    let point = |i: u32| &mesh.positions[i as usize * 3..i as usize * 3 + 3];
+
+    let enclosed: f32 = mesh
+        .indices
+        .chunks(3)
+        .map(|tri| {
+            let (a, b, c) = (point(tri[0]), point(tri[1]), point(tri[2]));
+            let cross = [
+                b[1] * c[2] - b[2] * c[1],
+                b[2] * c[0] - b[0] * c[2],
+                b[0] * c[1] - b[1] * c[0],
+            ];
+            (a[0] * cross[0] + a[1] * cross[1] + a[2] * cross[2]) / 6.
+        })
+        .sum();
+
+    let volume = region.volume(rect, 256);
+    assert!((enclosed - volume).abs() / volume < 0.01);
+}
+
+#[test]
+fn vector_fields() {
+    // vectors of x, y & z (in that order) are fields
+    let swirl = parse("F(x, y, z) = (-y, x, z/5)");
+    assert_eq!(GraphKind::of(&swirl), GraphKind::Field);
+    assert!(swirl.is_field() && !swirl.is_parametric());
+    assert!(!parse("F(x, y, t) = (x, y, t)").is_field());
+    assert!(!parse("F(y, x, z) = (x, y, z)").is_field());
+    assert!(!parse("f(x, y, z) = x").is_field());
+
+    let scope = Scope::new();
+    let field = Field::new(&swirl, &scope).unwrap();
+    assert_eq!(field.at([1., 2., 5.]), [-2., 1., 1.]);
+    assert!(Field::new(&parse("c(t) = (t, t, t)"), &scope).is_none());
+
+    // the lattice is centered in its cells
+    let bounds = [(-2., 2.), (-2., 2.), (0., 4.)];
+    let lattice = Field::lattice(bounds, &Axes::default(), 4);
+    assert_eq!(lattice.len(), 64);
+    assert_eq!(lattice[0], [-1.5, -1.5, 0.5]);
+    assert_eq!(lattice[63], [1.5, 1.5, 3.5]);
+
+    // streamlines of a rotation stay on their circle, running with the field
+    let rotation = parse("F(x, y, z) = (-y, x, 0)");
+    let field = Field::new(&rotation, &scope).unwrap();
+    let line = field.streamline([1., 0., 0.], 0.05, 100, bounds);
+    assert_eq!(line.len(), 201);
+    for p in line.iter() {
+        assert!((magnitude(*p) - 1.).abs() < 1e-4);
+        assert_eq!(p[2], 0.);
+    }
+    assert!(line[101][1] > 0. && line[99][1] < 0.);
+
+    // they stop where they leave the box or the field vanishes
+    let outward = parse("F(x, y, z) = (x, y, 0)");
+    let field = Field::new(&outward, &scope).unwrap();
+    let line = field.streamline([0.5, 0., 1.], 0.1, 100, bounds);
+    assert!(line.iter().all(|p| p[0] > 0. && p[0] <= 2.));
+    assert!(line.contains(&[0.5, 0., 1.]));
+    assert!(line.first().unwrap()[0] < 0.2);
+    assert!(line.last().unwrap()[0] > 1.9);
+    assert!(field.streamline([3., 0., 0.], 0.1, 100, bounds).is_empty());
+
+    // glyphs have unit normals & only reference their own vertices
+    for (verticies, indecies) in [
+        ShaderGenerator::generate_arrow(8),
+        ShaderGenerator::generate_tube(8),
+    ]
+    .iter()
+    {
+        let count = verticies.len() / 6;
+        assert!(indecies.iter().all(|&i| (i as usize) < count));
+        for vertex in verticies.chunks(6) {
+            let normal = [vertex[3], vertex[4], vertex[5]];
+            assert!((magnitude(normal) - 1.).abs() < 1e-5);
+            assert!(vertex[2] >= 0. && vertex[2] <= 1.);
+        }
+    }
+}
+
+#[test]
+fn primitives() {
+    // names given to points, vectors & planes have no inputs
+    let point = parse("P = (1, 2, 3)");
+    assert_eq!(point.readable(), "P = (1, 2, 3)");
+    assert_eq!(
+        Primitive::resolve(&point, &[]),
+        Ok(Some(Primitive::Point([1., 2., 3.])))
+    );
+
+    // names set to anything but a point, vector or plane & equations using
+    // the axes are implicit surfaces
+    for src in ["a = 2", "a = b*c", "Q = P + R", "x^2 + y^2 + z^2 = 1"].iter() {
+        assert!(matches!(parse(src), ParseNode::FunctionDefine(_, args, _) if args.len() == 3));
+    }
+
+    let vector = parse("v = vector((0, 0, 0), (1, 1, 0))");
+    let v = Primitive::Vector {
+        tail: [0.; 3],
+        head: [1., 1., 0.],
+    };
+    assert_eq!(Primitive::resolve(&vector, &[]), Ok(Some(v)));
+
+    // lines move a point along a vector, using the names above them
+    let defined = vec![
+        ("P".to_string(), Primitive::Point([1., 2., 3.])),
+        ("v".to_string(), v),
+    ];
+    assert_eq!(
+        Primitive::resolve(&parse("L(t) = P + t*v"), &defined),
+        Ok(Some(Primitive::Line {
+            point: [1., 2., 3.],
+            direction: [1., 1., 0.],
+        }))
+    );
+    assert!(Primitive::resolve(&parse("L(t) = P + t*t*v"), &defined).is_err());
+    assert!(Primitive::resolve(&parse("Q = vector(P, R)"), &defined).is_err());
+    for src in ["c(t) = (t, t^2, 0)", "f(x, y) = x*y"].iter() {
+        assert_eq!(Primitive::resolve(&parse(src), &defined), Ok(None));
+    }
+
+    let plane = parse("N = plane((0, 0, 1), P)");
+    assert_eq!(
+        Primitive::resolve(&plane, &defined),
+        Ok(Some(Primitive::Plane {
+            normal: [0., 0., 1.],
+            point: [1., 2., 3.],
+        }))
+    );
+    assert!(Primitive::resolve(&parse("N = plane((0, 0, 0), P)"), &defined).is_err());
+
+    // coordinates have to be finite
+    for src in [
+        "P = (1/0, 0, 0)",
+        "v = vector((0, 0, 0), (0, -1/0, 0))",
+        "N = plane((1/0, 0, 0), (0, 0, 0))",
+        "L(t) = (0/0, 0, 0) + t*v",
+    ]
+    .iter()
+    {
+        assert!(Primitive::resolve(&parse(src), &defined).is_err(), "{}", src);
+    }
+
+    // lines & planes are clipped to the box they are drawn in
+    let bounds = [(-2., 2.), (-2., 2.), (-2., 2.)];
+    let (start, end) = Primitive::clip_line([0.; 3], [1., 1., 0.], bounds).unwrap();
+    assert_eq!((start, end), ([-2., -2., 0.], [2., 2., 0.]));
+    assert!(Primitive::clip_line([0., 0., 3.], [1., 0., 0.], bounds).is_none());
+
+    let corners = Primitive::clip_plane([0., 0., 1.], [0.; 3], bounds);
+    assert_eq!(corners.len(), 4);
+    for pair in corners.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        assert_eq!(a[2], 0.);
+        assert!(a[0] * b[1] - a[1] * b[0] > 0.);
+    }
+    assert!(Primitive::clip_plane([0., 0., 1.], [0., 0., 3.], bounds).is_empty());
+    // undefined corners are sorted without panicking
+    Primitive::clip_plane([f32::INFINITY, 0., 1.], [0.; 3], bounds);
+}
+
+#[test]
+fn data() {
+    let point = |x: f32, y: f32, z: f32, size: f32| DataPoint {
+        position: [x, y, z],
+        size,
+    };
+
+    // csv columns are found by the header, or taken in order without one
+    let csv = Dataset::parse("# trial 1\nz, x, y, size\n1, 2, 3, 2\n\n4, 5, 6, 1.5\n").unwrap();
+    assert_eq!(
+        csv.points,
+        vec![point(2., 3., 1., 2.), point(5., 6., 4., 1.5)]
+    );
+    let csv = Dataset::parse("1\t2\t3\n-1\t-2\t-3").unwrap();
+    assert_eq!(
+        csv.points,
+        vec![point(1., 2., 3., 1.), point(-1., -2., -3., 1.)]
+    );
+
+    // the first row picks a single delimiter, spaces may line columns up
+    assert_eq!(
+        Dataset::parse("x; y; z\n1,5; 2; 3"),
+        Err("Line 2: 1,5 isn't a number".into())
+    );
+    let csv = Dataset::parse("1  2   3\n-1 -2 -3").unwrap();
+    assert_eq!(
+        csv.points,
+        vec![point(1., 2., 3., 1.), point(-1., -2., -3., 1.)]
+    );
+
+    // empty cells keep the ones after them in their column
+    assert_eq!(
+        Dataset::parse("1,2,3\n1,2,,3"),
+        Err("Line 2: missing value".into())
+    );
+    assert_eq!(
+        Dataset::parse("x,y,z\n,2,3"),
+        Err("Line 2: missing value".into())
+    );
+
+    assert_eq!(
+        Dataset::parse("1, 2\n"),
+        Err("Line 1 needs 3 numbers (x, y, z)".into())
+    );
+    assert_eq!(
+        Dataset::parse("1, 2, 3\n4, a, 6"),
+        Err("Line 2: a isn't a number".into())
+    );
+    assert!(Dataset::parse("a, b, c\n1, 2, 3").is_err());
+    assert!(Dataset::parse("x, y, z\n").is_err());
+
+    // json is an array of arrays or objects, optionally under "points"
+    let json = Dataset::parse("[[1, 2, 3], [4, 5, 6, 2]]").unwrap();
+    assert_eq!(
+        json.points,
+        vec![point(1., 2., 3., 1.), point(4., 5., 6., 2.)]
+    );
+    let json = r#"{"name": "trial \"2\"", "points": [{"x": 1, "y": -2.5e1, "z": 3}]}"#;
+    assert_eq!(
+        Dataset::parse(json).unwrap().points,
+        vec![point(1., -25., 3., 1.)]
+    );
+
+    assert!(Dataset::parse("[[1, 2, 3], [4, 5]]").is_err());
+    assert!(Dataset::parse("[[1, 2, 3]").is_err());
+    assert!(Dataset::parse("[[1, 2, 3]] x").is_err());
+    assert!(Dataset::parse("[]").is_err());
+
+    // escapes are decoded & nesting is capped
+    let json = r#"{"na\"me\u00e9\\": [], "points": [[1, 2, 3]], "\ud83d\ude00": null}"#;
+    assert_eq!(Dataset::parse(json).unwrap().points, vec![point(1., 2., 3., 1.)]);
+    assert!(Dataset::parse(r#"[[1, 2, 3], "\x"]"#).is_err());
+    let deep = format!("[{}[1, 2, 3]{}]", "[".repeat(100_000), "]".repeat(100_000));
+    assert!(Dataset::parse(&deep).is_err());
+
+    // the closest point to the camera under the cursor is picked
+    let data = Dataset::parse("0, 0, 5\n0, 0, 1\n10, 0, 0").unwrap();
+    let project = |p: &DataPoint| Some(([p.position[0], p.position[1]], 2., p.position[2]));
+    assert_eq!(data.pick([1., 1.], project), Some((1, 1.)));
+    assert_eq!(data.pick([11., 0.], project), Some((2, 0.)));
+    assert_eq!(data.pick([5., 0.], project), None);
+
+    // points are transformed like in the shaders
+    let mut view = Mat4::new();
+    view.look_at(
+        Vec3::new(0., 0., -8.),
+        Vec3::new(0., 0., 0.),
+        Vec3::new(0., 1., 0.),
+    );
+    assert_eq!(view.transform([1., 2., 3., 1.]), [-1., 2., -11., 1.]);
+}
+
+#[test]
+fn fitting() {
+    let quadratic = parse("f(x, y) = a*x^2 + b*y + c + pi*0");
+    assert_eq!(free_params(&quadratic), vec!["a", "b", "c"]);
+
+    // points on the surface give back its parameters exactly
+    let mut points = vec![];
+    for i in 0..5 {
+        for j in 0..5 {
+            let (x, y) = (i as f32 - 2., j as f32 - 2.);
+            points.push([x, y, 0.5 * x * x - 2. * y + 3.]);
+        }
+    }
+
+    let fit = Fit::new(&quadratic, &points, &[]).unwrap();
+    for ((name, val), expected) in fit.params.iter().zip([0.5, -2., 3.].iter()) {
+        assert!((val - expected).abs() < 1e-3, "{} = {}", name, val);
+    }
+    assert_eq!(fit.residuals.len(), 25);
+    assert!(fit.residuals.iter().all(|r| r.abs() < 1e-3));
+    assert!((fit.r_squared - 1.).abs() < 1e-5);
+
+    // nonlinear parameters, with noise lowering r2
+    let growth = parse("f(x, y) = a*exp(b*x)");
+    let points: Vec<[f32; 3]> = (0..20)
+        .map(|i| {
+            let x = i as f32 / 10.;
+            let noise = if i % 2 == 0 { 0.05 } else { -0.05 };
+            [x, 0., 2. * (0.7 * x).exp() + noise]
+        })
+        .collect();
+    let fit = Fit::new(&growth, &points, &[("b".into(), 0.1)]).unwrap();
+    assert!((fit.params[0].1 - 2.).abs() < 0.05);
+    assert!((fit.params[1].1 - 0.7).abs() < 0.05);
+    assert!(fit.r_squared < 1. && fit.r_squared > 0.99);
+
+    // the head picks the axis values are measured along
+    let sideways = parse("y(x, z) = m*x + k");
+    let points = [[0., 1., 0.], [1., 3., 5.], [2., 5., -1.]];
+    let fit = Fit::new(&sideways, &points, &[]).unwrap();
+    assert!((fit.params[0].1 - 2.).abs() < 1e-3 && (fit.params[1].1 - 1.).abs() < 1e-3);
+
+    assert!(Fit::new(&parse("f(x, y) = x*y"), &points, &[]).is_err());
+    assert!(Fit::new(&parse("c(t) = (a*t, t, t)"), &points, &[]).is_err());
+    assert!(Fit::new(&parse("f(x, y) = a*x + b*y + c + d"), &points, &[]).is_err());
+
+    // fitted values are filled into the equation
+    let params = vec![("a".to_string(), 0.5), ("b".to_string(), -2.)];
+    let filled = parse("f(x, y) = a*x + b").substitute(&params);
+    assert_eq!(filled.readable(), parse("f(x, y) = 0.5*x + -2").readable());
+
+    // names marked for fitting are drawn at the value fitting starts from
+    // until they are fitted, other free names aren't defined
+    let marked = ["a".to_string(), "b".to_string()];
+    let values = drawn_values(&marked, &[]);
+    let before = GraphEquation::generate("f(x, y) = a*x + b".into(), 0, 0, None, &[], &values);
+    let (equation, vert, _) = before.unwrap();
+    assert_eq!(equation.simplified, "f(x, y) = x + 1");
+    assert!(free_params(&equation.ast).is_empty());
+    assert!(!vert.contains("$CURRENT_FUNCTION$"));
+
+    let values = drawn_values(&marked, &[("a".to_string(), 2.)]);
+    let after = GraphEquation::generate("f(x, y) = a*x + b".into(), 0, 0, None, &[], &values);
+    assert_eq!(after.unwrap().0.simplified, "f(x, y) = 2*x + 1");
+
+    assert_eq!(
+        fill_params(&parse("f(x, y) = x + yy"), &[]),
+        Err("yy isn't defined".into())
+    );
+    assert_eq!(
+        fill_params(&parse("f(x, y) = sin(z)"), &values),
+        Err("z isn't defined".into())
+    );
+}